@@ -0,0 +1,294 @@
+use specs::prelude::*;
+use crate::{ActionQueue, QueuedAction, RunState, Pools, HungerClock, HungerState, InBackpack, UseTurns, WantsToUseItem,
+    TookAction, ActionType, Equippable, ProvidesFood, Map, TileType, gamelog::GameLog};
+use crate::player::{skip_turn, visible_hostiles};
+
+/// Hit points, hostiles in view and hunger, as compared from one turn to the next.
+fn player_state(ecs: &World) -> (i32, i32, Option<HungerState>) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let hit_points = ecs.read_storage::<Pools>().get(player_entity).map_or(0, |pools| pools.hit_points.current);
+    let hunger = ecs.read_storage::<HungerClock>().get(player_entity).map(|clock| clock.state);
+    (hit_points, visible_hostiles(ecs), hunger)
+}
+
+fn log(ecs: &World, message: String) {
+    ecs.write_resource::<GameLog>().entries.push(message);
+}
+
+/// Adds an action to the end of the player's queue, starting one if there isn't one already.
+pub fn queue_action(ecs: &mut World, action: QueuedAction) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let (hit_points, hostiles_in_view, hunger) = player_state(ecs);
+    let mut queues = ecs.write_storage::<ActionQueue>();
+    if let Some(queue) = queues.get_mut(player_entity) {
+        queue.actions.push(action);
+    } else {
+        queues.insert(player_entity, ActionQueue { actions: vec![action], hit_points, hostiles_in_view, hunger })
+            .expect("Unable to insert action queue");
+    }
+}
+
+pub fn is_busy(ecs: &World) -> bool {
+    ecs.read_storage::<ActionQueue>().get(*ecs.fetch::<Entity>()).is_some()
+}
+
+/// What the player is doing, in the form "eating Rations".
+fn describe(ecs: &World, action: &QueuedAction) -> String {
+    match action {
+        QueuedAction::UseItem { item, .. } => {
+            let verb = if ecs.read_storage::<Equippable>().get(*item).is_some() {
+                "putting on"
+            } else if ecs.read_storage::<ProvidesFood>().get(*item).is_some() {
+                "eating"
+            } else {
+                "using"
+            };
+            format!("{} {}", verb, crate::gui::get_item_display_name(ecs, *item))
+        }
+        QueuedAction::Rest { .. } | QueuedAction::RestUntilHealed => "resting".to_string(),
+        QueuedAction::Explore { .. } => "exploring".to_string(),
+        QueuedAction::Travel { .. } => "travelling".to_string(),
+    }
+}
+
+/// A label for the action under way, and how far through it the player is.
+pub fn progress(ecs: &World) -> Option<(String, i32, i32)> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let queues = ecs.read_storage::<ActionQueue>();
+    let action = queues.get(player_entity)?.actions.first()?;
+    let mut label = describe(ecs, action);
+    label[..1].make_ascii_uppercase();
+    match action {
+        QueuedAction::UseItem { done, turns, .. } | QueuedAction::Rest { done, turns } => {
+            Some((format!("{} {}/{}", label, done, turns), *done, *turns))
+        }
+        QueuedAction::Travel { done, distance, .. } => {
+            Some((format!("{} {}/{}", label, done, distance), *done, *distance))
+        }
+        QueuedAction::RestUntilHealed => {
+            let pools = ecs.read_storage::<Pools>();
+            let hit_points = &pools.get(player_entity)?.hit_points;
+            Some((format!("{} until healed {}/{}", label, hit_points.current, hit_points.max), hit_points.current, hit_points.max))
+        }
+        QueuedAction::Explore { .. } => {
+            let map = ecs.fetch::<Map>();
+            let revealed = map.revealed_tiles.iter().filter(|revealed| **revealed).count() as i32;
+            let percent = revealed * 100 / map.revealed_tiles.len() as i32;
+            Some((format!("{} {}% seen", label, percent), percent, 100))
+        }
+    }
+}
+
+/// Drops everything the player had queued.
+pub fn stop(ecs: &mut World) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let queue = ecs.write_storage::<ActionQueue>().remove(player_entity);
+    if let Some(action) = queue.as_ref().and_then(|queue| queue.actions.first()) {
+        let message = format!("You stop {}.", describe(ecs, action));
+        log(ecs, message);
+    }
+}
+
+/// Uses an item, queuing it if it takes more than one turn.
+pub fn use_item(ecs: &mut World, item: Entity, target: Option<rltk::Point>) -> RunState {
+    let turns = ecs.read_storage::<UseTurns>().get(item).map_or(1, |use_turns| use_turns.turns);
+    if turns > 1 {
+        let action = QueuedAction::UseItem { item, target, done: 0, turns };
+        let message = format!("You start {}.", describe(ecs, &action));
+        log(ecs, message);
+        queue_action(ecs, action);
+        return RunState::AwaitingInput;
+    }
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut intent = ecs.write_storage::<WantsToUseItem>();
+    intent.insert(player_entity, WantsToUseItem { item, target }).expect("Unable to insert intent.");
+    RunState::Ticking
+}
+
+/// Rests for a number of turns, or until healed if `turns` is `None`.
+pub fn rest(ecs: &mut World, turns: Option<i32>) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let (_hit_points, hostiles_in_view, hunger) = player_state(ecs);
+    let healed = ecs.read_storage::<Pools>().get(player_entity)
+        .is_some_and(|pools| pools.hit_points.current >= pools.hit_points.max && pools.mana.current >= pools.mana.max);
+
+    if hostiles_in_view > 0 {
+        log(ecs, "You can't rest with enemies nearby.".to_string());
+    } else if turns.is_none() && hunger >= Some(HungerState::Hungry) {
+        log(ecs, "You are too hungry to rest.".to_string());
+    } else if turns.is_none() && healed {
+        log(ecs, "You are already fully rested.".to_string());
+    } else {
+        log(ecs, "You start resting.".to_string());
+        queue_action(ecs, match turns {
+            Some(turns) => QueuedAction::Rest { done: 0, turns },
+            None => QueuedAction::RestUntilHealed,
+        });
+    }
+    RunState::AwaitingInput
+}
+
+/// Starts auto-exploring the level.
+pub fn explore(ecs: &mut World) -> RunState {
+    if visible_hostiles(ecs) > 0 {
+        log(ecs, "You can't explore with enemies nearby.".to_string());
+        return RunState::AwaitingInput;
+    }
+    let items_in_view = crate::travel::unwanted_items_in_view(ecs);
+    queue_action(ecs, QueuedAction::Explore { items_in_view });
+    RunState::AwaitingInput
+}
+
+/// Starts walking to a map index by the shortest known route.
+pub fn travel_to(ecs: &mut World, destination: usize) -> RunState {
+    let player_pos = *ecs.fetch::<rltk::Point>();
+    if ecs.fetch::<Map>().xy_index(player_pos.x, player_pos.y) == destination {
+        return RunState::AwaitingInput;
+    }
+    let distance = crate::travel::route(ecs, destination).len() as i32;
+    if visible_hostiles(ecs) > 0 {
+        log(ecs, "You can't travel with enemies nearby.".to_string());
+    } else if distance == 0 {
+        log(ecs, "You don't know a way there.".to_string());
+    } else {
+        queue_action(ecs, QueuedAction::Travel { destination: destination as i32, done: 0, distance });
+    }
+    RunState::AwaitingInput
+}
+
+/// Starts walking to the nearest known stairs of the given kind.
+pub fn travel_to_stairs(ecs: &mut World, stairs: TileType) -> RunState {
+    let nearest = {
+        let map = ecs.fetch::<Map>();
+        let player_pos = *ecs.fetch::<rltk::Point>();
+        map.tiles.iter().enumerate()
+            .filter(|(index, tile)| **tile == stairs && map.revealed_tiles[*index])
+            .map(|(index, _tile)| index)
+            .min_by_key(|index| {
+                let (x, y) = (*index as i32 % map.width, *index as i32 / map.width);
+                (x - player_pos.x).abs().max((y - player_pos.y).abs())
+            })
+    };
+    match nearest {
+        Some(destination) => travel_to(ecs, destination),
+        None => {
+            let direction = if stairs == TileType::DownStairs { "down" } else { "up" };
+            log(ecs, format!("You don't know where the stairs {} are.", direction));
+            RunState::AwaitingInput
+        }
+    }
+}
+
+/// Spends the player's turn on the first queued action. Returns `None` when there is nothing
+/// queued, or it has just been interrupted before using the turn, and the player should be asked
+/// what to do.
+pub fn run_queue(ecs: &mut World) -> Option<RunState> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let queue = ecs.read_storage::<ActionQueue>().get(player_entity).cloned()?;
+    let (hit_points, hostiles_in_view, hunger) = player_state(ecs);
+
+    let interruption = if hit_points < queue.hit_points {
+        Some("You are hurt!")
+    } else if hostiles_in_view > queue.hostiles_in_view {
+        Some("You see an enemy!")
+    } else if hunger > queue.hunger {
+        Some("You are getting hungry.")
+    } else {
+        None
+    };
+    if let Some(interruption) = interruption {
+        log(ecs, interruption.to_string());
+        stop(ecs);
+        return None;
+    }
+
+    let mut state = RunState::Ticking;
+    let next = match queue.actions.first() {
+        None => {
+            ecs.write_storage::<ActionQueue>().remove(player_entity);
+            return None;
+        }
+        Some(QueuedAction::UseItem { item, target, done, turns }) => {
+            let carried = ecs.read_storage::<InBackpack>().get(*item).is_some_and(|pack| pack.owner == player_entity);
+            if !carried {
+                ecs.write_storage::<ActionQueue>().remove(player_entity);
+                return None;
+            }
+            if done + 1 >= *turns {
+                let mut intent = ecs.write_storage::<WantsToUseItem>();
+                intent.insert(player_entity, WantsToUseItem { item: *item, target: *target }).expect("Unable to insert intent.");
+                None
+            } else {
+                let mut actions = ecs.write_storage::<TookAction>();
+                actions.insert(player_entity, TookAction { action: ActionType::Wait }).expect("Unable to insert action");
+                Some(QueuedAction::UseItem { item: *item, target: *target, done: done + 1, turns: *turns })
+            }
+        }
+        Some(QueuedAction::Rest { done, turns }) => {
+            skip_turn(ecs);
+            if done + 1 >= *turns {
+                log(ecs, "You finish resting.".to_string());
+                None
+            } else {
+                Some(QueuedAction::Rest { done: done + 1, turns: *turns })
+            }
+        }
+        Some(QueuedAction::RestUntilHealed) => {
+            if hunger >= Some(HungerState::Hungry) {
+                log(ecs, "You are too hungry to rest.".to_string());
+                stop(ecs);
+                return None;
+            }
+            skip_turn(ecs);
+            let healed = ecs.read_storage::<Pools>().get(player_entity)
+                .is_some_and(|pools| pools.hit_points.current >= pools.hit_points.max && pools.mana.current >= pools.mana.max);
+            if healed {
+                log(ecs, "You feel rested.".to_string());
+                None
+            } else {
+                Some(QueuedAction::RestUntilHealed)
+            }
+        }
+        Some(QueuedAction::Explore { items_in_view }) => {
+            match crate::travel::explore(ecs, *items_in_view) {
+                Ok(items_in_view) => Some(QueuedAction::Explore { items_in_view }),
+                Err(interruption) => {
+                    log(ecs, interruption.reason);
+                    ecs.write_storage::<ActionQueue>().remove(player_entity);
+                    return interruption.state;
+                }
+            }
+        }
+        Some(QueuedAction::Travel { destination, done, distance }) => {
+            match crate::travel::travel(ecs, *destination as usize) {
+                Ok(step_state) => {
+                    state = step_state;
+                    let player_pos = *ecs.fetch::<rltk::Point>();
+                    let arrived = ecs.fetch::<Map>().xy_index(player_pos.x, player_pos.y) == *destination as usize;
+                    if arrived { None } else { Some(QueuedAction::Travel { destination: *destination, done: done + 1, distance: *distance }) }
+                }
+                Err(interruption) => {
+                    log(ecs, interruption.reason);
+                    stop(ecs);
+                    return interruption.state;
+                }
+            }
+        }
+    };
+
+    let (hit_points, hostiles_in_view, hunger) = player_state(ecs);
+    let mut queues = ecs.write_storage::<ActionQueue>();
+    let queue = queues.get_mut(player_entity).unwrap();
+    match next {
+        Some(action) => queue.actions[0] = action,
+        None => { queue.actions.remove(0); }
+    }
+    if queue.actions.is_empty() {
+        queues.remove(player_entity);
+    } else {
+        queue.hit_points = hit_points;
+        queue.hostiles_in_view = hostiles_in_view;
+        queue.hunger = hunger;
+    }
+    Some(state)
+}
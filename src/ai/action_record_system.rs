@@ -0,0 +1,58 @@
+use specs::{Entities, Join, ReadStorage, System, WriteStorage};
+use crate::{ActionType, Equippable, EntityMoved, TookAction, WantsToCastSpell, WantsToDropItem, WantsToMelee,
+    WantsToPickupItem, WantsToRemoveItem, WantsToShoot, WantsToUseItem};
+
+/// Notes what each entity chose to do this turn, before the intents are consumed, so that the
+/// `InitiativeSystem` can charge for it.
+pub struct ActionRecordSystem {}
+
+impl<'a> System<'a> for ActionRecordSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, TookAction>,
+        ReadStorage<'a, EntityMoved>,
+        ReadStorage<'a, WantsToMelee>,
+        ReadStorage<'a, WantsToShoot>,
+        ReadStorage<'a, WantsToCastSpell>,
+        ReadStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Equippable>,
+        ReadStorage<'a, WantsToPickupItem>,
+        ReadStorage<'a, WantsToDropItem>,
+        ReadStorage<'a, WantsToRemoveItem>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut actions, moved, melee, shoot, cast, use_item, equippable, pickup, drop, remove) = data;
+
+        let mut record = |entity, action| {
+            actions.insert(entity, TookAction{ action }).expect("Unable to insert action");
+        };
+
+        for (entity, _moved) in (&entities, &moved).join() {
+            record(entity, ActionType::Move);
+        }
+        for (entity, _melee) in (&entities, &melee).join() {
+            record(entity, ActionType::Melee);
+        }
+        for (entity, _shoot) in (&entities, &shoot).join() {
+            record(entity, ActionType::Ranged);
+        }
+        for (entity, _cast) in (&entities, &cast).join() {
+            record(entity, ActionType::CastSpell);
+        }
+        for (entity, use_item) in (&entities, &use_item).join() {
+            let action = if equippable.get(use_item.item).is_some() { ActionType::Equip } else { ActionType::UseItem };
+            record(entity, action);
+        }
+        for (entity, _pickup) in (&entities, &pickup).join() {
+            record(entity, ActionType::PickUp);
+        }
+        for (entity, _drop) in (&entities, &drop).join() {
+            record(entity, ActionType::Drop);
+        }
+        for (entity, _remove) in (&entities, &remove).join() {
+            record(entity, ActionType::Equip);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use specs::{Entities, Entity, Join, ReadStorage, System, WriteStorage};
-use crate::{Faction, Map, MyTurn, Position, ReadExpect, WantsToMelee};
+use crate::{Faction, FactionStanding, Map, MyTurn, Position, ReadExpect, WantsToMelee};
 use crate::raws::faction_structs::Reaction;
 
 pub struct AdjacentAI {}
@@ -9,6 +9,7 @@ impl<'a> System<'a> for AdjacentAI {
     type SystemData = (
         WriteStorage<'a, MyTurn>,
         ReadStorage<'a, Faction>,
+        ReadStorage<'a, FactionStanding>,
         ReadStorage<'a, Position>,
         ReadExpect<'a, Map>,
         WriteStorage<'a, WantsToMelee>,
@@ -22,6 +23,7 @@ impl<'a> System<'a> for AdjacentAI {
         let (
             mut turns,
             factions,
+            standings,
             positions,
             map,
             mut wants_melee,
@@ -38,14 +40,14 @@ impl<'a> System<'a> for AdjacentAI {
                 let h = map.height;
 
                 // Add possible reactions to adjacent for each direction
-                if pos.x > 0 { evaluate(index - 1, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.x < w - 1 { evaluate(index + 1, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.y > 0 { evaluate(index - w as usize, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.y < h - 1 { evaluate(index + w as usize, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.y > 0 && pos.x > 0 { evaluate((index + w as usize) - 1, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.y > 0 && pos.x < w - 1 { evaluate((index - w as usize) + 1, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.y < h - 1 && pos.x > 0 { evaluate((index + w as usize) - 1, &map, &factions, &my_faction.name, &mut reactions); }
-                if pos.y < h - 1 && pos.x < w - 1 { evaluate((index + w as usize) + 1, &map, &factions, &my_faction.name, &mut reactions); }
+                if pos.x > 0 { evaluate(index - 1, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.x < w - 1 { evaluate(index + 1, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.y > 0 { evaluate(index - w as usize, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.y < h - 1 { evaluate(index + w as usize, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.y > 0 && pos.x > 0 { evaluate((index + w as usize) - 1, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.y > 0 && pos.x < w - 1 { evaluate((index - w as usize) + 1, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.y < h - 1 && pos.x > 0 { evaluate((index + w as usize) - 1, &map, &factions, &standings, &my_faction.name, &mut reactions); }
+                if pos.y < h - 1 && pos.x < w - 1 { evaluate((index + w as usize) + 1, &map, &factions, &standings, &my_faction.name, &mut reactions); }
 
                 let mut done = false;
                 for reaction in reactions.iter() {
@@ -66,12 +68,12 @@ impl<'a> System<'a> for AdjacentAI {
     }
 }
 
-fn evaluate(index: usize, map: &Map, factions: &ReadStorage<Faction>, my_faction: &str, reactions: &mut Vec<(Entity, Reaction)>) {
+fn evaluate(index: usize, map: &Map, factions: &ReadStorage<Faction>, standings: &ReadStorage<FactionStanding>, my_faction: &str, reactions: &mut Vec<(Entity, Reaction)>) {
     crate::spatial::for_each_tile_content(index, |other_entity| {
         if let Some(faction) = factions.get(other_entity) {
             reactions.push((
                 other_entity,
-                crate::raws::faction_reaction(my_faction, &faction.name, &crate::raws::RAWS.lock().unwrap())
+                crate::raws::faction_reaction_to(my_faction, &faction.name, standings.get(other_entity), &crate::raws::RAWS.lock().unwrap())
             ));
         }
     });
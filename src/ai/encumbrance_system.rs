@@ -2,7 +2,8 @@ use std::collections::HashMap;
 
 use specs::{System, WriteStorage, Entities, ReadStorage, Entity, ReadExpect, WriteExpect, Join};
 
-use crate::{EquipmentChanged, InBackpack, Equipped, Item, Pools, Attributes, gamelog::GameLog};
+use crate::{EquipmentChanged, InBackpack, Equipped, Item, Pools, Attributes, StatusEffect, AttributeBonus, SkillBonus, Skills, gamelog::GameLog};
+use crate::gamesystem::{attr_bonus, player_hp_at_level, npc_hp, mana_at_level};
 
 pub struct EncumbranceSystem{}
 
@@ -16,9 +17,13 @@ impl<'a> System<'a> for EncumbranceSystem {
         ReadStorage<'a, InBackpack>,
         ReadStorage<'a, Equipped>,
         WriteStorage<'a, Pools>,
-        ReadStorage<'a, Attributes>,
+        WriteStorage<'a, Attributes>,
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        ReadStorage<'a, StatusEffect>,
+        ReadStorage<'a, AttributeBonus>,
+        ReadStorage<'a, SkillBonus>,
+        WriteStorage<'a, Skills>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -29,9 +34,13 @@ impl<'a> System<'a> for EncumbranceSystem {
             backpacks,
             equipped,
             mut pools,
-            attributes,
+            mut attributes,
             player,
-            mut gamelog
+            mut gamelog,
+            statuses,
+            attribute_bonuses,
+            skill_bonuses,
+            mut skills
         ) = data;
 
         if equip_dirty.is_empty() { return; }
@@ -59,6 +68,71 @@ impl<'a> System<'a> for EncumbranceSystem {
             }
         }
 
+        // Total up attribute modifiers from equipped items and status effects
+        let mut attr_modifiers: HashMap<Entity, (i32, i32, i32, i32)> = HashMap::new();
+        let mut add_bonus = |owner: Entity, bonus: &AttributeBonus| {
+            if to_update.contains_key(&owner) {
+                let totals = attr_modifiers.entry(owner).or_insert((0, 0, 0, 0));
+                totals.0 += bonus.might.unwrap_or(0);
+                totals.1 += bonus.fitness.unwrap_or(0);
+                totals.2 += bonus.quickness.unwrap_or(0);
+                totals.3 += bonus.intelligence.unwrap_or(0);
+            }
+        };
+        for (equipped, bonus) in (&equipped, &attribute_bonuses).join() {
+            add_bonus(equipped.owner, bonus);
+        }
+        for (status, bonus) in (&statuses, &attribute_bonuses).join() {
+            add_bonus(status.target, bonus);
+        }
+
+        for entity in to_update.keys() {
+            if let Some(attr) = attributes.get_mut(*entity) {
+                let old_fitness = attr.fitness.base + attr.fitness.modifiers;
+                let old_intelligence = attr.intelligence.base + attr.intelligence.modifiers;
+
+                let (might, fitness, quickness, intelligence) = attr_modifiers.get(entity).copied().unwrap_or((0, 0, 0, 0));
+                attr.might.modifiers = might;
+                attr.fitness.modifiers = fitness;
+                attr.quickness.modifiers = quickness;
+                attr.intelligence.modifiers = intelligence;
+                attr.might.bonus = attr_bonus(attr.might.base + attr.might.modifiers);
+                attr.fitness.bonus = attr_bonus(attr.fitness.base + attr.fitness.modifiers);
+                attr.quickness.bonus = attr_bonus(attr.quickness.base + attr.quickness.modifiers);
+                attr.intelligence.bonus = attr_bonus(attr.intelligence.base + attr.intelligence.modifiers);
+
+                // Shift max HP and mana by however much the new totals change them, so that
+                // pools set by hand (or by levelling) keep their relative value.
+                if let Some(pool) = pools.get_mut(*entity) {
+                    let new_fitness = attr.fitness.base + attr.fitness.modifiers;
+                    let new_intelligence = attr.intelligence.base + attr.intelligence.modifiers;
+                    let hp_at = |fitness: i32| if *entity == *player { player_hp_at_level(fitness, pool.level) } else { npc_hp(fitness, pool.level) };
+                    pool.hit_points.max += hp_at(new_fitness) - hp_at(old_fitness);
+                    pool.mana.max += mana_at_level(new_intelligence, pool.level) - mana_at_level(old_intelligence, pool.level);
+                    pool.hit_points.max = i32::max(1, pool.hit_points.max);
+                    pool.mana.max = i32::max(0, pool.mana.max);
+                    pool.hit_points.current = i32::min(pool.hit_points.current, pool.hit_points.max);
+                    pool.mana.current = i32::min(pool.mana.current, pool.mana.max);
+                }
+            }
+        }
+
+        // Skill modifiers come only from equipped items
+        for entity in to_update.keys() {
+            if let Some(skills) = skills.get_mut(*entity) {
+                skills.modifiers.clear();
+            }
+        }
+        for (equipped, bonus) in (&equipped, &skill_bonuses).join() {
+            if to_update.contains_key(&equipped.owner) {
+                if let Some(skills) = skills.get_mut(equipped.owner) {
+                    for (skill, amount) in bonus.skills.iter() {
+                        *skills.modifiers.entry(skill.clone()).or_insert(0) += amount;
+                    }
+                }
+            }
+        }
+
         for (entity, (weight, initiative)) in to_update.iter() {
             if let Some(pool) = pools.get_mut(*entity) {
                 pool.total_weight = *weight;
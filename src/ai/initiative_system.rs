@@ -1,6 +1,8 @@
-use rltk::RandomNumberGenerator;
 use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
-use crate::{Attributes, Initiative, MyTurn, Position, RunState, Pools};
+use std::collections::HashMap;
+use crate::{Attributes, Initiative, MyTurn, Position, RunState, Pools, StatusEffect, Slow, TookAction, ActionType, Map};
+use crate::gamesystem::{settle_action_cost, energy_per_tick, STANDARD_ACTION_COST};
+use crate::map::tile_cost;
 
 pub struct InitiativeSystem {}
 
@@ -12,12 +14,15 @@ impl<'a> System<'a> for InitiativeSystem {
         ReadStorage<'a, Position>,
         WriteStorage<'a, MyTurn>,
         Entities<'a>,
-        WriteExpect<'a, RandomNumberGenerator>,
         ReadStorage<'a, Attributes>,
         WriteExpect<'a, RunState>,
         ReadExpect<'a, Entity>,
         ReadExpect<'a, rltk::Point>,
-        ReadStorage<'a, Pools>
+        ReadStorage<'a, Pools>,
+        ReadStorage<'a, StatusEffect>,
+        ReadStorage<'a, Slow>,
+        WriteStorage<'a, TookAction>,
+        ReadExpect<'a, Map>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -27,39 +32,47 @@ impl<'a> System<'a> for InitiativeSystem {
             positions,
             mut turns,
             entities,
-            mut rng,
             attributes,
             mut runstate,
             player,
             player_pos,
             pools,
+            statuses,
+            slows,
+            mut actions,
+            map,
         ) = data;
 
         if *runstate != RunState::Ticking { return; }
 
+        // Settle the actions taken since the last tick; the standard cost was paid when the turn began
+        for (entity, initiative, action) in (&entities, &mut initiatives, &actions).join() {
+            let penalty = pools.get(entity).map_or(0.0, |p| p.total_initiative_penalty);
+            let terrain = positions.get(entity)
+                .and_then(|pos| map.tiles.get(map.xy_index(pos.x, pos.y)))
+                .map_or(1.0, |tile| tile_cost(*tile));
+            initiative.energy -= settle_action_cost(action.action, penalty, terrain);
+        }
+        actions.clear();
+
+        // Slow and haste effects adjust the speed of whoever they target
+        let mut status_penalties: HashMap<Entity, i32> = HashMap::new();
+        for (status, slow) in (&statuses, &slows).join() {
+            *status_penalties.entry(status.target).or_insert(0) += slow.initiative_penalty;
+        }
+
         // Clear any remaining MyTurn we left by mistake
         turns.clear();
 
-        // Roll Initiative
+        // Gain energy, and take a turn once there is enough
         for (entity, initiative, pos) in (&entities, &mut initiatives, &positions).join() {
-            initiative.current -= 1;
-
-            if let Some(pools) = pools.get(entity) {
-                initiative.current += f32::floor(pools.total_initiative_penalty) as i32;
-            }
+            let quickness = attributes.get(entity).map_or(0, |attr| attr.quickness.bonus);
+            let penalty = status_penalties.get(&entity).copied().unwrap_or(0);
+            initiative.energy += energy_per_tick(quickness, penalty);
 
-            if initiative.current < 1 {
+            if initiative.energy >= 0 {
                 let mut my_turn = true;
-
-                // Re-roll initiative
-                initiative.current = 6 + rng.roll_dice(1, 6);
-
-                // Give a bonus for quickness
-                if let Some(attr) = attributes.get(entity) {
-                    initiative.current -= attr.quickness.bonus;
-                }
-
-                // TODO: More initiative granting boosts/penalties will go here later
+                initiative.energy -= STANDARD_ACTION_COST;
 
                 // If it is the player, we want to go to an AwaitingInput state.
                 if entity == *player {
@@ -75,8 +88,11 @@ impl<'a> System<'a> for InitiativeSystem {
                 }
 
                 if my_turn {
-                    // It's my turn!
+                    // It's my turn! Anything that doesn't record an action is charged as waiting.
                     turns.insert(entity, MyTurn{}).expect("Unable to insert turn");
+                    if entity != *player {
+                        actions.insert(entity, TookAction{ action: ActionType::Wait }).expect("Unable to insert action");
+                    }
                 }
             }
         }
@@ -8,6 +8,7 @@ mod flee_ai_system;
 mod default_move_system;
 mod chase_ai_system;
 mod encumbrance_system;
+mod action_record_system;
 
 pub use initiative_system::InitiativeSystem;
 pub use turn_status::TurnStatusSystem;
@@ -19,3 +20,4 @@ pub use flee_ai_system::FleeAI;
 pub use default_move_system::DefaultMoveAI;
 pub use chase_ai_system::ChaseAI;
 pub use encumbrance_system::EncumbranceSystem;
+pub use action_record_system::ActionRecordSystem;
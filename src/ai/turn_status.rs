@@ -1,14 +1,18 @@
-use specs::{Entities, Entity, Join, ReadExpect, System, WriteStorage};
-use crate::{Confusion, MyTurn, RunState};
+use std::collections::HashSet;
+use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+use crate::{Confusion, MyTurn, RunState, StatusEffect, gamelog::GameLog};
 
 pub struct TurnStatusSystem {}
 impl<'a> System<'a> for TurnStatusSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         WriteStorage<'a, MyTurn>,
-        WriteStorage<'a, Confusion>,
+        ReadStorage<'a, Confusion>,
+        ReadStorage<'a, StatusEffect>,
         Entities<'a>,
-        ReadExpect<'a, RunState>,
+        WriteExpect<'a, RunState>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -16,32 +20,37 @@ impl<'a> System<'a> for TurnStatusSystem {
 
         let (
             mut turns,
-            mut confusion,
+            confusion,
+            statuses,
             entities,
-            runstate
+            mut runstate,
+            player_entity,
+            mut log,
         ) = data;
 
-        if *runstate != RunState::Ticking { return; }
+        match *runstate {
+            RunState::Ticking | RunState::AwaitingInput => {}
+            _ => return
+        }
 
-        let mut not_my_turn: Vec<Entity> = Vec::new();
-        let mut not_confused: Vec<Entity> = Vec::new();
+        let mut confused: HashSet<Entity> = HashSet::new();
+        for (_confusion, status) in (&confusion, &statuses).join() {
+            confused.insert(status.target);
+        }
 
-        for (entity, _turn, confused) in (&entities, &mut turns, &mut confusion).join() {
-            confused.turns -= 1;
-            if confused.turns < 1 {
-                not_confused.push(entity);
-            } else {
+        let mut not_my_turn: Vec<Entity> = Vec::new();
+        for (entity, _turn) in (&entities, &turns).join() {
+            if confused.contains(&entity) {
                 not_my_turn.push(entity);
             }
         }
 
         for e in not_my_turn {
             turns.remove(e);
+            if e == *player_entity {
+                log.entries.push("You are confused, and stumble in place.".to_string());
+                *runstate = RunState::Ticking;
+            }
         }
-
-        for e in not_confused {
-            confusion.remove(e);
-        }
-
     }
 }
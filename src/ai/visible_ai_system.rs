@@ -1,5 +1,7 @@
-use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, System, WriteStorage};
-use crate::{Chasing, Faction, Map, MyTurn, Position, Viewshed, WantsToApproach, WantsToFlee};
+use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+use rltk::{DistanceAlg, Point, RandomNumberGenerator};
+use crate::{Chasing, Faction, FactionStanding, Map, MyTurn, Position, Viewshed, WantsToApproach, WantsToFlee,
+            KnownSpells, SpellTemplate, Name, Ranged, Pools, WantsToCastSpell};
 use crate::raws::faction_structs::Reaction;
 use crate::raws::RAWS;
 
@@ -8,8 +10,9 @@ pub struct VisibleAI {}
 impl<'a> System<'a> for VisibleAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        ReadStorage<'a, MyTurn>,
+        WriteStorage<'a, MyTurn>,
         ReadStorage<'a, Faction>,
+        ReadStorage<'a, FactionStanding>,
         ReadStorage<'a, Position>,
         ReadExpect<'a, Map>,
         WriteStorage<'a, WantsToApproach>,
@@ -17,13 +20,21 @@ impl<'a> System<'a> for VisibleAI {
         Entities<'a>,
         ReadExpect<'a, Entity>,
         ReadStorage<'a, Viewshed>,
-        WriteStorage<'a, Chasing>
+        WriteStorage<'a, Chasing>,
+        ReadStorage<'a, KnownSpells>,
+        ReadStorage<'a, SpellTemplate>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Ranged>,
+        ReadStorage<'a, Pools>,
+        WriteStorage<'a, WantsToCastSpell>,
+        WriteExpect<'a, RandomNumberGenerator>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            turns,
+            mut turns,
             factions,
+            standings,
             positions,
             map,
             mut want_approach,
@@ -31,9 +42,18 @@ impl<'a> System<'a> for VisibleAI {
             entities,
             player,
             viewsheds,
-            mut chasing
+            mut chasing,
+            known_spells,
+            spell_templates,
+            names,
+            ranged,
+            pools,
+            mut wants_cast,
+            mut rng,
         ) = data;
 
+        let mut turn_done: Vec<Entity> = Vec::new();
+
         for (entity, _turn, my_faction, pos, viewshed)
             in (&entities, &turns, &factions, &positions, &viewsheds).join() {
             if entity != *player {
@@ -43,7 +63,7 @@ impl<'a> System<'a> for VisibleAI {
                 for visible_tile in viewshed.visible_tiles.iter() {
                     let index = map.xy_index(visible_tile.x, visible_tile.y);
                     if my_index != index {
-                        evaluate(index, &map, &factions, &my_faction.name, &mut reactions);
+                        evaluate(index, &map, &factions, &standings, &my_faction.name, &mut reactions);
                     }
                 }
 
@@ -51,6 +71,13 @@ impl<'a> System<'a> for VisibleAI {
                 for reaction in reactions.iter() {
                     match reaction.1 {
                         Reaction::Attack => {
+                            if let Some(spell) = pick_ranged_spell(entity, pos, reaction.0, &map, &known_spells, &spell_templates, &names, &ranged, &pools, &entities, &mut rng) {
+                                let target = Point::new(reaction.0 as i32 % map.width, reaction.0 as i32 / map.width);
+                                wants_cast.insert(entity, WantsToCastSpell { spell, target: Some(target) }).expect("Unable to insert");
+                                turn_done.push(entity);
+                                done = true;
+                                break;
+                            }
                             want_approach.insert(entity, WantsToApproach { index: reaction.0 as i32 }).expect("Unable to insert");
                             chasing.insert(entity, Chasing { target: reaction.2 }).expect("Unable to insert");
                             done = true;
@@ -67,15 +94,44 @@ impl<'a> System<'a> for VisibleAI {
                 }
             }
         }
+
+        // Casting a spell uses up the turn
+        for done in turn_done.iter() {
+            turns.remove(*done);
+        }
+    }
+}
+
+/// Spellcasters facing an enemy may throw a ranged spell instead of closing to melee.
+#[allow(clippy::too_many_arguments)]
+fn pick_ranged_spell(entity: Entity, pos: &Position, target_index: usize, map: &Map, known_spells: &ReadStorage<KnownSpells>,
+    spell_templates: &ReadStorage<SpellTemplate>, names: &ReadStorage<Name>, ranged: &ReadStorage<Ranged>,
+    pools: &ReadStorage<Pools>, entities: &Entities, rng: &mut RandomNumberGenerator) -> Option<Entity>
+{
+    let known = known_spells.get(entity)?;
+    let mana = pools.get(entity)?.mana.current;
+    if rng.roll_dice(1, 2) == 1 { return None; }
+
+    let distance = DistanceAlg::Pythagoras.distance2d(
+        Point::new(pos.x, pos.y),
+        Point::new(target_index as i32 % map.width, target_index as i32 / map.width)
+    );
+    for spell in known.spells.iter().filter(|s| s.mana_cost <= mana) {
+        for (spell_entity, _template, name, range) in (entities, spell_templates, names, ranged).join() {
+            if name.name == spell.display_name && distance <= range.range as f32 {
+                return Some(spell_entity);
+            }
+        }
     }
+    None
 }
 
-fn evaluate(index: usize, map: &Map, factions: &ReadStorage<Faction>, my_faction: &str, reactions: &mut Vec<(usize, Reaction, Entity)>) {
+fn evaluate(index: usize, map: &Map, factions: &ReadStorage<Faction>, standings: &ReadStorage<FactionStanding>, my_faction: &str, reactions: &mut Vec<(usize, Reaction, Entity)>) {
     crate::spatial::for_each_tile_content(index, |other_entity| {
         if let Some(faction) = factions.get(other_entity) {
             reactions.push((
                 index,
-                crate::raws::faction_reaction(my_faction, &faction.name, &RAWS.lock().unwrap()),
+                crate::raws::faction_reaction_to(my_faction, &faction.name, standings.get(other_entity), &RAWS.lock().unwrap()),
                 other_entity
             ));
         }
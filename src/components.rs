@@ -5,6 +5,8 @@ use serde::{Serialize, Deserialize};
 use specs::saveload::{Marker, ConvertSaveload};
 use specs::error::NoError;
 use std::collections::HashMap;
+use crate::raws::faction_structs::Reaction;
+use crate::dice::DiceExpr;
 
 pub struct SerializeMe;
 
@@ -106,6 +108,7 @@ pub struct Ranged {
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
+    pub damage_type: Option<DamageType>,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -113,10 +116,11 @@ pub struct AreaOfEffect {
     pub radius: i32,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct Confusion {
-    pub turns: i32,
-}
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Confusion {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MagicMapper {}
 
 // Special component that exists to help serialize the game data
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -130,7 +134,7 @@ pub struct DMSerializationHelper {
 }
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
-pub enum EquipmentSlot { Melee, Shield, Head, Torso, Legs, Feet, Hands }
+pub enum EquipmentSlot { Melee, Ranged, Quiver, Shield, Head, Torso, Legs, Feet, Hands }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Equippable {
@@ -146,19 +150,81 @@ pub struct Equipped {
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum WeaponAttribute { Might, Quickness }
 
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageType { Slashing, Piercing, Bludgeoning, Fire, Cold, Poison }
+
+impl DamageType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Slashing => "slashing",
+            DamageType::Piercing => "piercing",
+            DamageType::Bludgeoning => "bludgeoning",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+        }
+    }
+
+    /// Physical damage is the kind armor can soak up.
+    pub fn is_physical(&self) -> bool {
+        matches!(self, DamageType::Slashing | DamageType::Piercing | DamageType::Bludgeoning)
+    }
+}
+
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct MeleeWeapon {
     pub attribute : WeaponAttribute,
-    pub damage_n_dice : i32,
-    pub damage_die_type : i32,
-    pub damage_bonus : i32,
-    pub hit_bonus : i32
+    pub damage : DiceExpr,
+    pub hit_bonus : i32,
+    pub damage_type : DamageType,
+    pub critical_multiplier : i32
+}
+
+/// A melee weapon that needs both hands, leaving no room for a shield or off-hand weapon.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct TwoHanded {}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct RangedWeapon {
+    pub range : i32,
+    pub damage : DiceExpr,
+    pub hit_bonus : i32,
+    pub ammunition : Option<String>,
+    pub damage_type : DamageType,
+    pub critical_multiplier : i32
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Ammunition {
+    pub ammo_type : String,
+    pub count : i32,
+    pub damage_bonus : i32
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToShoot {
+    pub target : Entity
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Wearable {
     pub armor_class : f32,
-    pub slot : EquipmentSlot
+    pub slot : EquipmentSlot,
+    pub damage_reduction : i32
+}
+
+/// Damage types halved or doubled against whoever has this, or whoever is wearing it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Resistances {
+    pub resistances : Vec<DamageType>,
+    pub vulnerabilities : Vec<DamageType>
+}
+
+/// Carried in the off hand: adds to armor class, and may block a melee hit outright.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Shield {
+    pub defense_bonus : i32,
+    pub block_chance : i32
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -201,11 +267,20 @@ pub struct Attributes {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
-pub enum Skill { Melee, Defense, Magic }
+pub enum Skill { Melee, Defense, Magic, Shield }
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Skills {
-    pub skills : HashMap<Skill, i32>
+    pub skills : HashMap<Skill, i32>,
+    pub modifiers : HashMap<Skill, i32>,
+    pub practice : HashMap<Skill, i32>
+}
+
+/// Choices the player has earned by levelling up but not yet spent.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct PendingLevelUp {
+    pub attribute_points : i32,
+    pub skill_points : i32
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -228,10 +303,9 @@ pub struct Pools {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct NaturalAttack {
     pub name : String,
-    pub damage_n_dice : i32,
-    pub damage_die_type : i32,
-    pub damage_bonus : i32,
-    pub hit_bonus : i32
+    pub damage : DiceExpr,
+    pub hit_bonus : i32,
+    pub damage_type : DamageType
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -258,9 +332,19 @@ pub struct LightSource {
     pub range: i32
 }
 
+/// Energy towards the next turn; see the action cost rules in `gamesystem`.
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Initiative {
-    pub current: i32
+    pub energy: i32
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ActionType { Move, Melee, Ranged, CastSpell, UseItem, Equip, PickUp, Drop, OpenDoor, Wait }
+
+/// The action an entity spent its turn on, awaiting the `InitiativeSystem` to charge for it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TookAction {
+    pub action: ActionType
 }
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
@@ -271,6 +355,12 @@ pub struct Faction {
     pub name : String
 }
 
+/// How particular factions regard this entity, overriding their usual reaction to its faction.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct FactionStanding {
+    pub reactions : HashMap<String, Reaction>
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct WantsToApproach {
     pub index : i32
@@ -311,7 +401,8 @@ pub struct ParticleLifetime {
     pub lifetime_ms: f32
 }
 
-#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+/// Ordered from best to worst.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, PartialOrd)]
 pub enum HungerState { WellFed, Normal, Hungry, Starving }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -321,4 +412,203 @@ pub struct HungerClock {
 }
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
-pub struct ProvidesFood {}
\ No newline at end of file
+pub struct ProvidesFood {
+    pub nutrition: i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SingleActivation {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RandomTeleport {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SpellTemplate {
+    pub mana_cost : i32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnownSpell {
+    pub display_name : String,
+    pub mana_cost : i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct KnownSpells {
+    pub spells : Vec<KnownSpell>
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToCastSpell {
+    pub spell : Entity,
+    pub target : Option<rltk::Point>
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeachesSpell {
+    pub spell : String
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct StatusEffect {
+    pub target : Entity
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Duration {
+    pub turns : i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DamageOverTime {
+    pub damage : i32
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HealOverTime {
+    pub heal : i32
+}
+
+/// Added to the target's initiative each time it is re-rolled; negative values hasten.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Slow {
+    pub initiative_penalty : i32
+}
+
+/// Skill levels granted to the wearer of an equipped item.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SkillBonus {
+    pub skills : HashMap<Skill, i32>
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AttributeBonus {
+    pub might : Option<i32>,
+    pub fitness : Option<i32>,
+    pub quickness : Option<i32>,
+    pub intelligence : Option<i32>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusEffectTemplate {
+    pub name : String,
+    pub turns : i32,
+    pub confusion : bool,
+    pub damage_over_time : i32,
+    pub heal_over_time : i32,
+    pub initiative_penalty : i32,
+    pub attributes : Option<AttributeBonus>,
+    pub stacking : bool
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+    pub status : StatusEffectTemplate
+}
+
+/// An item that can't be taken off once equipped, until the curse is lifted.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct CursedItem {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesRemoveCurse {}
+
+/// Services offered by a temple's priest, paid for in gold.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Temple {
+    pub remove_curse_cost : f32
+}
+
+/// A weapon or piece of armor spawned with a quality tier and affixes. `Name` holds the full
+/// name, such as "Fine Longsword of Speed"; this keeps the template it was made from.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct GeneratedItem {
+    pub template: String,
+    pub quality: Option<String>,
+    pub prefix: Option<String>,
+    pub suffix: Option<String>
+}
+
+/// Turns an item takes to use or put on; anything without it takes one.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct UseTurns {
+    pub turns: i32
+}
+
+/// A multi-turn action waiting in the player's `ActionQueue`.
+#[derive(Clone, Debug)]
+pub enum QueuedAction {
+    UseItem { item: Entity, target: Option<rltk::Point>, done: i32, turns: i32 },
+    Rest { done: i32, turns: i32 },
+    RestUntilHealed,
+    Explore { items_in_view: i32 },
+    Travel { destination: i32, done: i32, distance: i32 },
+}
+
+/// The player's queued multi-turn actions, with the hit points, visible hostiles and hunger seen
+/// on the last turn so that a change for the worse can interrupt them.
+#[derive(Component, Clone)]
+pub struct ActionQueue {
+    pub actions: Vec<QueuedAction>,
+    pub hit_points: i32,
+    pub hostiles_in_view: i32,
+    pub hunger: Option<HungerState>,
+}
+
+// ConvertSaveload can't be derived for a Vec of actions holding entities, so the queue is
+// converted by hand.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum QueuedActionData<M> {
+    UseItem { item: M, target: Option<rltk::Point>, done: i32, turns: i32 },
+    Rest { done: i32, turns: i32 },
+    RestUntilHealed,
+    Explore { items_in_view: i32 },
+    Travel { destination: i32, done: i32, distance: i32 },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ActionQueueData<M> {
+    actions: Vec<QueuedActionData<M>>,
+    hit_points: i32,
+    hostiles_in_view: i32,
+    hunger: Option<HungerState>,
+}
+
+impl<M: Marker + Serialize> ConvertSaveload<M> for ActionQueue
+    where for<'de> M: Deserialize<'de>
+{
+    type Data = ActionQueueData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+        where F: FnMut(Entity) -> Option<M>
+    {
+        let actions = self.actions.iter().map(|action| match action {
+            QueuedAction::UseItem { item, target, done, turns } =>
+                QueuedActionData::UseItem { item: ids(*item).unwrap(), target: *target, done: *done, turns: *turns },
+            QueuedAction::Rest { done, turns } => QueuedActionData::Rest { done: *done, turns: *turns },
+            QueuedAction::RestUntilHealed => QueuedActionData::RestUntilHealed,
+            QueuedAction::Explore { items_in_view } => QueuedActionData::Explore { items_in_view: *items_in_view },
+            QueuedAction::Travel { destination, done, distance } =>
+                QueuedActionData::Travel { destination: *destination, done: *done, distance: *distance },
+        }).collect();
+        Ok(ActionQueueData { actions, hit_points: self.hit_points, hostiles_in_view: self.hostiles_in_view, hunger: self.hunger })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+        where F: FnMut(M) -> Option<Entity>
+    {
+        let actions = data.actions.into_iter().map(|action| match action {
+            QueuedActionData::UseItem { item, target, done, turns } =>
+                QueuedAction::UseItem { item: ids(item).unwrap(), target, done, turns },
+            QueuedActionData::Rest { done, turns } => QueuedAction::Rest { done, turns },
+            QueuedActionData::RestUntilHealed => QueuedAction::RestUntilHealed,
+            QueuedActionData::Explore { items_in_view } => QueuedAction::Explore { items_in_view },
+            QueuedActionData::Travel { destination, done, distance } => QueuedAction::Travel { destination, done, distance },
+        }).collect();
+        Ok(ActionQueue { actions, hit_points: data.hit_points, hostiles_in_view: data.hostiles_in_view, hunger: data.hunger })
+    }
+}
@@ -1,8 +1,8 @@
 use specs::prelude::*;
 use super::{ Pools, SufferDamage, Player, Name, GameLog, RunState};
-use crate::{InBackpack, Position, Equipped, LootTable, Attributes, Map};
+use crate::{InBackpack, Position, Equipped, LootTable, Attributes, Map, PendingLevelUp};
 use rltk::RandomNumberGenerator;
-use crate::gamesystem::{player_hp_at_level, mana_at_level};
+use crate::gamesystem::{player_hp_at_level, mana_at_level, xp_to_next_level, ATTRIBUTE_POINTS_PER_LEVEL, SKILL_POINTS_PER_LEVEL};
 
 pub struct DamageSystem {}
 
@@ -16,6 +16,7 @@ impl<'a> System<'a> for DamageSystem {
         ReadExpect<'a, Entity>,
         ReadStorage<'a, Attributes>,
         WriteExpect<'a, GameLog>,
+        WriteStorage<'a, PendingLevelUp>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -28,6 +29,7 @@ impl<'a> System<'a> for DamageSystem {
             player,
             attributes,
             mut log,
+            mut pending_level_up,
         ) = data;
         let mut xp_gain = 0;
         let mut gold_gain = 0.0f32;
@@ -54,7 +56,7 @@ impl<'a> System<'a> for DamageSystem {
             let player_attributes = attributes.get(*player).unwrap();
             player_stats.xp += xp_gain;
             player_stats.gold += gold_gain;
-            if player_stats.xp >= player_stats.level * 1000 {
+            while player_stats.xp >= xp_to_next_level(player_stats.level) {
                 // We've gone up a level!
                 player_stats.level += 1;
                 player_stats.hit_points.max = player_hp_at_level(
@@ -68,6 +70,17 @@ impl<'a> System<'a> for DamageSystem {
                 );
                 player_stats.mana.current = player_stats.mana.max;
                 log.entries.push(format!("Congratulations, you are now level {}", player_stats.level));
+
+                // The level-up screen lets the player spend these once it is their turn
+                if let Some(pending) = pending_level_up.get_mut(*player) {
+                    pending.attribute_points += ATTRIBUTE_POINTS_PER_LEVEL;
+                    pending.skill_points += SKILL_POINTS_PER_LEVEL;
+                } else {
+                    pending_level_up.insert(*player, PendingLevelUp {
+                        attribute_points: ATTRIBUTE_POINTS_PER_LEVEL,
+                        skill_points: SKILL_POINTS_PER_LEVEL
+                    }).expect("Unable to insert level up");
+                }
             }
         }
 
@@ -156,12 +169,14 @@ pub fn delete_the_dead(ecs: &mut World) {
     // Handle spawned drops in a separate scope because it needs ECS.  Unlike "dropped" items,
     // these entities don't exist yet.
     {
+        let map_depth = ecs.fetch::<Map>().depth;
         for drop in to_spawn.iter() {
-            crate::raws::spawn_named_item(
+            crate::raws::spawn_generated_item(
                 &crate::raws::RAWS.lock().unwrap(),
                 ecs,
                 &drop.0,
-                crate::raws::SpawnType::AtPosition { x: drop.1.x, y: drop.1.y }
+                crate::raws::SpawnType::AtPosition { x: drop.1.x, y: drop.1.y },
+                map_depth
             );
         }
     }
@@ -0,0 +1,424 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use rltk::RandomNumberGenerator;
+use serde::{Serialize, Deserialize};
+
+/// Most dice a single term may roll.
+const MAX_DICE : i32 = 100;
+/// Most extra dice a single exploding die may add.
+const MAX_EXPLOSIONS : i32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keep { All, Highest(i32), Lowest(i32) }
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiceTerm {
+    Dice { count: i32, sides: i32, keep: Keep, explode: bool },
+    Constant(i32),
+}
+
+/// A compiled dice expression: terms such as `2d6`, `4d6kh3` (keep the highest 3), `2d20kl1`
+/// (keep the lowest), `1d6!` (a maximum roll adds another die) or a constant, added or
+/// subtracted, optionally followed by `min N` and/or `max N` clamps on the total.
+/// Whitespace is ignored, so `2d6+1d4+3` and `3d6 - 2 min 1` are both valid.
+///
+/// Stored and saved in its text form.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DiceExpr {
+    terms: Vec<(bool, DiceTerm)>,
+    min: Option<i32>,
+    max: Option<i32>,
+}
+
+impl DiceExpr {
+    /// The classic `NdM+B`.
+    pub fn new(n_dice: i32, die_type: i32, bonus: i32) -> DiceExpr {
+        let mut expr = DiceExpr {
+            terms: vec![(false, DiceTerm::Dice { count: n_dice, sides: die_type, keep: Keep::All, explode: false })],
+            min: None,
+            max: None
+        };
+        expr.add_bonus(bonus);
+        expr
+    }
+
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        let mut total = 0;
+        for (negative, term) in self.terms.iter() {
+            let value = term.roll(rng);
+            total += if *negative { -value } else { value };
+        }
+        if let Some(min) = self.min { total = i32::max(total, min); }
+        if let Some(max) = self.max { total = i32::min(total, max); }
+        total
+    }
+
+    /// Adds a flat amount, folding it into a trailing constant if there is one.
+    pub fn add_bonus(&mut self, bonus: i32) {
+        if bonus == 0 { return; }
+        if let Some((negative, DiceTerm::Constant(value))) = self.terms.last() {
+            let combined = if *negative { bonus - value } else { bonus + value };
+            self.terms.pop();
+            if combined != 0 || self.terms.is_empty() {
+                self.terms.push((combined < 0, DiceTerm::Constant(combined.abs())));
+            }
+        } else {
+            self.terms.push((bonus < 0, DiceTerm::Constant(bonus.abs())));
+        }
+    }
+
+    /// Grows (or shrinks) every die by `sides`, never below a d2.
+    pub fn add_sides(&mut self, sides: i32) {
+        for (_, term) in self.terms.iter_mut() {
+            if let DiceTerm::Dice { sides: die_sides, .. } = term {
+                *die_sides = i32::max(2, *die_sides + sides);
+            }
+        }
+    }
+}
+
+impl DiceTerm {
+    fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        match self {
+            DiceTerm::Constant(value) => *value,
+            DiceTerm::Dice { count, sides, keep, explode } => {
+                let mut rolls: Vec<i32> = (0..*count).map(|_| roll_die(rng, *sides, *explode)).collect();
+                match keep {
+                    Keep::All => rolls.iter().sum(),
+                    Keep::Highest(n) => {
+                        rolls.sort_unstable_by(|a, b| b.cmp(a));
+                        rolls.iter().take(*n as usize).sum()
+                    }
+                    Keep::Lowest(n) => {
+                        rolls.sort_unstable();
+                        rolls.iter().take(*n as usize).sum()
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn roll_die(rng: &mut RandomNumberGenerator, sides: i32, explode: bool) -> i32 {
+    let mut last = rng.roll_dice(1, sides);
+    let mut total = last;
+    let mut explosions = 0;
+    while explode && last == sides && explosions < MAX_EXPLOSIONS {
+        last = rng.roll_dice(1, sides);
+        total += last;
+        explosions += 1;
+    }
+    total
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (negative, term)) in self.terms.iter().enumerate() {
+            if *negative {
+                write!(f, "-")?;
+            } else if i > 0 {
+                write!(f, "+")?;
+            }
+            match term {
+                DiceTerm::Constant(value) => write!(f, "{}", value)?,
+                DiceTerm::Dice { count, sides, keep, explode } => {
+                    write!(f, "{}d{}", count, sides)?;
+                    match keep {
+                        Keep::All => {}
+                        Keep::Highest(n) => write!(f, "kh{}", n)?,
+                        Keep::Lowest(n) => write!(f, "kl{}", n)?,
+                    }
+                    if *explode { write!(f, "!")?; }
+                }
+            }
+        }
+        if let Some(min) = self.min { write!(f, " min {}", min)?; }
+        if let Some(max) = self.max { write!(f, " max {}", max)?; }
+        Ok(())
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<DiceExpr, String> {
+        let mut parser = Parser { text, chars: text.chars().collect(), pos: 0 };
+
+        let mut terms = Vec::new();
+        let mut negative = parser.eat("-");
+        if !negative { parser.eat("+"); }
+        loop {
+            terms.push((negative, parser.term()?));
+            if parser.eat("+") {
+                negative = false;
+            } else if parser.eat("-") {
+                negative = true;
+            } else {
+                break;
+            }
+        }
+
+        let mut min = None;
+        let mut max = None;
+        loop {
+            if parser.eat("min") {
+                min = Some(parser.signed_number()?);
+            } else if parser.eat("max") {
+                max = Some(parser.signed_number()?);
+            } else {
+                break;
+            }
+        }
+
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("'+', '-', \"min\", \"max\" or the end"));
+        }
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err(format!("min {} is above max {} in \"{}\"", min, max, text));
+            }
+        }
+        Ok(DiceExpr { terms, min, max })
+    }
+}
+
+impl TryFrom<String> for DiceExpr {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<DiceExpr, String> {
+        text.parse()
+    }
+}
+
+impl From<DiceExpr> for String {
+    fn from(expr: DiceExpr) -> String {
+        expr.to_string()
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, expected: &str) -> String {
+        format!("expected {} at position {} in \"{}\"", expected, self.pos + 1, self.text)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `token` (case-insensitively) if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let matches = token.chars().enumerate()
+            .all(|(i, t)| self.chars.get(self.pos + i).is_some_and(|c| c.eq_ignore_ascii_case(&t)));
+        if matches {
+            self.pos += token.chars().count();
+        }
+        matches
+    }
+
+    fn optional_number(&mut self) -> Result<Option<i32>, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse::<i32>().map(Some).map_err(|_| format!("{} is too large in \"{}\"", digits, self.text))
+    }
+
+    fn number(&mut self, what: &str) -> Result<i32, String> {
+        self.optional_number()?.ok_or_else(|| self.error(what))
+    }
+
+    fn signed_number(&mut self) -> Result<i32, String> {
+        let negative = self.eat("-");
+        let value = self.number("a number")?;
+        Ok(if negative { -value } else { value })
+    }
+
+    fn term(&mut self) -> Result<DiceTerm, String> {
+        let count = self.optional_number()?;
+        if !self.eat("d") {
+            return count.map(DiceTerm::Constant).ok_or_else(|| self.error("a number or dice such as 1d6"));
+        }
+        let count = count.unwrap_or(1);
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(format!("dice count must be between 1 and {} in \"{}\"", MAX_DICE, self.text));
+        }
+        let sides = self.number("the number of sides")?;
+        if sides < 1 {
+            return Err(format!("dice need at least one side in \"{}\"", self.text));
+        }
+
+        let mut keep = Keep::All;
+        let mut explode = false;
+        loop {
+            if self.eat("kh") {
+                keep = Keep::Highest(self.number("how many dice to keep")?);
+            } else if self.eat("kl") {
+                keep = Keep::Lowest(self.number("how many dice to keep")?);
+            } else if self.eat("!") {
+                explode = true;
+            } else {
+                break;
+            }
+        }
+        if let Keep::Highest(n) | Keep::Lowest(n) = keep {
+            if n < 1 || n > count {
+                return Err(format!("can only keep between 1 and {} of {}d{} in \"{}\"", count, count, sides, self.text));
+            }
+        }
+        if explode && sides < 2 {
+            return Err(format!("a d{} can't explode in \"{}\"", sides, self.text));
+        }
+        Ok(DiceTerm::Dice { count, sides, keep, explode })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> DiceExpr {
+        text.parse().unwrap_or_else(|e| panic!("{} should parse: {}", text, e))
+    }
+
+    fn dice(count: i32, sides: i32, keep: Keep, explode: bool) -> DiceTerm {
+        DiceTerm::Dice { count, sides, keep, explode }
+    }
+
+    #[test]
+    fn parses_each_form() {
+        assert_eq!(parse("2d6").terms, vec![(false, dice(2, 6, Keep::All, false))]);
+        assert_eq!(parse("d8").terms, vec![(false, dice(1, 8, Keep::All, false))]);
+        assert_eq!(parse("4d6kh3").terms, vec![(false, dice(4, 6, Keep::Highest(3), false))]);
+        assert_eq!(parse("2d20KL1").terms, vec![(false, dice(2, 20, Keep::Lowest(1), false))]);
+        assert_eq!(parse("1d6!").terms, vec![(false, dice(1, 6, Keep::All, true))]);
+        assert_eq!(parse("3d6kh2!").terms, vec![(false, dice(3, 6, Keep::Highest(2), true))]);
+        assert_eq!(parse("5").terms, vec![(false, DiceTerm::Constant(5))]);
+        assert_eq!(parse("-3").terms, vec![(true, DiceTerm::Constant(3))]);
+        assert_eq!(parse(" 2d6 + 1d4 - 3 ").terms, vec![
+            (false, dice(2, 6, Keep::All, false)),
+            (false, dice(1, 4, Keep::All, false)),
+            (true, DiceTerm::Constant(3)),
+        ]);
+
+        let clamped = parse("3d6 - 2 min 1");
+        assert_eq!((clamped.min, clamped.max), (Some(1), None));
+        assert_eq!(parse("1d4 max 3").max, Some(3));
+        assert_eq!(parse("1d4-5 min -2").min, Some(-2));
+        assert_eq!(parse("1d4 max 3 min 2"), DiceExpr { terms: vec![(false, dice(1, 4, Keep::All, false))], min: Some(2), max: Some(3) });
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let error = |text: &str| text.parse::<DiceExpr>().unwrap_err();
+        assert_eq!(error(""), "expected a number or dice such as 1d6 at position 1 in \"\"");
+        assert_eq!(error("2d"), "expected the number of sides at position 3 in \"2d\"");
+        assert_eq!(error("2d6+"), "expected a number or dice such as 1d6 at position 5 in \"2d6+\"");
+        assert_eq!(error("2d6 x"), "expected '+', '-', \"min\", \"max\" or the end at position 5 in \"2d6 x\"");
+        assert_eq!(error("2d6 min"), "expected a number at position 8 in \"2d6 min\"");
+        assert_eq!(error("4d6kh"), "expected how many dice to keep at position 6 in \"4d6kh\"");
+        assert_eq!(error("0d6"), "dice count must be between 1 and 100 in \"0d6\"");
+        assert_eq!(error("101d6"), "dice count must be between 1 and 100 in \"101d6\"");
+        assert_eq!(error("2d0"), "dice need at least one side in \"2d0\"");
+        assert_eq!(error("3d6kh4"), "can only keep between 1 and 3 of 3d6 in \"3d6kh4\"");
+        assert_eq!(error("3d6kl0"), "can only keep between 1 and 3 of 3d6 in \"3d6kl0\"");
+        assert_eq!(error("1d1!"), "a d1 can't explode in \"1d1!\"");
+        assert_eq!(error("2d6 min 5 max 3"), "min 5 is above max 3 in \"2d6 min 5 max 3\"");
+        assert_eq!(error("99999999999d6"), "99999999999 is too large in \"99999999999d6\"");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for text in ["2d6", "1d8+2", "1d4-1", "4d6kh3", "2d20kl1", "1d6!", "3d6kh2!", "2d6+1d4-3", "-3", "0", "3d6-2 min 1", "1d4 max 3", "1d6 min 2 max 5"] {
+            let expr = parse(text);
+            assert_eq!(expr.to_string(), text);
+            assert_eq!(parse(&expr.to_string()), expr);
+        }
+        assert_eq!(parse(" D6 +  2 MIN 3 ").to_string(), "1d6+2 min 3");
+        assert_eq!(DiceExpr::new(1, 8, -1).to_string(), "1d8-1");
+
+        let json = serde_json::to_string(&parse("4d6kh3+1")).unwrap();
+        assert_eq!(json, "\"4d6kh3+1\"");
+        assert_eq!(serde_json::from_str::<DiceExpr>(&json).unwrap(), parse("4d6kh3+1"));
+        assert!(serde_json::from_str::<DiceExpr>("\"2d\"").is_err());
+    }
+
+    fn roll_range(text: &str) -> (i32, i32) {
+        let expr = parse(text);
+        let mut rng = RandomNumberGenerator::seeded(42);
+        let rolls: Vec<i32> = (0..2000).map(|_| expr.roll(&mut rng)).collect();
+        (*rolls.iter().min().unwrap(), *rolls.iter().max().unwrap())
+    }
+
+    #[test]
+    fn rolls_stay_in_bounds() {
+        assert_eq!(roll_range("2d6+3"), (5, 15));
+        assert_eq!(roll_range("4d6kh3"), (3, 18));
+        assert_eq!(roll_range("2d20kl1"), (1, 20));
+        assert_eq!(roll_range("1d4-1d4"), (-3, 3));
+        assert_eq!(roll_range("7"), (7, 7));
+        assert_eq!(roll_range("1d6-4 min 1"), (1, 2));
+        assert_eq!(roll_range("3d6 max 4"), (3, 4));
+
+        let (low, high) = roll_range("1d6!");
+        assert_eq!(low, 1);
+        assert!(high > 6 && high <= 6 * (MAX_EXPLOSIONS + 1));
+    }
+
+    #[test]
+    fn rolls_repeat_for_a_seed() {
+        let expr = parse("3d6kh2!+1d4");
+        let mut first = RandomNumberGenerator::seeded(7);
+        let mut second = RandomNumberGenerator::seeded(7);
+        let a: Vec<i32> = (0..50).map(|_| expr.roll(&mut first)).collect();
+        let b: Vec<i32> = (0..50).map(|_| expr.roll(&mut second)).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn add_bonus_folds_into_constants() {
+        let mut expr = DiceExpr::new(2, 6, 0);
+        assert_eq!(expr.to_string(), "2d6");
+        expr.add_bonus(3);
+        assert_eq!(expr.to_string(), "2d6+3");
+        expr.add_bonus(-5);
+        assert_eq!(expr.to_string(), "2d6-2");
+        expr.add_bonus(2);
+        assert_eq!(expr.to_string(), "2d6");
+        expr.add_bonus(0);
+        assert_eq!(expr.to_string(), "2d6");
+
+        let mut constant = parse("3");
+        constant.add_bonus(-3);
+        assert_eq!(constant.to_string(), "0");
+
+        let mut clamped = parse("1d4 min 2");
+        clamped.add_bonus(1);
+        assert_eq!(clamped.to_string(), "1d4+1 min 2");
+    }
+
+    #[test]
+    fn add_sides_changes_every_die() {
+        let mut expr = parse("1d4+2d6kh1-1");
+        expr.add_sides(2);
+        assert_eq!(expr.to_string(), "1d6+2d8kh1-1");
+        expr.add_sides(-10);
+        assert_eq!(expr.to_string(), "1d2+2d2kh1-1");
+    }
+}
@@ -0,0 +1,39 @@
+use specs::prelude::*;
+use crate::{CursedItem, Equipped, InBackpack, Name, MasterDungeonMap, gamelog::GameLog};
+
+/// Cursed items the target is wearing or carrying.
+pub fn cursed_items(ecs: &World, target: Entity) -> Vec<Entity> {
+    let entities = ecs.entities();
+    let cursed = ecs.read_storage::<CursedItem>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    (&entities, &cursed).join()
+        .filter(|(item, _)| equipped.get(*item).is_some_and(|e| e.owner == target)
+            || backpack.get(*item).is_some_and(|b| b.owner == target))
+        .map(|(item, _)| item)
+        .collect()
+}
+
+/// Lifts the curse from everything the target is wearing or carrying.
+pub fn remove_curse(ecs: &mut World, target: Entity) {
+    let lifted = cursed_items(ecs, target);
+
+    let mut cursed = ecs.write_storage::<CursedItem>();
+    for item in lifted.iter() {
+        cursed.remove(*item);
+    }
+
+    if target == *ecs.fetch::<Entity>() {
+        let names = ecs.read_storage::<Name>();
+        let dm = ecs.fetch::<MasterDungeonMap>();
+        let mut log = ecs.fetch_mut::<GameLog>();
+        if lifted.is_empty() {
+            log.entries.push("You feel a brief warmth, but nothing else happens.".to_string());
+        }
+        for item in lifted.iter() {
+            if let Some(name) = names.get(*item) {
+                log.entries.push(format!("The curse on your {} is lifted.", dm.display_name(&name.name)));
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use specs::prelude::*;
+use crate::{Pools, SufferDamage, DamageType, Resistances, Equipped, Wearable, Name, gamelog::GameLog};
+use crate::gamesystem::{resisted_damage, ARMOR_REDUCES_DAMAGE};
+use super::{EffectSpawner, EffectType, Targets, add_effect};
+
+pub fn inflict_damage(ecs: &mut World, damage: &EffectSpawner, target: Entity) {
+    if let EffectType::Damage { amount, damage_type } = damage.effect_type {
+        if ecs.read_storage::<Pools>().get(target).is_none() { return; }
+        let amount = match damage_type {
+            Some(damage_type) => damage_after_defenses(ecs, target, amount, damage_type),
+            None => amount
+        };
+        let player_entity = *ecs.fetch::<Entity>();
+        let mut suffer_damage = ecs.write_storage::<SufferDamage>();
+        SufferDamage::new_damage(&mut suffer_damage, target, amount, damage.creator == Some(player_entity));
+
+        add_effect(None, EffectType::Particle {
+            glyph: rltk::to_cp437('‼'),
+            fg: rltk::RGB::named(rltk::ORANGE),
+            bg: rltk::RGB::named(rltk::BLACK),
+            lifespan: 200.0
+        }, Targets::Single { target });
+    }
+}
+
+/// What `amount` of damage comes to once the target's defenses have been applied, without
+/// reporting anything.
+pub fn mitigated_damage(ecs: &World, target: Entity, amount: i32, damage_type: Option<DamageType>) -> i32 {
+    match damage_type {
+        Some(damage_type) => defenses(ecs, target, amount, damage_type).1,
+        None => amount
+    }
+}
+
+/// `mitigated_damage` for systems, which hold the storages rather than the `World`.
+pub fn mitigated_damage_in(resistances: &ReadStorage<Resistances>, equipped: &ReadStorage<Equipped>, wearables: &ReadStorage<Wearable>,
+    target: Entity, amount: i32, damage_type: DamageType) -> i32
+{
+    storage_defenses(resistances, equipped, wearables, target, amount, damage_type).1
+}
+
+fn defenses(ecs: &World, target: Entity, amount: i32, damage_type: DamageType) -> (i32, i32) {
+    storage_defenses(&ecs.read_storage::<Resistances>(), &ecs.read_storage::<Equipped>(), &ecs.read_storage::<Wearable>(), target, amount, damage_type)
+}
+
+/// Typed damage is halved by a resistance and doubled by a vulnerability, whether innate or
+/// from something worn; worn armor then soaks up physical damage. Returns the damage after
+/// resistances, and after armor as well.
+fn storage_defenses(resistances: &ReadStorage<Resistances>, equipped: &ReadStorage<Equipped>, wearables: &ReadStorage<Wearable>,
+    target: Entity, amount: i32, damage_type: DamageType) -> (i32, i32)
+{
+    let sources: Vec<&Resistances> = resistances.get(target).into_iter()
+        .chain((equipped, resistances).join().filter(|(worn, _)| worn.owner == target).map(|(_, r)| r))
+        .collect();
+    let resistant = sources.iter().any(|r| r.resistances.contains(&damage_type));
+    let vulnerable = sources.iter().any(|r| r.vulnerabilities.contains(&damage_type));
+    let resisted = resisted_damage(amount, resistant, vulnerable);
+
+    let reduction = if ARMOR_REDUCES_DAMAGE && damage_type.is_physical() {
+        (equipped, wearables).join()
+            .filter(|(worn, _)| worn.owner == target)
+            .map(|(_, armor)| armor.damage_reduction)
+            .sum()
+    } else {
+        0
+    };
+    (resisted, i32::max(0, resisted - reduction))
+}
+
+fn damage_after_defenses(ecs: &World, target: Entity, amount: i32, damage_type: DamageType) -> i32 {
+    let (resisted, final_damage) = defenses(ecs, target, amount, damage_type);
+
+    if let Some(name) = ecs.read_storage::<Name>().get(target) {
+        let mut log = ecs.fetch_mut::<GameLog>();
+        if resisted < amount {
+            log.entries.push(format!("{} resists the {} damage, taking {}.", name.name, damage_type.name(), resisted));
+        } else if resisted > amount {
+            log.entries.push(format!("{} is vulnerable to {} damage, taking {}!", name.name, damage_type.name(), resisted));
+        }
+        if final_damage < resisted {
+            log.entries.push(format!("{}'s armor absorbs {} of it.", name.name, resisted - final_damage));
+        }
+    }
+
+    final_damage
+}
+
+pub fn heal_damage(ecs: &mut World, heal: &EffectSpawner, target: Entity) {
+    if let EffectType::Healing { amount } = heal.effect_type {
+        let mut pools = ecs.write_storage::<Pools>();
+        if let Some(pool) = pools.get_mut(target) {
+            pool.hit_points.current = i32::min(pool.hit_points.max, pool.hit_points.current + amount);
+            add_effect(None, EffectType::Particle {
+                glyph: rltk::to_cp437('♥'),
+                fg: rltk::RGB::named(rltk::GREEN),
+                bg: rltk::RGB::named(rltk::BLACK),
+                lifespan: 200.0
+            }, Targets::Single { target });
+        }
+    }
+}
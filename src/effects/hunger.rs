@@ -0,0 +1,12 @@
+use specs::prelude::*;
+use crate::{HungerClock, HungerState};
+use super::{EffectSpawner, EffectType};
+
+pub fn well_fed(ecs: &mut World, food: &EffectSpawner, target: Entity) {
+    if let EffectType::WellFed { nutrition } = food.effect_type {
+        if let Some(hc) = ecs.write_storage::<HungerClock>().get_mut(target) {
+            hc.state = HungerState::WellFed;
+            hc.duration = nutrition;
+        }
+    }
+}
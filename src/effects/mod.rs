@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use specs::prelude::*;
+use rltk::{FontCharType, RGB};
+use crate::{StatusEffectTemplate, DamageType};
+
+mod targeting;
+mod damage;
+mod particles;
+mod hunger;
+mod status;
+mod movement;
+mod triggers;
+mod curses;
+
+pub use targeting::*;
+pub use curses::cursed_items;
+pub use damage::mitigated_damage_in;
+
+lazy_static! {
+    static ref EFFECT_QUEUE : Mutex<VecDeque<EffectSpawner>> = Mutex::new(VecDeque::new());
+}
+
+pub enum EffectType {
+    Damage { amount: i32, damage_type: Option<DamageType> },
+    Healing { amount: i32 },
+    Status { status: StatusEffectTemplate },
+    WellFed { nutrition: i32 },
+    Particle { glyph: FontCharType, fg: RGB, bg: RGB, lifespan: f32 },
+    RandomTeleport,
+    RemoveCurse,
+    ItemUse { item: Entity },
+    TriggerFire { trigger: Entity },
+}
+
+#[derive(Clone)]
+pub enum Targets {
+    Single { target: Entity },
+    TargetList { targets: Vec<Entity> },
+    Tile { tile_index: i32 },
+    Tiles { tiles: Vec<i32> },
+}
+
+pub struct EffectSpawner {
+    pub creator: Option<Entity>,
+    pub effect_type: EffectType,
+    pub targets: Targets,
+}
+
+/// Queues an effect; it will be applied when the queue is next run.
+pub fn add_effect(creator: Option<Entity>, effect_type: EffectType, targets: Targets) {
+    EFFECT_QUEUE.lock().unwrap().push_back(EffectSpawner { creator, effect_type, targets });
+}
+
+/// Applies every queued effect in order. Effects may queue further effects,
+/// which are resolved in the same pass.
+pub fn run_effects_queue(ecs: &mut World) {
+    loop {
+        let effect = EFFECT_QUEUE.lock().unwrap().pop_front();
+        match effect {
+            None => break,
+            Some(effect) => target_applicator(ecs, &effect)
+        }
+    }
+}
+
+fn target_applicator(ecs: &mut World, effect: &EffectSpawner) {
+    match &effect.effect_type {
+        EffectType::ItemUse { item } => triggers::item_trigger(effect.creator, *item, &effect.targets, ecs),
+        EffectType::TriggerFire { trigger } => triggers::trigger(effect.creator, *trigger, &effect.targets, ecs),
+        _ => match &effect.targets {
+            Targets::Tile { tile_index } => affect_tile(ecs, effect, *tile_index),
+            Targets::Tiles { tiles } => tiles.iter().for_each(|tile_index| affect_tile(ecs, effect, *tile_index)),
+            Targets::Single { target } => affect_entity(ecs, effect, *target),
+            Targets::TargetList { targets } => targets.iter().for_each(|entity| affect_entity(ecs, effect, *entity)),
+        }
+    }
+}
+
+fn tile_effect_hits_entities(effect: &EffectType) -> bool {
+    !matches!(effect, EffectType::Particle { .. })
+}
+
+fn affect_tile(ecs: &mut World, effect: &EffectSpawner, tile_index: i32) {
+    if tile_effect_hits_entities(&effect.effect_type) {
+        for entity in entities_in_tile(tile_index) {
+            affect_entity(ecs, effect, entity);
+        }
+    }
+
+    if let EffectType::Particle { .. } = effect.effect_type {
+        particles::particle_to_tile(ecs, tile_index, effect);
+    }
+}
+
+fn affect_entity(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    match &effect.effect_type {
+        EffectType::Damage { .. } => damage::inflict_damage(ecs, effect, target),
+        EffectType::Healing { .. } => damage::heal_damage(ecs, effect, target),
+        EffectType::Status { .. } => status::apply_status(ecs, effect, target),
+        EffectType::WellFed { .. } => hunger::well_fed(ecs, effect, target),
+        EffectType::Particle { .. } => {
+            if let Some(tile_index) = entity_position(ecs, target) {
+                particles::particle_to_tile(ecs, tile_index, effect);
+            }
+        }
+        EffectType::RandomTeleport => movement::random_teleport(ecs, target),
+        EffectType::RemoveCurse => curses::remove_curse(ecs, target),
+        _ => {}
+    }
+}
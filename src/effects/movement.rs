@@ -0,0 +1,59 @@
+use specs::prelude::*;
+use rltk::{Point, RandomNumberGenerator};
+use crate::{Map, Position, Viewshed, tile_walkable, gamelog::GameLog};
+use super::{EffectType, Targets, add_effect};
+
+pub fn random_teleport(ecs: &mut World, target: Entity) {
+    let destination = {
+        let map = ecs.fetch::<Map>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        find_teleport_destination(&map, &mut rng)
+    };
+    let destination = match destination {
+        None => return,
+        Some(destination) => destination
+    };
+
+    let old_index = match super::entity_position(ecs, target) {
+        None => return,
+        Some(index) => index as usize
+    };
+    crate::spatial::move_entity(target, old_index, destination);
+
+    let map_width = ecs.fetch::<Map>().width;
+    let new_pos = Point::new(destination as i32 % map_width, destination as i32 / map_width);
+    if let Some(pos) = ecs.write_storage::<Position>().get_mut(target) {
+        pos.x = new_pos.x;
+        pos.y = new_pos.y;
+    }
+    if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(target) {
+        viewshed.dirty = true;
+    }
+
+    let teleport_flash = |tile_index: i32| add_effect(None, EffectType::Particle {
+        glyph: rltk::to_cp437('*'),
+        fg: rltk::RGB::named(rltk::CYAN),
+        bg: rltk::RGB::named(rltk::BLACK),
+        lifespan: 200.0
+    }, Targets::Tile { tile_index });
+    teleport_flash(old_index as i32);
+    teleport_flash(destination as i32);
+
+    if target == *ecs.fetch::<Entity>() {
+        let mut player_pos = ecs.write_resource::<Point>();
+        *player_pos = new_pos;
+        ecs.fetch_mut::<GameLog>().entries.push("The world spins around you!".to_string());
+    }
+}
+
+fn find_teleport_destination(map: &Map, rng: &mut RandomNumberGenerator) -> Option<usize> {
+    for _attempt in 0..100 {
+        let x = rng.roll_dice(1, map.width - 2);
+        let y = rng.roll_dice(1, map.height - 2);
+        let index = map.xy_index(x, y);
+        if tile_walkable(map.tiles[index]) && !crate::spatial::is_blocked(index) {
+            return Some(index);
+        }
+    }
+    None
+}
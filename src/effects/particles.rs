@@ -0,0 +1,12 @@
+use specs::prelude::*;
+use crate::Map;
+use crate::particle_system::ParticleBuilder;
+use super::{EffectSpawner, EffectType};
+
+pub fn particle_to_tile(ecs: &mut World, tile_index: i32, effect: &EffectSpawner) {
+    if let EffectType::Particle { glyph, fg, bg, lifespan } = effect.effect_type {
+        let map = ecs.fetch::<Map>();
+        let mut particle_builder = ecs.fetch_mut::<ParticleBuilder>();
+        particle_builder.request(tile_index % map.width, tile_index / map.width, fg, bg, glyph, lifespan);
+    }
+}
@@ -0,0 +1,17 @@
+use specs::prelude::*;
+use crate::Pools;
+use crate::status_effect_system::add_status_effect;
+use super::{EffectSpawner, EffectType, Targets, add_effect};
+
+pub fn apply_status(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::Status { status } = &effect.effect_type {
+        if ecs.read_storage::<Pools>().get(target).is_none() { return; }
+        add_status_effect(ecs, target, status);
+        add_effect(None, EffectType::Particle {
+            glyph: rltk::to_cp437('?'),
+            fg: rltk::RGB::named(rltk::MAGENTA),
+            bg: rltk::RGB::named(rltk::BLACK),
+            lifespan: 200.0
+        }, Targets::Single { target });
+    }
+}
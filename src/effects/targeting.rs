@@ -0,0 +1,42 @@
+use specs::prelude::*;
+use rltk::Point;
+use crate::{Map, Position, AreaOfEffect};
+use super::Targets;
+
+pub fn entity_position(ecs: &World, target: Entity) -> Option<i32> {
+    let map = ecs.fetch::<Map>();
+    ecs.read_storage::<Position>().get(target).map(|pos| map.xy_index(pos.x, pos.y) as i32)
+}
+
+pub fn entities_in_tile(tile_index: i32) -> Vec<Entity> {
+    let mut content = Vec::new();
+    crate::spatial::for_each_tile_content(tile_index as usize, |entity| content.push(entity));
+    content
+}
+
+pub fn aoe_tiles(map: &Map, target: Point, radius: i32) -> Vec<i32> {
+    let mut blast_tiles = rltk::field_of_view(target, radius, map);
+    blast_tiles.retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
+    blast_tiles.iter().map(|p| map.xy_index(p.x, p.y) as i32).collect()
+}
+
+/// Works out who an item (or spell) used by `user` affects: the user themselves if no
+/// target was chosen, otherwise the chosen tile - or the area around it.
+pub fn item_targets(map: &Map, user: Entity, target: Option<Point>, area_of_effect: Option<&AreaOfEffect>) -> Targets {
+    match target {
+        None => Targets::Single { target: user },
+        Some(target) => match area_of_effect {
+            None => Targets::Tile { tile_index: map.xy_index(target.x, target.y) as i32 },
+            Some(aoe) => Targets::Tiles { tiles: aoe_tiles(map, target, aoe.radius) }
+        }
+    }
+}
+
+pub fn target_entities(targets: &Targets) -> Vec<Entity> {
+    match targets {
+        Targets::Single { target } => vec![*target],
+        Targets::TargetList { targets } => targets.clone(),
+        Targets::Tile { tile_index } => entities_in_tile(*tile_index),
+        Targets::Tiles { tiles } => tiles.iter().flat_map(|tile_index| entities_in_tile(*tile_index)).collect()
+    }
+}
@@ -0,0 +1,148 @@
+use specs::prelude::*;
+use crate::{Consumable, ProvidesFood, MagicMapper, TeachesSpell, ProvidesHealing, InflictsDamage, InflictsStatus,
+            RandomTeleport, SingleActivation, AreaOfEffect, Name, Pools, RunState, MasterDungeonMap, ProvidesRemoveCurse,
+            gamelog::GameLog};
+use super::{EffectType, Targets, add_effect, target_entities};
+use super::damage::mitigated_damage;
+
+/// An item (or spell) has been used: apply everything it does, and use it up if it is consumable.
+pub fn item_trigger(creator: Option<Entity>, item: Entity, targets: &Targets, ecs: &mut World) {
+    // Using an item reveals what it is
+    if creator == Some(*ecs.fetch::<Entity>()) {
+        if let Some(name) = ecs.read_storage::<Name>().get(item) {
+            let unidentified_name = ecs.fetch::<MasterDungeonMap>().display_name(&name.name);
+            if ecs.write_resource::<MasterDungeonMap>().identify(&name.name) {
+                ecs.fetch_mut::<GameLog>().entries.push(format!("You identify the {} as {}.", unidentified_name, name.name));
+            }
+        }
+    }
+
+    let did_something = event_trigger(creator, item, targets, ecs);
+    if did_something && ecs.read_storage::<Consumable>().get(item).is_some() {
+        ecs.entities().delete(item).expect("Delete failed");
+    }
+}
+
+/// Something stepped on a trap: apply its effects, and remove it if it only fires once.
+pub fn trigger(creator: Option<Entity>, trigger: Entity, targets: &Targets, ecs: &mut World) {
+    let did_something = event_trigger(creator, trigger, targets, ecs);
+    if did_something && ecs.read_storage::<SingleActivation>().get(trigger).is_some() {
+        ecs.entities().delete(trigger).expect("Unable to delete trap");
+    }
+}
+
+fn event_trigger(creator: Option<Entity>, entity: Entity, targets: &Targets, ecs: &mut World) -> bool {
+    let mut did_something = false;
+
+    // Show the blast area
+    if let Targets::Tiles { tiles } = targets {
+        if ecs.read_storage::<AreaOfEffect>().get(entity).is_some() {
+            add_effect(None, EffectType::Particle {
+                glyph: rltk::to_cp437('░'),
+                fg: rltk::RGB::named(rltk::ORANGE),
+                bg: rltk::RGB::named(rltk::BLACK),
+                lifespan: 200.0
+            }, Targets::Tiles { tiles: tiles.clone() });
+        }
+    }
+
+    // Food
+    if let Some(food) = ecs.read_storage::<ProvidesFood>().get(entity) {
+        add_effect(creator, EffectType::WellFed { nutrition: food.nutrition }, targets.clone());
+        if creator == Some(*ecs.fetch::<Entity>()) {
+            let names = ecs.read_storage::<Name>();
+            ecs.fetch_mut::<GameLog>().entries.push(format!("You eat the {}.", ecs.fetch::<MasterDungeonMap>().display_name(&names.get(entity).unwrap().name)));
+        }
+        did_something = true;
+    }
+
+    // Magic mapping
+    if ecs.read_storage::<MagicMapper>().get(entity).is_some() {
+        if creator == Some(*ecs.fetch::<Entity>()) {
+            ecs.fetch_mut::<GameLog>().entries.push("The map is revealed to you!".to_string());
+            *ecs.write_resource::<RunState>() = RunState::MagicMapReveal { row: 0 };
+        }
+        did_something = true;
+    }
+
+    // Spell books
+    let teaches_spell = ecs.read_storage::<TeachesSpell>().get(entity).map(|teacher| teacher.spell.clone());
+    if let (Some(spell), Some(learner)) = (teaches_spell, creator) {
+        // A book that teaches nothing new isn't used up
+        if crate::spell_system::learn_spell(ecs, learner, &spell) {
+            did_something = true;
+        }
+    }
+
+    // Healing
+    if let Some(heal) = ecs.read_storage::<ProvidesHealing>().get(entity) {
+        add_effect(creator, EffectType::Healing { amount: heal.heal_amount }, targets.clone());
+        for target in target_entities(targets) {
+            log_use(ecs, creator, entity, target, &format!(", healing {} hp", heal.heal_amount));
+        }
+        did_something = true;
+    }
+
+    // Damage
+    if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(entity) {
+        add_effect(creator, EffectType::Damage { amount: damage.damage, damage_type: damage.damage_type }, targets.clone());
+        for target in target_entities(targets) {
+            // Report what actually gets through the target's resistances and armor
+            let amount = mitigated_damage(ecs, target, damage.damage, damage.damage_type);
+            let damage_text = match damage.damage_type {
+                Some(damage_type) => format!(", inflicting {} {} damage", amount, damage_type.name()),
+                None => format!(", inflicting {} hp", amount)
+            };
+            log_use(ecs, creator, entity, target, &damage_text);
+        }
+        did_something = true;
+    }
+
+    // Status effects
+    if let Some(inflicts) = ecs.read_storage::<InflictsStatus>().get(entity) {
+        add_effect(creator, EffectType::Status { status: inflicts.status.clone() }, targets.clone());
+        for target in target_entities(targets) {
+            let pronoun = if target == *ecs.fetch::<Entity>() { "you" } else { "them" };
+            log_use(ecs, creator, entity, target, &format!(", leaving {} {}", pronoun, inflicts.status.name.to_lowercase()));
+        }
+        did_something = true;
+    }
+
+    // Teleportation
+    if ecs.read_storage::<RandomTeleport>().get(entity).is_some() {
+        add_effect(creator, EffectType::RandomTeleport, targets.clone());
+        did_something = true;
+    }
+
+    // Curse removal
+    if ecs.read_storage::<ProvidesRemoveCurse>().get(entity).is_some() {
+        add_effect(creator, EffectType::RemoveCurse, targets.clone());
+        did_something = true;
+    }
+
+    did_something
+}
+
+/// Logs the outcome of using an item on a target, if the player was involved.
+fn log_use(ecs: &World, creator: Option<Entity>, item: Entity, target: Entity, outcome: &str) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let creator = match creator {
+        None => return,
+        Some(creator) => creator
+    };
+    if creator != player_entity && target != player_entity { return; }
+
+    if ecs.read_storage::<Pools>().get(target).is_none() { return; }
+    let names = ecs.read_storage::<Name>();
+    let item_name = ecs.fetch::<MasterDungeonMap>().display_name(&names.get(item).unwrap().name);
+    let target_name = if target == player_entity { "you" } else { &names.get(target).unwrap().name };
+    let message = if creator == player_entity {
+        if target == creator { format!("You use {}{}.", item_name, outcome) }
+        else { format!("You use {} on {}{}.", item_name, target_name, outcome) }
+    } else {
+        let creator_name = &names.get(creator).unwrap().name;
+        if target == creator { format!("{} uses {}{}.", creator_name, item_name, outcome) }
+        else { format!("{} uses {} on {}{}.", creator_name, item_name, target_name, outcome) }
+    };
+    ecs.fetch_mut::<GameLog>().entries.push(message);
+}
@@ -1,3 +1,90 @@
+use crate::DamageType;
+
 pub struct GameLog {
     pub entries: Vec<String>,
-}
\ No newline at end of file
+}
+
+/// How many attack records the combat history keeps before dropping the oldest.
+const MAX_ATTACK_RECORDS : usize = 500;
+
+#[derive(Clone, Copy)]
+pub enum AttackOutcome {
+    Hit { damage: i32, damage_type: DamageType, critical: bool },
+    Blocked,
+    Fumble,
+    Miss,
+}
+
+/// Every term that went into one attack roll, and the armor class it was compared against.
+#[derive(Clone)]
+pub struct AttackRecord {
+    pub attacker : String,
+    pub target : String,
+    pub natural_roll : i32,
+    pub attribute_bonus : i32,
+    pub skill_bonus : i32,
+    pub weapon_bonus : i32,
+    pub status_bonus : i32,
+    pub hand_bonus : i32,
+    pub base_armor_class : i32,
+    pub armor_quickness_bonus : i32,
+    pub armor_skill_bonus : i32,
+    pub armor_item_bonus : i32,
+    pub armor_shield_bonus : i32,
+    pub outcome : AttackOutcome,
+}
+
+impl AttackRecord {
+    pub fn hit_roll(&self) -> i32 {
+        self.natural_roll + self.attribute_bonus + self.skill_bonus + self.weapon_bonus + self.status_bonus + self.hand_bonus
+    }
+
+    pub fn armor_class(&self) -> i32 {
+        self.base_armor_class + self.armor_quickness_bonus + self.armor_skill_bonus + self.armor_item_bonus + self.armor_shield_bonus
+    }
+
+    /// One-line breakdown of the roll against the armor class, leaving out terms that are zero,
+    /// e.g. "d20 12 attr+1 skill+1 = 14 vs AC 10 qck+1 def+1 armor+1 = 13".
+    pub fn breakdown(&self) -> String {
+        let terms = |terms: &[(&str, i32)]| -> String {
+            terms.iter().filter(|(_, value)| *value != 0).map(|(name, value)| format!(" {}{:+}", name, value)).collect()
+        };
+        let hit_terms = terms(&[("attr", self.attribute_bonus), ("skill", self.skill_bonus), ("wpn", self.weapon_bonus),
+            ("fed", self.status_bonus), ("hand", self.hand_bonus)]);
+        let armor_terms = terms(&[("qck", self.armor_quickness_bonus), ("def", self.armor_skill_bonus),
+            ("armor", self.armor_item_bonus), ("shield", self.armor_shield_bonus)]);
+        format!("d20 {}{} = {} vs AC {}{} = {}", self.natural_roll, hit_terms, self.hit_roll(),
+            self.base_armor_class, armor_terms, self.armor_class())
+    }
+
+    pub fn outcome_text(&self) -> String {
+        match self.outcome {
+            AttackOutcome::Hit { damage, damage_type, critical } => {
+                format!("{}hit for {} {}", if critical { "critical " } else { "" }, damage, damage_type.name())
+            }
+            AttackOutcome::Blocked => "blocked".to_string(),
+            AttackOutcome::Fumble => "fumbled".to_string(),
+            AttackOutcome::Miss => "missed".to_string(),
+        }
+    }
+}
+
+/// Attack records for auditing combat balance. With `verbose` set, each attack's breakdown
+/// is also printed to the game log.
+#[derive(Default)]
+pub struct CombatLog {
+    pub records: Vec<AttackRecord>,
+    pub verbose: bool,
+}
+
+impl CombatLog {
+    /// Stores the record, and returns its breakdown if it should also go to the game log.
+    pub fn record(&mut self, record: AttackRecord) -> Option<String> {
+        let breakdown = if self.verbose { Some(format!("  {}", record.breakdown())) } else { None };
+        self.records.push(record);
+        if self.records.len() > MAX_ATTACK_RECORDS {
+            self.records.remove(0);
+        }
+        breakdown
+    }
+}
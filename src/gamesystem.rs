@@ -1,4 +1,4 @@
-use crate::{Skill, Skills};
+use crate::{ActionType, Skill, Skills};
 
 pub fn attr_bonus(value: i32) -> i32 {
     (value-10)/2 // See: https://roll20.net/compendium/dnd5e/Ability%20Scores#content
@@ -28,10 +28,288 @@ pub fn mana_at_level(intelligence: i32, level: i32) -> i32 {
     mana_per_level(intelligence) * level
 }
 
+/// Experience needed to advance past `level`.
+pub fn xp_to_next_level(level: i32) -> i32 {
+    level * 1000
+}
+
+pub const ATTRIBUTE_POINTS_PER_LEVEL : i32 = 1;
+pub const SKILL_POINTS_PER_LEVEL : i32 = 2;
+
+/// When set, successful skill checks count towards raising that skill.
+pub const LEARN_BY_USE : bool = true;
+
+/// Successful uses needed to raise a skill from `level` to the next.
+pub fn practice_to_advance(level: i32) -> i32 {
+    10 * i32::max(1, level)
+}
+
+/// Records a successful use of `skill`, returning its new level if that was enough to advance it.
+pub fn practice_skill(skills: &mut Skills, skill: Skill) -> Option<i32> {
+    if !LEARN_BY_USE { return None; }
+    let level = skills.skills.get(&skill).copied().unwrap_or(0);
+    let practice = skills.practice.entry(skill.clone()).or_insert(0);
+    *practice += 1;
+    if *practice >= practice_to_advance(level) {
+        *practice = 0;
+        skills.skills.insert(skill, level + 1);
+        Some(level + 1)
+    } else {
+        None
+    }
+}
+
 pub fn skill_bonus(skill : Skill, skills: &Skills) -> i32 {
+    let modifier = skills.modifiers.get(&skill).copied().unwrap_or(0);
     if skills.skills.contains_key(&skill) {
-        skills.skills[&skill]
+        skills.skills[&skill] + modifier
     } else {
-        -4
+        -4 + modifier
+    }
+}
+
+pub fn spell_difficulty(mana_cost: i32) -> i32 {
+    5 + mana_cost
+}
+
+pub fn spell_succeeds(natural_roll: i32, magic_bonus: i32, mana_cost: i32) -> bool {
+    natural_roll != 1 && (natural_roll == 20 || natural_roll + magic_bonus > spell_difficulty(mana_cost))
+}
+
+/// Whether a second one-handed melee weapon can be wielded in the off hand.
+pub const DUAL_WIELDING : bool = true;
+
+/// To-hit adjustment for attacks made with an off-hand weapon.
+pub const OFF_HAND_HIT_PENALTY : i32 = -4;
+
+/// Percentage chance for a shield to block a melee hit, from its own block chance and the
+/// wielder's Shield skill.
+pub fn block_chance(shield_block_chance: i32, shield_skill: i32) -> i32 {
+    i32::min(75, i32::max(0, shield_block_chance + shield_skill * 5))
+}
+
+/// Damage multiplier for a natural 20, unless the weapon specifies its own.
+pub const CRITICAL_HIT_MULTIPLIER : i32 = 2;
+
+/// When set, worn armor's damage reduction is subtracted from incoming physical damage.
+pub const ARMOR_REDUCES_DAMAGE : bool = true;
+
+/// Damage after resistance (halved, rounding down) and vulnerability (doubled); having
+/// both cancels out.
+pub fn resisted_damage(amount: i32, resistant: bool, vulnerable: bool) -> i32 {
+    match (resistant, vulnerable) {
+        (true, false) => amount / 2,
+        (false, true) => amount * 2,
+        _ => amount
+    }
+}
+
+/// Energy and action costs.
+///
+/// Every tick, each entity with `Initiative` gains `energy_per_tick` energy. Once its energy is
+/// zero or more it takes a turn, paying `STANDARD_ACTION_COST` up front. On the following tick the
+/// difference between that and the `action_cost` of what it actually did is settled, so picking
+/// something up brings the next turn round sooner and a move through water later. Entities are
+/// updated in entity order and nothing is rolled, so the turn order is the same for a given seed.
+pub const BASE_ENERGY_PER_TICK : i32 = 10;
+pub const STANDARD_ACTION_COST : i32 = 100;
+
+/// Extra energy every action costs per point of `total_initiative_penalty` (heavy gear, overloading).
+pub const ENERGY_PER_INITIATIVE_PENALTY : f32 = 10.0;
+
+/// Energy gained per tick: the base rate plus the Quickness bonus, less any slow penalty (haste
+/// is a negative penalty), and never below 1.
+pub fn energy_per_tick(quickness_bonus: i32, status_penalty: i32) -> i32 {
+    i32::max(1, BASE_ENERGY_PER_TICK + quickness_bonus - status_penalty)
+}
+
+pub fn base_action_cost(action: ActionType) -> i32 {
+    match action {
+        ActionType::Move | ActionType::Melee | ActionType::Ranged | ActionType::CastSpell
+            | ActionType::UseItem | ActionType::Wait => STANDARD_ACTION_COST,
+        ActionType::Equip => 150,
+        ActionType::PickUp | ActionType::Drop | ActionType::OpenDoor => 50,
+    }
+}
+
+/// Full energy cost of an action. `tile_cost` is the movement cost of the tile moved onto, and
+/// only applies to moves.
+pub fn action_cost(action: ActionType, initiative_penalty: f32, tile_cost: f32) -> i32 {
+    let terrain = if action == ActionType::Move { tile_cost } else { 1.0 };
+    let cost = base_action_cost(action) as f32 * terrain + f32::max(0.0, initiative_penalty) * ENERGY_PER_INITIATIVE_PENALTY;
+    i32::max(1, cost as i32)
+}
+
+/// Energy still owed for an action once its turn has paid `STANDARD_ACTION_COST`; negative for a
+/// refund.
+pub fn settle_action_cost(action: ActionType, initiative_penalty: f32, tile_cost: f32) -> i32 {
+    action_cost(action, initiative_penalty, tile_cost) - STANDARD_ACTION_COST
+}
+
+/// Point-buy character creation: every attribute starts at `POINT_BUY_BASE` and may be raised
+/// to `POINT_BUY_MAX`, spending from a pool of `POINT_BUY_POINTS`.
+pub const POINT_BUY_BASE : i32 = 8;
+pub const POINT_BUY_MAX : i32 = 15;
+pub const POINT_BUY_POINTS : i32 = 27;
+
+/// Total cost of raising an attribute from `POINT_BUY_BASE` to `value`; the last two steps cost double.
+pub fn point_buy_cost(value: i32) -> i32 {
+    let raised = value - POINT_BUY_BASE;
+    raised + i32::max(0, value - 13)
+}
+
+/// Rolled character creation: 4d6, dropping the lowest die.
+pub fn roll_attribute(rng: &mut rltk::RandomNumberGenerator) -> i32 {
+    let mut dice : Vec<i32> = (0..4).map(|_| rng.roll_dice(1, 6)).collect();
+    dice.sort_unstable();
+    dice.iter().skip(1).sum()
+}
+
+/// Quality tiers for generated weapons and armor.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ItemQuality { Crude, Normal, Fine, Masterwork }
+
+/// Rolls the quality of a generated item; the deeper the level, the better the odds.
+pub fn roll_item_quality(rng: &mut rltk::RandomNumberGenerator, depth: i32) -> ItemQuality {
+    match rng.roll_dice(1, 100) + depth * 3 {
+        roll if roll <= 15 => ItemQuality::Crude,
+        roll if roll <= 90 => ItemQuality::Normal,
+        roll if roll <= 110 => ItemQuality::Fine,
+        _ => ItemQuality::Masterwork
+    }
+}
+
+/// Percentage chance that a generated item gains a prefix, and separately a suffix.
+pub fn affix_chance(depth: i32) -> i32 {
+    i32::min(50, 5 + depth * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn skills_with(skill: Skill, level: i32) -> Skills {
+        let mut skills = Skills { skills: HashMap::new(), modifiers: HashMap::new(), practice: HashMap::new() };
+        skills.skills.insert(skill, level);
+        skills
+    }
+
+    #[test]
+    fn xp_curve_grows_with_level() {
+        assert_eq!(xp_to_next_level(1), 1000);
+        assert_eq!(xp_to_next_level(2), 2000);
+        assert_eq!(xp_to_next_level(10), 10000);
+        for level in 1..20 {
+            assert!(xp_to_next_level(level + 1) > xp_to_next_level(level));
+        }
+    }
+
+    #[test]
+    fn practice_raises_a_skill_at_the_threshold() {
+        let mut skills = skills_with(Skill::Melee, 2);
+        let needed = practice_to_advance(2);
+        assert_eq!(needed, 20);
+        for _ in 1..needed {
+            assert_eq!(practice_skill(&mut skills, Skill::Melee), None);
+        }
+        assert_eq!(skills.practice[&Skill::Melee], needed - 1);
+        assert_eq!(practice_skill(&mut skills, Skill::Melee), Some(3));
+        assert_eq!(skills.skills[&Skill::Melee], 3);
+        assert_eq!(skills.practice[&Skill::Melee], 0);
+        assert_eq!(skill_bonus(Skill::Melee, &skills), 3);
+    }
+
+    #[test]
+    fn practice_teaches_an_unknown_skill() {
+        let mut skills = skills_with(Skill::Melee, 1);
+        assert_eq!(skill_bonus(Skill::Shield, &skills), -4);
+        for _ in 1..practice_to_advance(0) {
+            assert_eq!(practice_skill(&mut skills, Skill::Shield), None);
+        }
+        assert_eq!(practice_skill(&mut skills, Skill::Shield), Some(1));
+        assert_eq!(skill_bonus(Skill::Shield, &skills), 1);
+        assert_eq!(skills.skills[&Skill::Melee], 1);
+    }
+
+    #[test]
+    fn energy_per_tick_follows_quickness_and_status() {
+        assert_eq!(energy_per_tick(0, 0), BASE_ENERGY_PER_TICK);
+        assert_eq!(energy_per_tick(2, 0), 12);
+        assert_eq!(energy_per_tick(0, 3), 7);
+        assert_eq!(energy_per_tick(0, -5), 15);
+        assert_eq!(energy_per_tick(-4, 20), 1);
+    }
+
+    #[test]
+    fn action_costs() {
+        assert_eq!(action_cost(ActionType::Move, 0.0, 1.0), STANDARD_ACTION_COST);
+        assert_eq!(action_cost(ActionType::Melee, 0.0, 1.0), STANDARD_ACTION_COST);
+        assert_eq!(action_cost(ActionType::Wait, 0.0, 1.0), STANDARD_ACTION_COST);
+        assert_eq!(action_cost(ActionType::Equip, 0.0, 1.0), 150);
+        assert_eq!(action_cost(ActionType::PickUp, 0.0, 1.0), 50);
+        assert_eq!(action_cost(ActionType::OpenDoor, 0.0, 1.0), 50);
+
+        // Terrain only slows movement
+        assert_eq!(action_cost(ActionType::Move, 0.0, 1.5), 150);
+        assert_eq!(action_cost(ActionType::Melee, 0.0, 1.5), STANDARD_ACTION_COST);
+
+        // Encumbrance adds to everything; a negative penalty is ignored
+        assert_eq!(action_cost(ActionType::Move, 2.0, 1.0), 120);
+        assert_eq!(action_cost(ActionType::Drop, 1.5, 1.0), 65);
+        assert_eq!(action_cost(ActionType::Move, -3.0, 1.0), STANDARD_ACTION_COST);
+        assert_eq!(action_cost(ActionType::Move, 0.0, 0.0), 1);
+    }
+
+    #[test]
+    fn settling_up_charges_the_difference_from_standard() {
+        assert_eq!(settle_action_cost(ActionType::Move, 0.0, 1.0), 0);
+        assert_eq!(settle_action_cost(ActionType::PickUp, 0.0, 1.0), -50);
+        assert_eq!(settle_action_cost(ActionType::Equip, 0.0, 1.0), 50);
+        assert_eq!(settle_action_cost(ActionType::Move, 1.0, 2.0), 110);
+
+        // A turn costs the standard up front, then the settle-up; together that is the full cost
+        for action in [ActionType::Move, ActionType::PickUp, ActionType::Equip] {
+            let mut energy = 0;
+            energy -= STANDARD_ACTION_COST;
+            energy -= settle_action_cost(action, 1.0, 1.5);
+            assert_eq!(energy, -action_cost(action, 1.0, 1.5));
+        }
+    }
+
+    #[test]
+    fn cheaper_actions_bring_the_next_turn_sooner() {
+        let ticks_until_next_turn = |action: ActionType| {
+            let mut energy = -STANDARD_ACTION_COST - settle_action_cost(action, 0.0, 1.0);
+            let mut ticks = 0;
+            while energy < 0 {
+                energy += energy_per_tick(0, 0);
+                ticks += 1;
+            }
+            ticks
+        };
+        assert_eq!(ticks_until_next_turn(ActionType::Move), 10);
+        assert_eq!(ticks_until_next_turn(ActionType::PickUp), 5);
+        assert_eq!(ticks_until_next_turn(ActionType::Equip), 15);
+    }
+
+    #[test]
+    fn point_buy_costs() {
+        assert_eq!(point_buy_cost(POINT_BUY_BASE), 0);
+        assert_eq!(point_buy_cost(9), 1);
+        assert_eq!(point_buy_cost(13), 5);
+        assert_eq!(point_buy_cost(14), 7);
+        assert_eq!(point_buy_cost(POINT_BUY_MAX), 9);
+    }
+
+    #[test]
+    fn point_buy_limits() {
+        assert_eq!((POINT_BUY_BASE, POINT_BUY_MAX, POINT_BUY_POINTS), (8, 15, 27));
+        let spent = |attributes: [i32; 4]| attributes.iter().map(|a| point_buy_cost(*a)).sum::<i32>();
+        assert_eq!(spent([POINT_BUY_BASE; 4]), 0);
+        assert_eq!(spent([POINT_BUY_MAX, POINT_BUY_MAX, POINT_BUY_MAX, POINT_BUY_BASE]), POINT_BUY_POINTS);
+        assert!(spent([POINT_BUY_MAX, POINT_BUY_MAX, POINT_BUY_MAX, 9]) > POINT_BUY_POINTS);
+        assert!(spent([13, 13, 13, 13]) <= POINT_BUY_POINTS);
+        assert!(spent([14, 14, 14, 14]) > POINT_BUY_POINTS);
     }
 }
@@ -4,10 +4,15 @@ use specs::prelude::*;
 use super::{
     Pools, GameLog, Map, Name, Position, Point,
     InBackpack, State, Viewshed, RunState,
-    Equipped
+    Equipped, MasterDungeonMap
 };
 use crate::rex_assets::RexAssets;
-use crate::{camera, Hidden, Attribute, Attributes, Consumable, VendorMode, Item, Vendor, HungerClock, HungerState};
+use crate::gamelog::{CombatLog, AttackOutcome};
+use crate::raws::{RAWS, get_background_list};
+use crate::gamesystem::{attr_bonus, point_buy_cost, roll_attribute, POINT_BUY_BASE, POINT_BUY_MAX, POINT_BUY_POINTS};
+use crate::{camera, Hidden, Attribute, Attributes, Consumable, VendorMode, Item, Vendor, HungerClock, HungerState, Ammunition, KnownSpells,
+            StatusEffect, Duration, Confusion, DamageOverTime, Slow, Skills, Skill, PendingLevelUp,
+            CursedItem, Temple};
 
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     use rltk::to_cp437;
@@ -73,9 +78,16 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     // Equipped
     let equipped = ecs.read_storage::<Equipped>();
     let name = ecs.read_storage::<Name>();
-    for (equipped_by, item_name) in (&equipped, &name).join() {
+    let dm = ecs.fetch::<MasterDungeonMap>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let entities = ecs.entities();
+    for (item_entity, equipped_by) in (&entities, &equipped).join() {
         if equipped_by.owner == *player_entity {
-            ctx.print_color(50, y, white, black, &item_name.name);
+            if let Some(ammo) = ammunition.get(item_entity) {
+                ctx.print_color(50, y, white, black, format!("{} ({})", get_item_display_name(ecs, item_entity), ammo.count));
+            } else {
+                ctx.print_color(50, y, white, black, get_item_display_name(ecs, item_entity));
+            }
             y += 1;
         }
     }
@@ -90,12 +102,24 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     for (carried_by, _consumable, item_name) in (&backpack, &consumables, &name).join() {
         if carried_by.owner == *player_entity &&index < 10 {
             ctx.print_color(50, y, yellow, black, &format!("↑{}", index));
-            ctx.print_color(53, y, green, black, &item_name.name);
+            ctx.print_color(53, y, green, black, dm.display_name(&item_name.name));
             y += 1;
             index += 1;
         }
     }
 
+    // Spells
+    y += 1;
+    let blue = RGB::named(rltk::CYAN);
+    let known_spells_storage = ecs.read_storage::<KnownSpells>();
+    if let Some(known_spells) = known_spells_storage.get(*player_entity) {
+        for (index, spell) in known_spells.spells.iter().enumerate().take(9) {
+            let row = y + index as i32;
+            ctx.print_color(50, row, blue, black, format!("^{}", index + 1));
+            ctx.print_color(53, row, blue, black, format!("{} ({})", spell.display_name, spell.mana_cost));
+        }
+    }
+
     // Status
     let hunger = ecs.read_storage::<HungerClock>();
     let hc = hunger.get(*player_entity).unwrap();
@@ -106,6 +130,33 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         HungerState::Starving => ctx.print_color(50, 44, RGB::named(rltk::RED), RGB::named(rltk::BLACK), "Starving"),
     }
 
+    // Status effects, stacked upwards from the hunger line
+    let statuses = ecs.read_storage::<StatusEffect>();
+    let durations = ecs.read_storage::<Duration>();
+    let confusion = ecs.read_storage::<Confusion>();
+    let damage_over_time = ecs.read_storage::<DamageOverTime>();
+    let slows = ecs.read_storage::<Slow>();
+    let mut status_y = 43;
+    for (effect, status, duration, status_name) in (&entities, &statuses, &durations, &name).join() {
+        if status.target == *player_entity && status_y > y {
+            let harmful = confusion.get(effect).is_some()
+                || damage_over_time.get(effect).is_some_and(|dot| dot.damage > 0)
+                || slows.get(effect).is_some_and(|slow| slow.initiative_penalty > 0);
+            let color = if harmful { RGB::named(rltk::ORANGE) } else { RGB::named(rltk::CYAN) };
+            ctx.print_color(50, status_y, color, black, format!("{} ({})", status_name.name, duration.turns));
+            status_y -= 1;
+        }
+    }
+
+    // Progress through a multi-turn action, on the log box border
+    if let Some((label, done, total)) = crate::action_queue::progress(ecs) {
+        let width = label.len() as i32;
+        ctx.set(2, 45, box_gray, black, to_cp437('┤'));
+        ctx.print_color(3, 45, RGB::named(rltk::YELLOW), black, &label);
+        ctx.draw_bar_horizontal(4 + width, 45, 10, done, total, RGB::named(rltk::YELLOW), black);
+        ctx.set(14 + width, 45, box_gray, black, to_cp437('├'));
+    }
+
     // Draw the log
     let log = ecs.fetch::<GameLog>();
     let mut y = 46;
@@ -119,17 +170,10 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
-fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
-    use rltk::to_cp437;
-
+/// The map tile under the mouse cursor, unless it's off the edge of the map.
+pub fn mouse_map_pos(ecs: &World, ctx: &mut Rltk) -> Option<(i32, i32)> {
     let (min_x, _max_x, min_y, _max_y) = camera::get_screen_bounds(ecs, ctx);
     let map = ecs.fetch::<Map>();
-    let names = ecs.read_storage::<Name>();
-    let positions = ecs.read_storage::<Position>();
-    let hidden = ecs.read_storage::<Hidden>();
-    let attributes = ecs.read_storage::<Attributes>();
-    let pools = ecs.read_storage::<Pools>();
-    let entities = ecs.entities();
 
     let mouse_pos = ctx.mouse_pos();
     let mut mouse_map_pos = mouse_pos;
@@ -139,13 +183,32 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         || mouse_map_pos.1 >= map.height - 1
         || mouse_map_pos.0 < 1
         || mouse_map_pos.1 < 1 {
-        return;
+        return None;
     }
+    Some(mouse_map_pos)
+}
+
+fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
+    use rltk::to_cp437;
+
+    let mouse_map_pos = match mouse_map_pos(ecs, ctx) {
+        Some(pos) => pos,
+        None => return
+    };
+    let names = ecs.read_storage::<Name>();
+    let dm = ecs.fetch::<MasterDungeonMap>();
+    let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let attributes = ecs.read_storage::<Attributes>();
+    let pools = ecs.read_storage::<Pools>();
+    let entities = ecs.entities();
+
+    let mouse_pos = ctx.mouse_pos();
     let mut tip_boxes: Vec<Tooltip> = Vec::new();
     for (entity, name, position, _hidden) in (&entities, &names, &positions, !&hidden).join() {
         if position.x == mouse_map_pos.0 && position.y == mouse_map_pos.1 {
             let mut tip = Tooltip::new();
-            tip.add(name.name.to_string());
+            tip.add(dm.display_name(&name.name));
             //Comment on attributes
             let attr = attributes.get(entity);
             if let Some(attr) = attr {
@@ -210,6 +273,18 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     }
 }
 
+/// The name the player knows an item by, noting a curse once they know what the item is.
+pub fn get_item_display_name(ecs: &World, item: Entity) -> String {
+    let dm = ecs.fetch::<MasterDungeonMap>();
+    match ecs.read_storage::<Name>().get(item) {
+        Some(name) if ecs.read_storage::<CursedItem>().get(item).is_some() && dm.is_identified(&name.name) => {
+            format!("{} (cursed)", name.name)
+        }
+        Some(name) => dm.display_name(&name.name),
+        None => "Nameless item (bug)".to_string()
+    }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum ItemMenuResult { Cancel, NoResponse, Selected }
 
@@ -230,12 +305,12 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
 
     let mut equippable: Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity ) {        ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+    for (entity, _pack, _name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity ) {        ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, get_item_display_name(&gs.ecs, entity));
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -274,12 +349,12 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
 
     let mut equippable: Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity) {
+    for (entity, _pack, _name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity) {
         ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as u8);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, get_item_display_name(&gs.ecs, entity));
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -318,12 +393,12 @@ pub fn remove_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Op
 
     let mut equippable : Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity ) {
+    for (entity, _pack, _name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity ) {
         ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, get_item_display_name(&gs.ecs, entity));
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -346,6 +421,49 @@ pub fn remove_item_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Op
     }
 }
 
+pub fn cast_spell_menu(gs : &mut State, ctx : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let known_spells_storage = gs.ecs.read_storage::<KnownSpells>();
+    let known_spells = match known_spells_storage.get(*player_entity) {
+        None => return (ItemMenuResult::Cancel, None),
+        Some(known) => &known.spells
+    };
+    let count = known_spells.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y-2, 31, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Cast Which Spell?");
+    ctx.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    for (j, spell) in known_spells.iter().enumerate() {
+        let row = y + j as i32;
+        ctx.set(17, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, row, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
+        ctx.set(19, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+        ctx.print(21, row, format!("{} ({} mana)", spell.display_name, spell.mana_cost));
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
+                _ => {
+                    let selection = rltk::letter_to_option(key);
+                    if selection > -1 && selection < count as i32 {
+                        let spell = crate::spell_system::find_spell_entity(&gs.ecs, &known_spells[selection as usize].display_name);
+                        if spell.is_some() {
+                            return (ItemMenuResult::Selected, spell);
+                        }
+                    }
+                    (ItemMenuResult::NoResponse, None)
+                }
+            }
+        }
+    }
+}
+
 pub fn ranged_target(gs: &mut State, ctx: &mut Rltk, range: i32) -> (ItemMenuResult, Option<Point>) {
     let (min_x, max_x, min_y, max_y) = camera::get_screen_bounds(&gs.ecs, ctx);
     let player_entity = gs.ecs.fetch::<Entity>();
@@ -487,6 +605,242 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     MainMenuResult::NoSelection { selected: MainMenuSelection::NewGame }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum CreationStep { Method, Attributes, Background, Name }
+
+pub const ATTRIBUTE_NAMES : [&str; 4] = ["Might", "Fitness", "Quickness", "Intelligence"];
+const MAX_NAME_LENGTH : usize = 20;
+
+/// The choices made so far on the character creation screens.
+pub struct CharacterSheet {
+    pub step : CreationStep,
+    pub selection : usize,
+    pub rolled : bool,
+    pub attributes : [i32; 4],
+    pub background : String,
+    pub name : String
+}
+
+impl CharacterSheet {
+    pub fn new() -> CharacterSheet {
+        CharacterSheet {
+            step: CreationStep::Method,
+            selection: 0,
+            rolled: false,
+            attributes: [POINT_BUY_BASE; 4],
+            background: String::new(),
+            name: String::new()
+        }
+    }
+
+    fn points_remaining(&self) -> i32 {
+        POINT_BUY_POINTS - self.attributes.iter().map(|a| point_buy_cost(*a)).sum::<i32>()
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterCreationResult { NoResponse, Cancel, Done }
+
+pub fn character_creation(gs: &mut State, ctx: &mut Rltk) -> CharacterCreationResult {
+    let white = RGB::named(rltk::WHITE);
+    let yellow = RGB::named(rltk::YELLOW);
+    let magenta = RGB::named(rltk::MAGENTA);
+    let black = RGB::named(rltk::BLACK);
+
+    ctx.print_color_centered(8, yellow, black, "Create Your Character");
+
+    let sheet = &mut gs.character;
+    match sheet.step {
+        CreationStep::Method => {
+            ctx.print_color_centered(12, white, black, "How would you like to determine your attributes?");
+            let options = ["Point Buy", "Roll 4d6, Drop Lowest"];
+            for (i, option) in options.iter().enumerate() {
+                let fg = if i == sheet.selection { magenta } else { white };
+                ctx.print_color_centered(15 + i as i32, fg, black, option);
+            }
+            ctx.print_color_centered(20, yellow, black, "ENTER to choose, ESCAPE to return to the menu");
+
+            match ctx.key {
+                Some(VirtualKeyCode::Escape) => return CharacterCreationResult::Cancel,
+                Some(VirtualKeyCode::Up) | Some(VirtualKeyCode::Down) => sheet.selection = 1 - sheet.selection,
+                Some(VirtualKeyCode::Return) => {
+                    sheet.rolled = sheet.selection == 1;
+                    if sheet.rolled {
+                        let mut rng = gs.ecs.write_resource::<rltk::RandomNumberGenerator>();
+                        for attribute in sheet.attributes.iter_mut() {
+                            *attribute = roll_attribute(&mut rng);
+                        }
+                    } else {
+                        sheet.attributes = [POINT_BUY_BASE; 4];
+                    }
+                    sheet.step = CreationStep::Attributes;
+                    sheet.selection = 0;
+                }
+                _ => {}
+            }
+        }
+
+        CreationStep::Attributes => {
+            for (i, name) in ATTRIBUTE_NAMES.iter().enumerate() {
+                let y = 14 + i as i32 * 2;
+                let fg = if i == sheet.selection && !sheet.rolled { magenta } else { white };
+                ctx.print_color(28, y, fg, black, name);
+                ctx.print_color(44, y, fg, black, sheet.attributes[i]);
+                let bonus = attr_bonus(sheet.attributes[i]);
+                let bonus_text = if bonus > 0 { format!("(+{})", bonus) } else { format!("({})", bonus) };
+                ctx.print_color(48, y, fg, black, &bonus_text);
+            }
+            if sheet.rolled {
+                ctx.print_color_centered(24, white, black, "R to reroll");
+                ctx.print_color_centered(26, yellow, black, "ENTER to accept, ESCAPE to go back");
+            } else {
+                ctx.print_color_centered(24, white, black, format!("Points remaining: {}", sheet.points_remaining()));
+                ctx.print_color_centered(26, yellow, black, "UP/DOWN to select, LEFT/RIGHT to adjust, ENTER to accept, ESCAPE to go back");
+            }
+
+            match ctx.key {
+                Some(VirtualKeyCode::Escape) => {
+                    sheet.step = CreationStep::Method;
+                    sheet.selection = if sheet.rolled { 1 } else { 0 };
+                }
+                Some(VirtualKeyCode::Return) => {
+                    sheet.step = CreationStep::Background;
+                    sheet.selection = 0;
+                }
+                Some(VirtualKeyCode::R) if sheet.rolled => {
+                    let mut rng = gs.ecs.write_resource::<rltk::RandomNumberGenerator>();
+                    for attribute in sheet.attributes.iter_mut() {
+                        *attribute = roll_attribute(&mut rng);
+                    }
+                }
+                Some(VirtualKeyCode::Up) if !sheet.rolled && sheet.selection > 0 => sheet.selection -= 1,
+                Some(VirtualKeyCode::Down) if !sheet.rolled && sheet.selection < ATTRIBUTE_NAMES.len() - 1 => sheet.selection += 1,
+                Some(VirtualKeyCode::Left) if !sheet.rolled && sheet.attributes[sheet.selection] > POINT_BUY_BASE => {
+                    sheet.attributes[sheet.selection] -= 1;
+                }
+                Some(VirtualKeyCode::Right) if !sheet.rolled && sheet.attributes[sheet.selection] < POINT_BUY_MAX => {
+                    sheet.attributes[sheet.selection] += 1;
+                    if sheet.points_remaining() < 0 {
+                        sheet.attributes[sheet.selection] -= 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        CreationStep::Background => {
+            let backgrounds = get_background_list(&RAWS.lock().unwrap());
+            if backgrounds.is_empty() {
+                sheet.step = CreationStep::Name;
+                return CharacterCreationResult::NoResponse;
+            }
+            ctx.print_color_centered(12, white, black, "Choose a background:");
+            for (i, (name, _description)) in backgrounds.iter().enumerate() {
+                let fg = if i == sheet.selection { magenta } else { white };
+                ctx.print_color_centered(15 + i as i32, fg, black, name);
+            }
+            let description_y = 17 + backgrounds.len() as i32;
+            ctx.print_color_centered(description_y, white, black, &backgrounds[sheet.selection].1);
+            ctx.print_color_centered(description_y + 3, yellow, black, "ENTER to choose, ESCAPE to go back");
+
+            match ctx.key {
+                Some(VirtualKeyCode::Escape) => {
+                    sheet.step = CreationStep::Attributes;
+                    sheet.selection = 0;
+                }
+                Some(VirtualKeyCode::Up) if sheet.selection > 0 => sheet.selection -= 1,
+                Some(VirtualKeyCode::Down) if sheet.selection < backgrounds.len() - 1 => sheet.selection += 1,
+                Some(VirtualKeyCode::Return) => {
+                    sheet.background = backgrounds[sheet.selection].0.clone();
+                    sheet.step = CreationStep::Name;
+                }
+                _ => {}
+            }
+        }
+
+        CreationStep::Name => {
+            ctx.print_color_centered(12, white, black, "What is your name?");
+            ctx.print_color_centered(15, magenta, black, format!("{}_", sheet.name));
+            ctx.print_color_centered(20, yellow, black, "ENTER to begin your adventure, ESCAPE to go back");
+
+            match ctx.key {
+                Some(VirtualKeyCode::Escape) => {
+                    sheet.step = CreationStep::Background;
+                    sheet.selection = 0;
+                }
+                Some(VirtualKeyCode::Return) if !sheet.name.trim().is_empty() => {
+                    sheet.name = sheet.name.trim().to_string();
+                    return CharacterCreationResult::Done;
+                }
+                Some(VirtualKeyCode::Back) => { sheet.name.pop(); }
+                Some(VirtualKeyCode::Space) if sheet.name.len() < MAX_NAME_LENGTH => sheet.name.push(' '),
+                Some(key) if sheet.name.len() < MAX_NAME_LENGTH => {
+                    let letter = rltk::letter_to_option(key);
+                    if letter >= 0 {
+                        let c = (b'a' + letter as u8) as char;
+                        sheet.name.push(if ctx.shift || sheet.name.is_empty() { c.to_ascii_uppercase() } else { c });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    CharacterCreationResult::NoResponse
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LevelUpResult { NoResponse { selection: i32 }, Selected { selection: i32 } }
+
+const SKILL_NAMES : [&str; 4] = ["Melee", "Defense", "Magic", "Shield"];
+
+pub fn level_up_menu(gs: &mut State, ctx: &mut Rltk, selection: i32) -> LevelUpResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let pools = gs.ecs.read_storage::<Pools>();
+    let attributes = gs.ecs.read_storage::<Attributes>();
+    let skills = gs.ecs.read_storage::<Skills>();
+    let pending = gs.ecs.read_storage::<PendingLevelUp>();
+
+    let white = RGB::named(rltk::WHITE);
+    let yellow = RGB::named(rltk::YELLOW);
+    let magenta = RGB::named(rltk::MAGENTA);
+    let gray = RGB::named(rltk::GRAY);
+    let black = RGB::named(rltk::BLACK);
+
+    let (attribute_points, skill_points) = pending.get(*player_entity).map_or((0, 0), |p| (p.attribute_points, p.skill_points));
+    let level = pools.get(*player_entity).map_or(1, |p| p.level);
+    let attr = attributes.get(*player_entity).unwrap();
+    let player_skills = skills.get(*player_entity).unwrap();
+
+    ctx.draw_box(15, 10, 50, 20, white, black);
+    ctx.print_color(18, 10, yellow, black, format!("Level Up! You are now level {}.", level));
+    ctx.print_color(18, 12, white, black, format!("Attribute points: {}   Skill points: {}", attribute_points, skill_points));
+
+    let attribute_values = [&attr.might, &attr.fitness, &attr.quickness, &attr.intelligence];
+    for (i, (name, attribute)) in ATTRIBUTE_NAMES.iter().zip(attribute_values.iter()).enumerate() {
+        let y = 14 + i as i32;
+        let fg = if i as i32 == selection { magenta } else if attribute_points > 0 { white } else { gray };
+        ctx.print_color(20, y, fg, black, name);
+        ctx.print_color(40, y, fg, black, attribute.base + attribute.modifiers);
+    }
+    for (i, (name, skill)) in SKILL_NAMES.iter().zip([Skill::Melee, Skill::Defense, Skill::Magic, Skill::Shield].iter()).enumerate() {
+        let row = ATTRIBUTE_NAMES.len() + i;
+        let y = 15 + row as i32;
+        let fg = if row as i32 == selection { magenta } else if skill_points > 0 { white } else { gray };
+        ctx.print_color(20, y, fg, black, name);
+        ctx.print_color(40, y, fg, black, player_skills.skills.get(skill).copied().unwrap_or(0));
+    }
+    ctx.print_color(18, 30, yellow, black, "UP/DOWN to select, ENTER to raise");
+
+    let rows = (ATTRIBUTE_NAMES.len() + SKILL_NAMES.len()) as i32;
+    match ctx.key {
+        Some(VirtualKeyCode::Up) => LevelUpResult::NoResponse { selection: (selection + rows - 1) % rows },
+        Some(VirtualKeyCode::Down) => LevelUpResult::NoResponse { selection: (selection + 1) % rows },
+        Some(VirtualKeyCode::Return) => LevelUpResult::Selected { selection },
+        _ => LevelUpResult::NoResponse { selection }
+    }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult { NoSelection, QuitToMenu }
 
@@ -580,12 +934,12 @@ impl Tooltip {
 }
 
 #[derive(PartialEq, Copy, Clone)]
-pub enum CheatMenuResult { NoResponse, Cancel, TeleportToExit }
+pub enum CheatMenuResult { NoResponse, Cancel, TeleportToExit, ReloadRaws, RefreshFromRaws, ToggleVerboseCombat, CombatHistory }
 
-pub fn show_cheat_mode(_gs: &mut State, ctx: &mut Rltk) -> CheatMenuResult {
-    let count = 2;
+pub fn show_cheat_mode(gs: &mut State, ctx: &mut Rltk) -> CheatMenuResult {
+    let count = 6;
     let y = (25 - (count / 2)) as i32;
-    ctx.draw_box(15, y-2, 31, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.draw_box(15, y-2, 40, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
     ctx.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Cheating!");
     ctx.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
 
@@ -595,11 +949,40 @@ pub fn show_cheat_mode(_gs: &mut State, ctx: &mut Rltk) -> CheatMenuResult {
 
     ctx.print(21, y, "Teleport to exit");
 
+    ctx.set(17, y+1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+    ctx.set(18, y+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437('R'));
+    ctx.set(19, y+1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+    ctx.print(21, y+1, "Reload raws");
+
+    ctx.set(17, y+2, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+    ctx.set(18, y+2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437('E'));
+    ctx.set(19, y+2, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+    ctx.print(21, y+2, "Reload raws and refresh entities");
+
+    ctx.set(17, y+3, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+    ctx.set(18, y+3, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437('V'));
+    ctx.set(19, y+3, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+    let verbose = gs.ecs.fetch::<CombatLog>().verbose;
+    ctx.print(21, y+3, if verbose { "Verbose combat: on" } else { "Verbose combat: off" });
+
+    ctx.set(17, y+4, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+    ctx.set(18, y+4, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437('H'));
+    ctx.set(19, y+4, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+    ctx.print(21, y+4, "Combat history");
+
     match ctx.key {
         None => CheatMenuResult::NoResponse,
         Some(key) => {
             match key {
                 VirtualKeyCode::T => CheatMenuResult::TeleportToExit,
+                VirtualKeyCode::R => CheatMenuResult::ReloadRaws,
+                VirtualKeyCode::E => CheatMenuResult::RefreshFromRaws,
+                VirtualKeyCode::V => CheatMenuResult::ToggleVerboseCombat,
+                VirtualKeyCode::H => CheatMenuResult::CombatHistory,
                 VirtualKeyCode::Escape => CheatMenuResult::Cancel,
                 _ => CheatMenuResult::NoResponse
             }
@@ -607,6 +990,126 @@ pub fn show_cheat_mode(_gs: &mut State, ctx: &mut Rltk) -> CheatMenuResult {
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum RestMenuResult { NoResponse, Cancel, UntilHealed, Turns(i32) }
+
+const REST_OPTIONS : [(char, &str, Option<i32>); 4] =
+    [('H', "Until healed", None), ('1', "10 turns", Some(10)), ('2', "25 turns", Some(25)), ('3', "50 turns", Some(50))];
+
+pub fn show_rest_menu(ctx: &mut Rltk) -> RestMenuResult {
+    let count = REST_OPTIONS.len();
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y-2, 31, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Rest");
+    ctx.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    for (i, (key, label, _turns)) in REST_OPTIONS.iter().enumerate() {
+        let row = y + i as i32;
+        ctx.set(17, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, row, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437(*key));
+        ctx.set(19, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print(21, row, label);
+    }
+
+    let chosen = match ctx.key {
+        None => return RestMenuResult::NoResponse,
+        Some(VirtualKeyCode::Escape) => return RestMenuResult::Cancel,
+        Some(VirtualKeyCode::H) => 'H',
+        Some(VirtualKeyCode::Key1) => '1',
+        Some(VirtualKeyCode::Key2) => '2',
+        Some(VirtualKeyCode::Key3) => '3',
+        Some(_) => return RestMenuResult::NoResponse,
+    };
+    match REST_OPTIONS.iter().find(|(key, _, _)| *key == chosen) {
+        Some((_, _, Some(turns))) => RestMenuResult::Turns(*turns),
+        _ => RestMenuResult::UntilHealed,
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum AutoPickupMenuResult { NoResponse, Cancel, ToggleConsumables, ToggleAmmunition, ToggleEquipment }
+
+pub fn show_auto_pickup_menu(gs: &mut State, ctx: &mut Rltk) -> AutoPickupMenuResult {
+    let rules = gs.ecs.fetch::<crate::travel::AutoPickup>();
+    let options = [('C', "Consumables", rules.consumables), ('A', "Ammunition", rules.ammunition), ('E', "Equipment", rules.equipment)];
+    let count = options.len();
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y-2, 31, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Auto-explore picks up");
+    ctx.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE when done");
+
+    for (i, (key, label, enabled)) in options.iter().enumerate() {
+        let row = y + i as i32;
+        ctx.set(17, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, row, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437(*key));
+        ctx.set(19, row, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print(21, row, format!("{}: {}", label, if *enabled { "yes" } else { "no" }));
+    }
+
+    match ctx.key {
+        None => AutoPickupMenuResult::NoResponse,
+        Some(key) => {
+            match key {
+                VirtualKeyCode::C => AutoPickupMenuResult::ToggleConsumables,
+                VirtualKeyCode::A => AutoPickupMenuResult::ToggleAmmunition,
+                VirtualKeyCode::E => AutoPickupMenuResult::ToggleEquipment,
+                VirtualKeyCode::Escape => AutoPickupMenuResult::Cancel,
+                _ => AutoPickupMenuResult::NoResponse
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CombatHistoryResult { NoResponse { offset: i32 }, Close }
+
+const COMBAT_HISTORY_ROWS : i32 = 52;
+
+/// Every recorded attack, newest first, with its roll breakdown beneath it. `offset` is the
+/// number of lines scrolled past.
+pub fn combat_history(gs: &mut State, ctx: &mut Rltk, offset: i32) -> CombatHistoryResult {
+    let combat_log = gs.ecs.fetch::<CombatLog>();
+
+    let white = RGB::named(rltk::WHITE);
+    let yellow = RGB::named(rltk::YELLOW);
+    let gray = RGB::named(rltk::GRAY);
+    let black = RGB::named(rltk::BLACK);
+
+    let mut lines: Vec<(RGB, String)> = Vec::new();
+    for record in combat_log.records.iter().rev() {
+        let fg = match record.outcome {
+            AttackOutcome::Hit { .. } => RGB::named(rltk::ORANGE),
+            AttackOutcome::Blocked => white,
+            AttackOutcome::Fumble => RGB::named(rltk::BLUE),
+            AttackOutcome::Miss => RGB::named(rltk::CYAN),
+        };
+        lines.push((fg, format!("{} attacks {}: {}", record.attacker, record.target, record.outcome_text())));
+        lines.push((gray, format!("  {}", record.breakdown())));
+    }
+
+    let max_offset = i32::max(0, lines.len() as i32 - COMBAT_HISTORY_ROWS);
+    let offset = offset.clamp(0, max_offset);
+
+    ctx.draw_box(0, 1, 79, COMBAT_HISTORY_ROWS + 3, white, black);
+    ctx.print_color(3, 1, yellow, black, format!("Combat History ({} attacks)", combat_log.records.len()));
+    if lines.is_empty() {
+        ctx.print_color(3, 3, white, black, "No attacks recorded yet.");
+    }
+    for (i, (fg, line)) in lines.iter().skip(offset as usize).take(COMBAT_HISTORY_ROWS as usize).enumerate() {
+        ctx.print_color(2, 3 + i as i32, *fg, black, line);
+    }
+    ctx.print_color(3, COMBAT_HISTORY_ROWS + 4, yellow, black, "UP/DOWN or PAGE UP/DOWN to scroll, ESCAPE to close");
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => CombatHistoryResult::Close,
+        Some(VirtualKeyCode::Up) => CombatHistoryResult::NoResponse { offset: i32::max(0, offset - 1) },
+        Some(VirtualKeyCode::Down) => CombatHistoryResult::NoResponse { offset: i32::min(max_offset, offset + 1) },
+        Some(VirtualKeyCode::PageUp) => CombatHistoryResult::NoResponse { offset: i32::max(0, offset - COMBAT_HISTORY_ROWS) },
+        Some(VirtualKeyCode::PageDown) => CombatHistoryResult::NoResponse { offset: i32::min(max_offset, offset + COMBAT_HISTORY_ROWS) },
+        _ => CombatHistoryResult::NoResponse { offset }
+    }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum VendorResult { NoResponse, Cancel, Sell, BuyMode, SellMode, Buy }
 
@@ -628,14 +1131,14 @@ fn vendor_sell_menu(gs: &mut State, ctx: &mut Rltk, _vendor: Entity, _mode: Vend
     
     let mut equippable: Vec<Entity> = Vec::new();
     let mut j = 0;
-    for (entity, _pack, name, item) 
+    for (entity, _pack, _name, item) 
         in (&entities, &backpack, &names, &items).join()
         .filter(|item| item.1.owner == *player_entity) {
             ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
             ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
             ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
     
-            ctx.print(21, y, &name.name.to_string());
+            ctx.print(21, y, get_item_display_name(&gs.ecs, entity));
             ctx.print(50, y, &format!("{:.1} gp", item.base_value * 0.8));
             equippable.push(entity);
             y += 1;
@@ -665,6 +1168,7 @@ fn vendor_buy_menu(gs: &mut State, ctx: &mut Rltk, vendor: Entity, _mode: Vendor
     use crate::raws::*;
 
     let vendors = gs.ecs.read_storage::<Vendor>();
+    let dm = gs.ecs.fetch::<MasterDungeonMap>();
     let inventory = crate::raws::get_vendor_items(
         &vendors.get(vendor).unwrap().categories, 
         &RAWS.lock().unwrap()
@@ -681,7 +1185,7 @@ fn vendor_buy_menu(gs: &mut State, ctx: &mut Rltk, vendor: Entity, _mode: Vendor
         ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
         ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        ctx.print(21, y, &sale.0);
+        ctx.print(21, y, dm.display_name(&sale.0));
         ctx.print(50, y, &format!("{:.1} gp", sale.1 * 1.2));
         y += 1;
     }
@@ -710,4 +1214,34 @@ pub fn show_vendor_mode(gs: &mut State, ctx: &mut Rltk, vendor: Entity, mode: Ve
         VendorMode::Buy => vendor_buy_menu(gs, ctx, vendor, mode),
         VendorMode::Sell => vendor_sell_menu(gs, ctx, vendor, mode),
     }
-}
\ No newline at end of file
+}
+#[derive(PartialEq, Copy, Clone)]
+pub enum TempleResult { NoResponse, Cancel, RemoveCurse }
+
+pub fn show_temple(gs: &mut State, ctx: &mut Rltk, temple: Entity) -> TempleResult {
+    let temples = gs.ecs.read_storage::<Temple>();
+    let cost = temples.get(temple).map_or(0.0, |t| t.remove_curse_cost);
+
+    let count : usize = 1;
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(15, y-2, 51, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "The priest offers their services");
+    ctx.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+    ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437('R'));
+    ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+    ctx.print(21, y, "Remove curses");
+    ctx.print(50, y, format!("{:.1} gp", cost));
+
+    match ctx.key {
+        None => TempleResult::NoResponse,
+        Some(key) => {
+            match key {
+                VirtualKeyCode::R => TempleResult::RemoveCurse,
+                VirtualKeyCode::Escape => TempleResult::Cancel,
+                _ => TempleResult::NoResponse
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use specs::prelude::*;
 use crate::MyTurn;
+use crate::raws::{RAWS, hunger_duration};
 
-use super::{HungerClock, RunState, HungerState, SufferDamage, gamelog::GameLog};
+use crate::effects::{add_effect, EffectType, Targets};
+use super::{HungerClock, RunState, HungerState, gamelog::GameLog};
 
 pub struct HungerSystem {}
 
@@ -12,7 +14,6 @@ impl<'a> System<'a> for HungerSystem {
         WriteStorage<'a, HungerClock>,
         ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
-        WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, GameLog>,
         ReadStorage<'a, MyTurn>,
     );
@@ -23,7 +24,6 @@ impl<'a> System<'a> for HungerSystem {
             mut hunger_clock,
             player_entity,
             runstate,
-            mut inflict_damage,
             mut log,
             turns,
         ) = data;
@@ -34,21 +34,21 @@ impl<'a> System<'a> for HungerSystem {
                 match clock.state {
                     HungerState::WellFed => {
                         clock.state = HungerState::Normal;
-                        clock.duration = 200;
+                        clock.duration = hunger_duration(&RAWS.lock().unwrap(), HungerState::Normal);
                         if entity == *player_entity {
                             log.entries.push("You are no longer well fed.".to_string())
                         }
                     }
                     HungerState::Normal => {
                         clock.state = HungerState::Hungry;
-                        clock.duration = 200;
+                        clock.duration = hunger_duration(&RAWS.lock().unwrap(), HungerState::Hungry);
                         if entity == *player_entity {
                             log.entries.push("You are hungry.".to_string());
                         }
                     }
                     HungerState::Hungry => {
                         clock.state = HungerState::Starving;
-                        clock.duration = 200;
+                        clock.duration = hunger_duration(&RAWS.lock().unwrap(), HungerState::Starving);
                         if entity == *player_entity {
                             log.entries.push("You are starving.".to_string());
                         }
@@ -57,7 +57,7 @@ impl<'a> System<'a> for HungerSystem {
                         if entity == *player_entity {
                             log.entries.push("Your hunger pangs are getting painful! You suffer 1 hp damage.".to_string());
                         }
-                        SufferDamage::new_damage(&mut inflict_damage, entity, 1, false);  
+                        add_effect(None, EffectType::Damage { amount: 1, damage_type: None }, Targets::Single { target: entity });  
                     }
                 }
             }
@@ -1,17 +1,19 @@
 use specs::prelude::*;
-use crate::{EquipmentChanged, particle_system::ParticleBuilder, ProvidesFood, HungerClock, HungerState};
+use crate::EquipmentChanged;
+use crate::effects::{add_effect, item_targets, EffectType};
 
 use super::{
     WantsToPickupItem, 
     Name, InBackpack, 
     Position, 
     gamelog::GameLog, 
-    Pools, WantsToDropItem,
-    Consumable, WantsToUseItem, ProvidesHealing,
-    InflictsDamage, SufferDamage, Map,
-    AreaOfEffect, Confusion, Equippable, Equipped,
-    WantsToRemoveItem
+    WantsToDropItem,
+    WantsToUseItem, Map,
+    AreaOfEffect, Equippable, Equipped,
+    WantsToRemoveItem, MasterDungeonMap, CursedItem,
+    EquipmentSlot, MeleeWeapon, TwoHanded
 };
+use crate::gamesystem::DUAL_WIELDING;
 
 pub struct ItemCollectionSystem {}
 
@@ -25,6 +27,7 @@ impl<'a> System<'a> for ItemCollectionSystem {
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
         WriteStorage<'a, EquipmentChanged>,
+        ReadExpect<'a, MasterDungeonMap>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -35,7 +38,8 @@ impl<'a> System<'a> for ItemCollectionSystem {
             mut positions, 
             names, 
             mut backpack,
-            mut dirty) = data;
+            mut dirty,
+            dm) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
@@ -45,7 +49,7 @@ impl<'a> System<'a> for ItemCollectionSystem {
             dirty.insert(pickup.collected_by, EquipmentChanged {  }).expect("Unable to insert");
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You pick up the {}.", names.get(pickup.item).unwrap().name));
+                gamelog.entries.push(format!("You pick up the {}.", dm.display_name(&names.get(pickup.item).unwrap().name)));
             }
         }
         wants_pickup.clear();
@@ -65,6 +69,7 @@ impl<'a> System<'a> for ItemDropSystem {
         WriteStorage<'a, Position>,
         WriteStorage<'a, InBackpack>,
         WriteStorage<'a, EquipmentChanged>,
+        ReadExpect<'a, MasterDungeonMap>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -76,7 +81,8 @@ impl<'a> System<'a> for ItemDropSystem {
             names, 
             mut positions, 
             mut backpack,
-            mut dirty) = data;
+            mut dirty,
+            dm) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
             let mut dropper_pos: Position = Position{ x: 0, y:0 };
@@ -90,7 +96,7 @@ impl<'a> System<'a> for ItemDropSystem {
             backpack.remove(to_drop.item);
             dirty.insert(entity, EquipmentChanged {}).expect("Unable to insert");
             if entity == *player_entity {
-                gamelog.entries.push(format!("You drop the {}.", names.get(to_drop.item).unwrap().name));
+                gamelog.entries.push(format!("You drop the {}.", dm.display_name(&names.get(to_drop.item).unwrap().name)));
             }
         }
 
@@ -105,17 +111,36 @@ impl<'a> System<'a> for ItemRemoveSystem {
                         Entities<'a>,
                         WriteStorage<'a, WantsToRemoveItem>,
                         WriteStorage<'a, Equipped>,
-                        WriteStorage<'a, InBackpack>
+                        WriteStorage<'a, InBackpack>,
+                        ReadStorage<'a, CursedItem>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, GameLog>,
+                        ReadStorage<'a, Name>,
+                        ReadExpect<'a, MasterDungeonMap>,
+                        WriteStorage<'a, EquipmentChanged>
                       );
     fn run(&mut self, data : Self::SystemData) {
         let (entities, 
              mut wants_remove, 
              mut equipped, 
-             mut backpack
+             mut backpack,
+             cursed,
+             player_entity,
+             mut gamelog,
+             names,
+             dm,
+             mut dirty
         ) = data;
 
         for (entity, to_remove) in (&entities, &wants_remove).join() {
+            if cursed.get(to_remove.item).is_some() {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You cannot remove the {}; it is cursed!", dm.display_name(&names.get(to_remove.item).unwrap().name)));
+                }
+                continue;
+            }
             equipped.remove(to_remove.item);
+            dirty.insert(entity, EquipmentChanged {}).expect("Unable to insert");
             backpack.insert(to_remove.item, InBackpack{ owner: entity }).expect("Unable to insert backpack");
         }
 
@@ -123,216 +148,156 @@ impl<'a> System<'a> for ItemRemoveSystem {
     }
 }
 
-pub struct ItemUseSystem {}
+pub struct ItemEquipOnUse {}
 
-impl<'a> System<'a> for ItemUseSystem {
+impl<'a> System<'a> for ItemEquipOnUse {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        WriteExpect<'a, Map>,
         ReadExpect<'a, Entity>,
-        Entities<'a>,
         WriteExpect<'a, GameLog>,
+        Entities<'a>,
         WriteStorage<'a, WantsToUseItem>,
-        ReadStorage<'a, Consumable>,
-        ReadStorage<'a, ProvidesHealing>,
-        ReadStorage<'a, InflictsDamage>,
-        WriteStorage<'a, SufferDamage>,
-        WriteStorage<'a, Pools>,
         ReadStorage<'a, Name>,
-        ReadStorage<'a, AreaOfEffect>,
-        WriteStorage<'a, Confusion>,
         ReadStorage<'a, Equippable>,
         WriteStorage<'a, Equipped>,
         WriteStorage<'a, InBackpack>,
-        WriteExpect<'a, ParticleBuilder>,
-        ReadStorage<'a, Position>,
         WriteStorage<'a, EquipmentChanged>,
-        ReadStorage<'a, ProvidesFood>,
-        WriteStorage<'a, HungerClock>,
+        WriteExpect<'a, MasterDungeonMap>,
+        ReadStorage<'a, CursedItem>,
+        ReadStorage<'a, MeleeWeapon>,
+        ReadStorage<'a, TwoHanded>,
     );
+
     fn run(&mut self, data: Self::SystemData) {
         let (
-            map,
             player_entity,
-            entities, 
             mut gamelog,
-            mut wants_use, 
-            consumables,
-            healing,
-            inflict_damage,
-            mut suffer_damage,
-            mut pools,
+            entities,
+            mut wants_use,
             names,
-            aoe,
-            mut confused,
-            equippable, 
-            mut equipped, 
+            equippable,
+            mut equipped,
             mut backpack,
-            mut particle_builder,
-            positions,
             mut dirty,
-            provides_food,
-            mut hunger_clocks
+            mut dm,
+            cursed,
+            melee_weapons,
+            two_handed
         ) = data;
 
-        for (entity, useitem) in (&entities, &wants_use).join() {
-            dirty.insert(entity, EquipmentChanged {  });
-            let mut used_item = true;
-            let mut targets : Vec<Entity> = Vec::new();
-            match useitem.target {
-                None => { targets.push( *player_entity )},
-                Some(target) => {
-                    let area_of_effect = aoe.get(useitem.item);
-                    match area_of_effect {
-                        None => {
-                            let index = map.xy_index(target.x, target.y);
-                            crate::spatial::for_each_tile_content(index, |mob| targets.push(mob));
-                        }
-                        Some(area_of_effect) => {
-                            let mut blast_tiles = rltk::field_of_view(target, area_of_effect.radius, &*map);
-                            blast_tiles.retain( |p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
-                            for tile_index in blast_tiles.iter() {
-                                let index = map.xy_index(tile_index.x, tile_index.y);
-                                crate::spatial::for_each_tile_content(index, |mob| targets.push(mob));
-                                particle_builder.request(tile_index.x, tile_index.y, rltk::RGB::named(rltk::ORANGE), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('░'), 200.0);
-                            }
-                        }
-                    }
-                }
-            }
-
+        let mut remove_use: Vec<Entity> = Vec::new();
+        for (target, useitem) in (&entities, &wants_use).join() {
             // If it is equippable, then we want to equip it - and unequip whatever else was in that slot
-            let item_equippable = equippable.get(useitem.item);
-            match item_equippable {
-                None => {}
-                Some (can_equip) => {
-                    let target_slot = can_equip.slot;
-                    let target = targets[0];
+            if let Some(can_equip) = equippable.get(useitem.item) {
+                let mut target_slot = can_equip.slot;
+                let item_is_two_handed = two_handed.get(useitem.item).is_some();
 
-                    // Remove any items the target has in the item's slot
-                    let mut to_unequip: Vec<Entity> = Vec::new();
-                    for (item_entity, already_equipped, name) in (&entities, &equipped, &names).join() {
-                        if already_equipped.owner == target && already_equipped.slot == target_slot {
-                            to_unequip.push(item_entity);
-                            if target == *player_entity {
-                                gamelog.entries.push(format!("You unequiped {}.", name.name));
-                            }
+                // With a one-handed weapon already in hand and the off hand free, a second
+                // one-handed weapon goes in the off hand
+                if DUAL_WIELDING && target_slot == EquipmentSlot::Melee && !item_is_two_handed && melee_weapons.get(useitem.item).is_some() {
+                    let held = |slot: EquipmentSlot| (&entities, &equipped).join()
+                        .find(|(_, e)| e.owner == target && e.slot == slot)
+                        .map(|(item_entity, _)| item_entity);
+                    if let (Some(main_hand), None) = (held(EquipmentSlot::Melee), held(EquipmentSlot::Shield)) {
+                        if two_handed.get(main_hand).is_none() {
+                            target_slot = EquipmentSlot::Shield;
                         }
                     }
-                    for item in to_unequip.iter() {
-                        equipped.remove(*item);
-                        backpack.insert(*item, InBackpack { owner: target }).expect("Unable to insert backpack entry.");
-                    }
-
-                    // Wield the item
-                    equipped.insert(useitem.item, Equipped { owner: target, slot: target_slot }).expect("Unable to insert equipped component");
-                    backpack.remove(useitem.item);
-                    if target == *player_entity {
-                        gamelog.entries.push(format!("You equip {}.", names.get(useitem.item).unwrap().name));
-                    }
                 }
-            }
-            
-            let item_edible = provides_food.get(useitem.item);
-            match item_edible {
-                None => {}
-                Some(_) => {
-                    used_item = true;
-                    let target = targets[0];
-                    let hc = hunger_clocks.get_mut(target);
-                    if let Some(hc) = hc {
-                        hc.state = HungerState::WellFed;
-                        hc.duration = 20;
-                        gamelog.entries.push(format!("You eat the {}.", names.get(useitem.item).unwrap().name));
-                    }
-                }
-            }
-
 
-            let item_heals = healing.get(useitem.item);
-            match item_heals {
-                None => {},
-                Some(healer) => {
-                    used_item = false;
-                    for target in targets.iter() {
-                        let pools = pools.get_mut(*target);
-                        if let Some(pools) = pools {
-                            pools.hit_points.current = i32::min(pools.hit_points.max, pools.hit_points.current + healer.heal_amount);
-                            if entity == *player_entity {
-                                gamelog.entries.push(format!("You drink the {}, healing {} hp.", names.get(useitem.item).unwrap().name, healer.heal_amount));
-                            }
-                        }
-                        used_item = true;
-
-                        let pos = positions.get(*target);
-                        if let Some(pos) = pos {
-                            particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::GREEN), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('♥'), 200.0);
-                        }
-                    }  
-                }
-            }
+                // Anything in the same slot has to come off; a two-handed weapon also needs the
+                // off hand free, and anything going in the off hand displaces a two-handed weapon
+                let displaces = |item_entity: Entity, already_equipped: &Equipped| {
+                    already_equipped.owner == target && (already_equipped.slot == target_slot
+                        || (item_is_two_handed && already_equipped.slot == EquipmentSlot::Shield)
+                        || (target_slot == EquipmentSlot::Shield && two_handed.get(item_entity).is_some()))
+                };
 
-            let item_damages = inflict_damage.get(useitem.item);
-            match item_damages {
-                None => {},
-                Some(damage) => {
-                    used_item = false;
-                    for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage, false);
-                        if entity == *player_entity {
-                            let mob_name = names.get(*mob).unwrap();
-                            let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(format!("You use {} on {}, inflicting {} hp.", item_name.name, mob_name.name, damage.damage));
-                            
-                            let pos = positions.get(*mob);
-                            if let Some(pos) = pos {
-                                particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::RED), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('‼'), 200.0);
-                            }
-                        }
-                        used_item = true;
+                // A cursed item in the way can't be swapped out
+                remove_use.push(target);
+                let stuck = (&entities, &equipped, &cursed).join()
+                    .find(|(item_entity, already_equipped, _)| displaces(*item_entity, already_equipped))
+                    .map(|(item_entity, _, _)| item_entity);
+                if let Some(stuck) = stuck {
+                    if target == *player_entity {
+                        gamelog.entries.push(format!("You cannot remove the {}; it is cursed!", dm.display_name(&names.get(stuck).unwrap().name)));
                     }
+                    continue;
                 }
-            }
 
-            let mut add_confusion = Vec::new();
-            {
-                let causes_confusion = confused.get(useitem.item);
-                match causes_confusion {
-                    None => {},
-                    Some(confusion) => {
-                        used_item = false;
-                        for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns));
-                            if entity == *player_entity {
-                                let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(useitem.item).unwrap();
-                                gamelog.entries.push(format!("You use {} on {}, confusing them.", item_name.name, mob_name.name));
-                            
-                                let pos = positions.get(*mob);
-                                if let Some(pos) = pos {
-                                    particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::MAGENTA), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('?'), 200.0);
-                                }
-                            }
-                            used_item = true;
+                // Remove any items the target has in the way
+                let mut to_unequip: Vec<Entity> = Vec::new();
+                for (item_entity, already_equipped, name) in (&entities, &equipped, &names).join() {
+                    if displaces(item_entity, already_equipped) {
+                        to_unequip.push(item_entity);
+                        if target == *player_entity {
+                            gamelog.entries.push(format!("You unequiped {}.", dm.display_name(&name.name)));
                         }
                     }
                 }
-            }
-            for mob in add_confusion.iter() {
-                confused.insert(mob.0, Confusion {turns: mob.1}).expect("Unable to insert status");
-            }
+                for item in to_unequip.iter() {
+                    equipped.remove(*item);
+                    backpack.insert(*item, InBackpack { owner: target }).expect("Unable to insert backpack entry.");
+                }
 
-            if used_item {
-                let consumable = consumables.get(useitem.item);
-                match consumable {
-                    None => {},
-                    Some(_) => {
-                        entities.delete(useitem.item).expect("Delete failed");
+                // Wield the item
+                equipped.insert(useitem.item, Equipped { owner: target, slot: target_slot }).expect("Unable to insert equipped component");
+                backpack.remove(useitem.item);
+                if target == *player_entity {
+                    // Wearing an item reveals what it is
+                    let name = &names.get(useitem.item).unwrap().name;
+                    let unidentified_name = dm.display_name(name);
+                    if dm.identify(name) {
+                        gamelog.entries.push(format!("You equip the {}, and recognize it as {}.", unidentified_name, name));
+                    } else if target_slot == EquipmentSlot::Shield && can_equip.slot == EquipmentSlot::Melee {
+                        gamelog.entries.push(format!("You wield {} in your off hand.", name));
+                    } else {
+                        gamelog.entries.push(format!("You equip {}.", name));
+                    }
+                    if cursed.get(useitem.item).is_some() {
+                        gamelog.entries.push(format!("The {} binds itself to you. It is cursed!", name));
                     }
                 }
             }
         }
 
+        for target in remove_use.iter() {
+            dirty.insert(*target, EquipmentChanged {}).expect("Unable to insert");
+            wants_use.remove(*target);
+        }
+    }
+}
+
+pub struct ItemUseSystem {}
+
+impl<'a> System<'a> for ItemUseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, AreaOfEffect>,
+        WriteStorage<'a, EquipmentChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            entities,
+            mut wants_use,
+            aoe,
+            mut dirty
+        ) = data;
+
+        for (entity, useitem) in (&entities, &wants_use).join() {
+            dirty.insert(entity, EquipmentChanged {}).expect("Unable to insert");
+            add_effect(
+                Some(entity),
+                EffectType::ItemUse { item: useitem.item },
+                item_targets(&map, entity, useitem.target, aoe.get(useitem.item))
+            );
+        }
+
         wants_use.clear();
     }
 }
@@ -19,6 +19,7 @@ use inventory_system::ItemUseSystem;
 pub use map::*;
 pub use map_indexing_system::*;
 use melee_combat_system::MeleeCombatSystem;
+use ranged_combat_system::RangedCombatSystem;
 pub use player::*;
 use random_tables::RandomTable;
 pub use rect::*;
@@ -33,6 +34,7 @@ mod map_indexing_system;
 mod rect;
 mod visibility_system;
 mod melee_combat_system;
+mod ranged_combat_system;
 mod damage_system;
 mod inventory_system;
 mod spawner;
@@ -40,6 +42,9 @@ mod spawner;
 mod gui;
 mod gamelog;
 mod random_tables;
+mod dice;
+mod action_queue;
+mod travel;
 pub mod saveload_system;
 pub mod map_builders;
 pub mod raws;
@@ -51,6 +56,10 @@ mod gamesystem;
 mod lighting_system;
 mod ai;
 mod spatial;
+mod trigger_system;
+mod spell_system;
+mod status_effect_system;
+pub mod effects;
 
 const SHOW_MAPGEN_VISUALIZER : bool = true;
 const MAP_WIDTH: i32 = 80;
@@ -67,7 +76,11 @@ pub enum RunState {
     ShowInventory, 
     ShowDropItem,
     ShowTargeting { range: i32, item: Entity },
+    ShowRangedTargeting { range: i32 },
+    ShowCastSpell,
     MainMenu { menu_selection: gui::MainMenuSelection },
+    LevelUp { selection: i32 },
+    CharacterCreation,
     SaveGame,
     NextLevel,
     PreviousLevel,
@@ -76,6 +89,11 @@ pub enum RunState {
     MapGeneration,
     ShowCheatMenu,
     ShowVendor { vendor: Entity, mode: VendorMode },
+    ShowTemple { temple: Entity },
+    ShowCombatHistory { offset: i32 },
+    ShowRestMenu,
+    ShowAutoPickupMenu,
+    MagicMapReveal { row: i32 },
 }
 
 pub struct State {
@@ -84,6 +102,7 @@ pub struct State {
     mapgen_history: Vec<Map>,
     mapgen_index: usize,
     mapgen_timer: f32,
+    character: gui::CharacterSheet,
 }
 
 impl State {
@@ -94,6 +113,8 @@ impl State {
         vis.run_now(&self.ecs);
         let mut initiative = InitiativeSystem{};
         initiative.run_now(&self.ecs);
+        let mut statuses = status_effect_system::StatusEffectSystem{};
+        statuses.run_now(&self.ecs);
         let mut turnstatus = TurnStatusSystem{};
         turnstatus.run_now(&self.ecs);
         let mut quipper = QuipSystem{};
@@ -110,12 +131,20 @@ impl State {
         chase.run_now(&self.ecs);
         let mut defaultmove = DefaultMoveAI{};
         defaultmove.run_now(&self.ecs);
+        let mut actions = ai::ActionRecordSystem{};
+        actions.run_now(&self.ecs);
+        let mut triggers = trigger_system::TriggerSystem{};
+        triggers.run_now(&self.ecs);
         let mut melee = MeleeCombatSystem{};
         melee.run_now(&self.ecs);
-        let mut damage = DamageSystem{};
-        damage.run_now(&self.ecs);
+        let mut ranged = RangedCombatSystem{};
+        ranged.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem{};
         pickup.run_now(&self.ecs);
+        let mut spells = spell_system::SpellCastSystem{};
+        spells.run_now(&self.ecs);
+        let mut item_equip = inventory_system::ItemEquipOnUse{};
+        item_equip.run_now(&self.ecs);
         let mut item_use = ItemUseSystem{};
         item_use.run_now(&self.ecs);
         let mut item_drop = ItemDropSystem{};
@@ -124,6 +153,9 @@ impl State {
         item_remove.run_now(&self.ecs);
         let mut hunger = HungerSystem{};
         hunger.run_now(&self.ecs);
+        effects::run_effects_queue(&mut self.ecs);
+        let mut damage = DamageSystem{};
+        damage.run_now(&self.ecs);
         let mut lighting = lighting_system::LightingSystem{};
         lighting.run_now(&self.ecs);
         let mut particles = particle_system::ParticleSpawnSystem{};
@@ -163,8 +195,11 @@ impl State {
         }
 
 
+        raws::spawn_all_spells(&mut self.ecs);
+
         // Replace the world maps
-        self.ecs.insert(map::MasterDungeonMap::new());
+        let dungeon_master = map::MasterDungeonMap::new(&mut self.ecs.write_resource::<rltk::RandomNumberGenerator>());
+        self.ecs.insert(dungeon_master);
 
         // Build a new map and place the player
         self.generate_world_map(1, 0);
@@ -197,7 +232,7 @@ impl GameState for State {
         particle_system::cull_dead_particles(&mut self.ecs, ctx);
 
         match newrunstate {
-            RunState::MainMenu{ .. } => { }
+            RunState::MainMenu{ .. } | RunState::CharacterCreation => { }
             _ => {
                 camera::render_camera(&self.ecs, ctx);
                 gui::draw_ui(&self.ecs, ctx);
@@ -231,7 +266,17 @@ impl GameState for State {
             }
             RunState::AwaitingInput => {
                 println!("AwaitingInput");
-                newrunstate = player_input(self, ctx);
+                // Any key but escape (save and quit), or a click, stops a queued action
+                if ctx.key.is_none() && !ctx.left_click {
+                    newrunstate = match action_queue::run_queue(&mut self.ecs) {
+                        Some(state) => state,
+                        None => player_input(self, ctx)
+                    };
+                } else if ctx.key != Some(rltk::VirtualKeyCode::Escape) && action_queue::is_busy(&self.ecs) {
+                    action_queue::stop(&mut self.ecs);
+                } else {
+                    newrunstate = player_input(self, ctx);
+                }
             }
             RunState::Ticking => {
                 while newrunstate == RunState::Ticking {
@@ -239,11 +284,31 @@ impl GameState for State {
                     self.run_systems();
                     self.ecs.maintain();
                     match *self.ecs.fetch::<RunState>() {
-                        RunState::AwaitingInput => newrunstate = RunState::AwaitingInput,
+                        RunState::AwaitingInput => {
+                            let player_entity = self.ecs.fetch::<Entity>();
+                            if self.ecs.read_storage::<PendingLevelUp>().get(*player_entity).is_some() {
+                                newrunstate = RunState::LevelUp { selection: 0 };
+                            } else {
+                                newrunstate = RunState::AwaitingInput;
+                            }
+                        }
+                        RunState::MagicMapReveal{ .. } => newrunstate = RunState::MagicMapReveal{ row: 0 },
                         _ => newrunstate = RunState::Ticking
                     }
                 }
             }
+            RunState::MagicMapReveal{ row } => {
+                let mut map = self.ecs.fetch_mut::<Map>();
+                for x in 0..map.width {
+                    let index = map.xy_index(x, row);
+                    map.revealed_tiles[index] = true;
+                }
+                if row == map.height - 1 {
+                    newrunstate = RunState::Ticking;
+                } else {
+                    newrunstate = RunState::MagicMapReveal{ row: row + 1 };
+                }
+            }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
                 match result.0 {
@@ -251,15 +316,12 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {},
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        let is_ranged = self.ecs.read_storage::<Ranged>();
-                        let is_item_ranged = is_ranged.get(item_entity);
+                        let range = self.ecs.read_storage::<Ranged>().get(item_entity).map(|ranged| ranged.range);
                         
-                        if let Some(is_item_ranged) = is_item_ranged {
-                            newrunstate = RunState::ShowTargeting { range: is_item_ranged.range, item: item_entity };
+                        if let Some(range) = range {
+                            newrunstate = RunState::ShowTargeting { range, item: item_entity };
                         } else {
-                            let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                            intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem{ item: item_entity, target: None }).expect("Unable to insert intent.");
-                            newrunstate = RunState::Ticking;
+                            newrunstate = action_queue::use_item(&mut self.ecs, item_entity, None);
                         }
                     }
                 }
@@ -283,12 +345,33 @@ impl GameState for State {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {},
                     gui::ItemMenuResult::Selected => {
-                        let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem { 
-                            item, 
-                            target: result.1 
-                        }).expect("Unable to insert intent.");
-                        newrunstate = RunState::Ticking;
+                        if self.ecs.read_storage::<SpellTemplate>().get(item).is_some() {
+                            let mut intent = self.ecs.write_storage::<WantsToCastSpell>();
+                            intent.insert(*self.ecs.fetch::<Entity>(), WantsToCastSpell { spell: item, target: result.1 }).expect("Unable to insert intent.");
+                            newrunstate = RunState::Ticking;
+                        } else {
+                            newrunstate = action_queue::use_item(&mut self.ecs, item, result.1);
+                        }
+                    }
+                }
+            }
+            RunState::ShowRangedTargeting{range} => {
+                let result = gui::ranged_target(self, ctx, range);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {},
+                    gui::ItemMenuResult::Selected => {
+                        newrunstate = try_fire_at(&mut self.ecs, result.1.unwrap());
+                    }
+                }
+            }
+            RunState::ShowCastSpell => {
+                let result = gui::cast_spell_menu(self, ctx);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {},
+                    gui::ItemMenuResult::Selected => {
+                        newrunstate = cast_spell(&mut self.ecs, result.1.unwrap());
                     }
                 }
             }
@@ -313,7 +396,10 @@ impl GameState for State {
                     },
                     gui::MainMenuResult::Selected { selected } => {
                         match selected {
-                            gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
+                            gui::MainMenuSelection::NewGame => {
+                                self.character = gui::CharacterSheet::new();
+                                newrunstate = RunState::CharacterCreation;
+                            }
                             gui::MainMenuSelection::LoadGame =>  {
                                 saveload_system::load_game(&mut self.ecs);
                                 newrunstate = RunState::AwaitingInput;
@@ -324,6 +410,28 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::LevelUp { selection } => {
+                let result = gui::level_up_menu(self, ctx, selection);
+                match result {
+                    gui::LevelUpResult::NoResponse { selection } => newrunstate = RunState::LevelUp { selection },
+                    gui::LevelUpResult::Selected { selection } => {
+                        if player::spend_level_up_point(&mut self.ecs, selection) {
+                            newrunstate = RunState::AwaitingInput;
+                        }
+                    }
+                }
+            }
+            RunState::CharacterCreation => {
+                let result = gui::character_creation(self, ctx);
+                match result {
+                    gui::CharacterCreationResult::NoResponse => {}
+                    gui::CharacterCreationResult::Cancel => newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame },
+                    gui::CharacterCreationResult::Done => {
+                        spawner::apply_character(&mut self.ecs, &self.character);
+                        newrunstate = RunState::PreRun;
+                    }
+                }
+            }
             RunState::SaveGame => {
                 saveload_system::save_game(&mut self.ecs);
                 newrunstate = RunState::MainMenu{ menu_selection : gui::MainMenuSelection::LoadGame };
@@ -358,6 +466,44 @@ impl GameState for State {
                         self.mapgen_next_state = Some(RunState::PreRun);
                         newrunstate = RunState::MapGeneration;
                     }
+                    gui::CheatMenuResult::ReloadRaws => {
+                        raws::reload_raws(&mut self.ecs, false);
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                    gui::CheatMenuResult::RefreshFromRaws => {
+                        raws::reload_raws(&mut self.ecs, true);
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                    gui::CheatMenuResult::ToggleVerboseCombat => {
+                        let mut combat_log = self.ecs.fetch_mut::<gamelog::CombatLog>();
+                        combat_log.verbose = !combat_log.verbose;
+                    }
+                    gui::CheatMenuResult::CombatHistory => newrunstate = RunState::ShowCombatHistory { offset: 0 },
+                }
+            }
+            RunState::ShowRestMenu => {
+                match gui::show_rest_menu(ctx) {
+                    gui::RestMenuResult::NoResponse => {},
+                    gui::RestMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::RestMenuResult::UntilHealed => newrunstate = action_queue::rest(&mut self.ecs, None),
+                    gui::RestMenuResult::Turns(turns) => newrunstate = action_queue::rest(&mut self.ecs, Some(turns)),
+                }
+            }
+            RunState::ShowAutoPickupMenu => {
+                let result = gui::show_auto_pickup_menu(self, ctx);
+                let mut rules = self.ecs.fetch_mut::<travel::AutoPickup>();
+                match result {
+                    gui::AutoPickupMenuResult::NoResponse => {},
+                    gui::AutoPickupMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::AutoPickupMenuResult::ToggleConsumables => rules.consumables = !rules.consumables,
+                    gui::AutoPickupMenuResult::ToggleAmmunition => rules.ammunition = !rules.ammunition,
+                    gui::AutoPickupMenuResult::ToggleEquipment => rules.equipment = !rules.equipment,
+                }
+            }
+            RunState::ShowCombatHistory { offset } => {
+                match gui::combat_history(self, ctx, offset) {
+                    gui::CombatHistoryResult::NoResponse { offset } => newrunstate = RunState::ShowCombatHistory { offset },
+                    gui::CombatHistoryResult::Close => newrunstate = RunState::AwaitingInput,
                 }
             }
             RunState::ShowVendor{vendor, mode} => {
@@ -380,12 +526,40 @@ impl GameState for State {
                             std::mem::drop(pools);
                             let player_entity = *self.ecs.fetch::<Entity>();
                             crate::raws::spawn_named_item(&RAWS.lock().unwrap(), &mut self.ecs, &tag, SpawnType::Carried{ by: player_entity });
+                            self.ecs.write_resource::<map::MasterDungeonMap>().identify(&tag);
                         }
                     },
                     gui::VendorResult::BuyMode => newrunstate = RunState::ShowVendor { vendor, mode: VendorMode::Buy },
                     gui::VendorResult::SellMode => newrunstate = RunState::ShowVendor { vendor, mode: VendorMode::Sell },
                 }
             }
+            RunState::ShowTemple{temple} => {
+                let result = gui::show_temple(self, ctx, temple);
+                match result {
+                    gui::TempleResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::TempleResult::NoResponse => {},
+                    gui::TempleResult::RemoveCurse => {
+                        let cost = self.ecs.read_storage::<Temple>().get(temple).map_or(0.0, |t| t.remove_curse_cost);
+                        let player_entity = *self.ecs.fetch::<Entity>();
+                        let has_curses = !effects::cursed_items(&self.ecs, player_entity).is_empty();
+                        let mut pools = self.ecs.write_storage::<Pools>();
+                        let player_pools = pools.get_mut(player_entity).unwrap();
+                        if !has_curses {
+                            std::mem::drop(pools);
+                            self.ecs.fetch_mut::<gamelog::GameLog>().entries.push("The priest finds no curse on you, and asks for nothing.".to_string());
+                        } else if player_pools.gold >= cost {
+                            player_pools.gold -= cost;
+                            std::mem::drop(pools);
+                            effects::add_effect(None, effects::EffectType::RemoveCurse, effects::Targets::Single { target: player_entity });
+                            effects::run_effects_queue(&mut self.ecs);
+                        } else {
+                            std::mem::drop(pools);
+                            self.ecs.fetch_mut::<gamelog::GameLog>().entries.push("You can't afford the priest's services.".to_string());
+                        }
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                }
+            }
         }
 
         {
@@ -401,6 +575,20 @@ impl GameState for State {
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
 
+    // Validate the raws and exit without opening a window
+    if std::env::args().any(|arg| arg == "--check-raws") {
+        let errors = raws::check_raws();
+        for error in errors.iter() {
+            eprintln!("{}", error);
+        }
+        if !errors.is_empty() {
+            eprintln!("{} problem(s) found in raws.", errors.len());
+            std::process::exit(1);
+        }
+        println!("Raws OK.");
+        std::process::exit(0);
+    }
+
     let mut context = RltkBuilder::simple(80, 60)
         .unwrap()
         .with_title("Roguelike Tutorial")
@@ -413,6 +601,7 @@ fn main() -> rltk::BError {
         mapgen_index: 0,
         mapgen_history: Vec::new(),
         mapgen_timer: 0.0,
+        character: gui::CharacterSheet::new(),
     };
 
     rltk::console::log(format!("Registering components."));
@@ -468,21 +657,56 @@ fn main() -> rltk::BError {
     gs.ecs.register::<ParticleLifetime>();
     gs.ecs.register::<HungerClock>();
     gs.ecs.register::<ProvidesFood>();
+    gs.ecs.register::<EntryTrigger>();
+    gs.ecs.register::<SingleActivation>();
+    gs.ecs.register::<RandomTeleport>();
+    gs.ecs.register::<MagicMapper>();
+    gs.ecs.register::<RangedWeapon>();
+    gs.ecs.register::<Ammunition>();
+    gs.ecs.register::<WantsToShoot>();
+    gs.ecs.register::<SpellTemplate>();
+    gs.ecs.register::<KnownSpells>();
+    gs.ecs.register::<WantsToCastSpell>();
+    gs.ecs.register::<TeachesSpell>();
+    gs.ecs.register::<StatusEffect>();
+    gs.ecs.register::<Duration>();
+    gs.ecs.register::<DamageOverTime>();
+    gs.ecs.register::<HealOverTime>();
+    gs.ecs.register::<Slow>();
+    gs.ecs.register::<AttributeBonus>();
+    gs.ecs.register::<SkillBonus>();
+    gs.ecs.register::<FactionStanding>();
+    gs.ecs.register::<PendingLevelUp>();
+    gs.ecs.register::<CursedItem>();
+    gs.ecs.register::<ProvidesRemoveCurse>();
+    gs.ecs.register::<Temple>();
+    gs.ecs.register::<TwoHanded>();
+    gs.ecs.register::<Shield>();
+    gs.ecs.register::<Resistances>();
+    gs.ecs.register::<TookAction>();
+    gs.ecs.register::<UseTurns>();
+    gs.ecs.register::<ActionQueue>();
+    gs.ecs.register::<GeneratedItem>();
+    gs.ecs.register::<InflictsStatus>();
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
 
     raws::load_raws();
 
-    gs.ecs.insert(map::MasterDungeonMap::new());
+    gs.ecs.insert(rltk::RandomNumberGenerator::new());
+    let dungeon_master = map::MasterDungeonMap::new(&mut gs.ecs.write_resource::<rltk::RandomNumberGenerator>());
+    gs.ecs.insert(dungeon_master);
     gs.ecs.insert(Map::new(1, MAP_WIDTH, MAP_HEIGHT, "New Map"));
     gs.ecs.insert(Point::new(0, 0));
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
 
     let player_entity = spawner::player(&mut gs.ecs, 0, 0);
     gs.ecs.insert(player_entity);
+    raws::spawn_all_spells(&mut gs.ecs);
     
     gs.ecs.insert(RunState::MapGeneration{});
     gs.ecs.insert(gamelog::GameLog{ entries: vec!["Welcome to Rusty Roguelike!".to_string()]});
+    gs.ecs.insert(gamelog::CombatLog::default());
+    gs.ecs.insert(travel::AutoPickup::default());
     gs.ecs.insert(particle_system::ParticleBuilder::new());
     gs.ecs.insert(rex_assets::RexAssets::new());
 
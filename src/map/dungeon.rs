@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
 use crate::{Map, TileType, Position, Viewshed, OtherLevelPosition};
 use specs::{World, WorldExt, Entity, Join};
@@ -7,12 +7,46 @@ use crate::map_builders::level_builder;
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct MasterDungeonMap {
-    maps: HashMap<i32, Map>
+    maps: HashMap<i32, Map>,
+    identified_items: HashSet<String>,
+    obfuscated_names: HashMap<String, String>
 }
 
 impl MasterDungeonMap {
-    pub fn new() -> MasterDungeonMap {
-        MasterDungeonMap { maps: HashMap::new() }
+    /// Starts a new run, giving every magic item in the raws a fresh unidentified name.
+    pub fn new(rng: &mut RandomNumberGenerator) -> MasterDungeonMap {
+        let mut dm = MasterDungeonMap { maps: HashMap::new(), identified_items: HashSet::new(), obfuscated_names: HashMap::new() };
+
+        let mut used_names: HashSet<String> = HashSet::new();
+        for (name, naming) in crate::raws::get_magic_item_namings(&crate::raws::RAWS.lock().unwrap()) {
+            let obfuscated = match naming.as_str() {
+                "scroll" => unique_name(&mut used_names, || make_scroll_name(rng)),
+                "potion" => unique_name(&mut used_names, || make_potion_name(rng)),
+                _ => naming
+            };
+            dm.obfuscated_names.insert(name, obfuscated);
+        }
+
+        dm
+    }
+
+    /// The name the player knows an item by: its unidentified name until they have
+    /// identified that kind of item, and its real name otherwise.
+    pub fn display_name(&self, name: &str) -> String {
+        match self.obfuscated_names.get(name) {
+            Some(obfuscated) if !self.identified_items.contains(name) => obfuscated.clone(),
+            _ => name.to_string()
+        }
+    }
+
+    /// Whether the player knows what this kind of item really is.
+    pub fn is_identified(&self, name: &str) -> bool {
+        !self.obfuscated_names.contains_key(name) || self.identified_items.contains(name)
+    }
+
+    /// Identifies every item with this name. Returns true if it was previously unknown.
+    pub fn identify(&mut self, name: &str) -> bool {
+        self.obfuscated_names.contains_key(name) && self.identified_items.insert(name.to_string())
     }
 
     pub fn store_map(&mut self, map: &Map) {
@@ -29,6 +63,54 @@ impl MasterDungeonMap {
     }
 }
 
+const SCROLL_SYLLABLES: [&str; 16] = ["ZOR", "BAX", "KLA", "THU", "NEM", "OX", "VAR", "QUI",
+    "DRE", "LOM", "SNA", "GRI", "FEL", "UMA", "PRAX", "ITH"];
+const POTION_ADJECTIVES: [&str; 10] = ["Swirling", "Effervescent", "Slimy", "Oily", "Viscous", "Smoking",
+    "Glowing", "Bubbling", "Murky", "Sparkling"];
+const POTION_COLORS: [&str; 10] = ["Red", "Orange", "Yellow", "Green", "Blue", "Indigo", "Violet", "Black",
+    "White", "Silver"];
+
+/// How many fresh names to try before numbering a repeat instead.
+const UNIQUE_NAME_ATTEMPTS: usize = 100;
+
+fn unique_name<F: FnMut() -> String>(used_names: &mut HashSet<String>, mut make_name: F) -> String {
+    let mut name = make_name();
+    for _ in 1..UNIQUE_NAME_ATTEMPTS {
+        if !used_names.contains(&name) {
+            break;
+        }
+        name = make_name();
+    }
+    if used_names.insert(name.clone()) {
+        return name;
+    }
+
+    // The pool of names has run dry, so tell this one apart with a number
+    let mut n = 2;
+    loop {
+        let numbered = format!("{} ({})", name, n);
+        if used_names.insert(numbered.clone()) {
+            return numbered;
+        }
+        n += 1;
+    }
+}
+
+fn make_scroll_name(rng: &mut RandomNumberGenerator) -> String {
+    let syllables = rng.roll_dice(1, 2) + 1;
+    let mut word = String::new();
+    for _ in 0..syllables {
+        word += SCROLL_SYLLABLES[rng.roll_dice(1, SCROLL_SYLLABLES.len() as i32) as usize - 1];
+    }
+    format!("Scroll of {}", word)
+}
+
+fn make_potion_name(rng: &mut RandomNumberGenerator) -> String {
+    let adjective = POTION_ADJECTIVES[rng.roll_dice(1, POTION_ADJECTIVES.len() as i32) as usize - 1];
+    let color = POTION_COLORS[rng.roll_dice(1, POTION_COLORS.len() as i32) as usize - 1];
+    format!("{} {} Potion", adjective, color)
+}
+
 pub fn level_transition(ecs: &mut World, new_depth: i32, offset: i32) -> Option<Vec<Map>> {
     // Obtain the master dungeon map
     let dungeon_master = ecs.read_resource::<MasterDungeonMap>();
@@ -172,3 +254,16 @@ fn transition_to_existing_map(ecs: &mut World, new_depth: i32, offset: i32) {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_name_numbers_repeats_once_names_run_out() {
+        let mut used_names = HashSet::new();
+        assert_eq!(unique_name(&mut used_names, || "Murky Red Potion".to_string()), "Murky Red Potion");
+        assert_eq!(unique_name(&mut used_names, || "Murky Red Potion".to_string()), "Murky Red Potion (2)");
+        assert_eq!(unique_name(&mut used_names, || "Murky Red Potion".to_string()), "Murky Red Potion (3)");
+    }
+}
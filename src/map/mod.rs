@@ -9,9 +9,8 @@ mod themes;
 pub mod dungeon;
 pub use dungeon::{MasterDungeonMap, level_transition, freeze_level_entities, thaw_level_entities};
 
-pub use tiletype::{TileType, tile_walkable, tile_opaque};
+pub use tiletype::{TileType, tile_walkable, tile_opaque, tile_cost};
 pub use themes::*;
-use crate::map::tiletype::tile_cost;
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Map {
@@ -1,8 +1,10 @@
 use specs::prelude::*;
-use super::{Pools, WantsToMelee, Name, SufferDamage, GameLog};
-use crate::particle_system::ParticleBuilder;
-use crate::{Attributes, Skills, Position, Skill, NaturalAttackDefense, MeleeWeapon, WeaponAttribute, Equipped, EquipmentSlot, Wearable, HungerClock, HungerState};
-use crate::gamesystem::skill_bonus;
+use super::{Pools, WantsToMelee, Name, GameLog};
+use crate::gamelog::{CombatLog, AttackRecord, AttackOutcome};
+use crate::dice::DiceExpr;
+use crate::effects::{add_effect, mitigated_damage_in, EffectType, Targets};
+use crate::{Attributes, Skills, Skill, NaturalAttackDefense, MeleeWeapon, WeaponAttribute, Equipped, EquipmentSlot, Wearable, HungerClock, HungerState, Shield, DamageType, Resistances};
+use crate::gamesystem::{skill_bonus, practice_skill, block_chance, OFF_HAND_HIT_PENALTY, CRITICAL_HIT_MULTIPLIER};
 
 pub struct MeleeCombatSystem {}
 
@@ -13,10 +15,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, Attributes>,
-        ReadStorage<'a, Skills>,
-        WriteStorage<'a, SufferDamage>,
-        WriteExpect<'a, ParticleBuilder>,
-        ReadStorage<'a, Position>,
+        WriteStorage<'a, Skills>,
         ReadStorage<'a, Pools>,
         WriteExpect<'a, rltk::RandomNumberGenerator>,
         ReadStorage<'a, Equipped>,
@@ -25,6 +24,9 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, NaturalAttackDefense>,
         ReadStorage<'a, HungerClock>,
         ReadExpect<'a, Entity>,
+        ReadStorage<'a, Shield>,
+        WriteExpect<'a, CombatLog>,
+        ReadStorage<'a, Resistances>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -36,10 +38,7 @@ impl<'a> System<'a> for MeleeCombatSystem {
             mut wants_melee,
             names,
             attributes,
-            skills,
-            mut inflict_damage,
-            mut particle_builder,
-            positions,
+            mut skills,
             pools,
             mut rng,
             equipped_items,
@@ -48,8 +47,13 @@ impl<'a> System<'a> for MeleeCombatSystem {
             natural,
             hunger_clocks,
             player_entity,
+            shields,
+            mut combat_log,
+            resistances,
         ) = data;
 
+        let mut practiced: Vec<(Entity, Skill)> = Vec::new();
+
         for (entity, wants_melee, name, attacker_attributes, attacker_skills, attacker_pools)
             in (&entities, &wants_melee, &names, &attributes, &skills, &pools).join()
         {
@@ -65,40 +69,35 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 let mut weapon_info = MeleeWeapon{
                     attribute : WeaponAttribute::Might,
                     hit_bonus : 0,
-                    damage_n_dice : 1,
-                    damage_die_type : 4,
-                    damage_bonus : 0
+                    damage : DiceExpr::new(1, 4, 0),
+                    damage_type : DamageType::Bludgeoning,
+                    critical_multiplier : CRITICAL_HIT_MULTIPLIER
                 };
 
                 if let Some(nat) = natural.get(entity) {
                     if !nat.attacks.is_empty() {
                         let attack_index = if nat.attacks.len()==1 { 0 } else { rng.roll_dice(1, nat.attacks.len() as i32) as usize -1 };
                         weapon_info.hit_bonus = nat.attacks[attack_index].hit_bonus;
-                        weapon_info.damage_n_dice = nat.attacks[attack_index].damage_n_dice;
-                        weapon_info.damage_die_type = nat.attacks[attack_index].damage_die_type;
-                        weapon_info.damage_bonus = nat.attacks[attack_index].damage_bonus;
+                        weapon_info.damage = nat.attacks[attack_index].damage.clone();
+                        weapon_info.damage_type = nat.attacks[attack_index].damage_type;
                     }
                 }
 
+                // The main hand always attacks; a weapon in the off hand follows up at a penalty
+                let mut attacks : Vec<(MeleeWeapon, i32)> = Vec::new();
+                let mut off_hand : Option<MeleeWeapon> = None;
                 for (wielded,melee) in (&equipped_items, &meleeweapons).join() {
                     if wielded.owner == entity && wielded.slot == EquipmentSlot::Melee {
                         weapon_info = melee.clone();
                     }
-                }
-
-                let natural_roll = rng.roll_dice(1, 20);
-                let attribute_hit_bonus =
-                    if weapon_info.attribute == WeaponAttribute::Might { attacker_attributes.might.bonus }
-                    else { attacker_attributes.quickness.bonus};
-                let skill_hit_bonus = skill_bonus(Skill::Melee, &*attacker_skills);
-                let weapon_hit_bonus = weapon_info.hit_bonus;
-                let mut status_hit_bonus = 0;
-                if let Some(hc) = hunger_clocks.get(entity) {
-                    if hc.state == HungerState::WellFed {
-                        status_hit_bonus += 1;
+                    if wielded.owner == entity && wielded.slot == EquipmentSlot::Shield {
+                        off_hand = Some(melee.clone());
                     }
                 }
-                let modified_hit_roll = natural_roll + attribute_hit_bonus + skill_hit_bonus + weapon_hit_bonus + status_hit_bonus;
+                attacks.push((weapon_info, 0));
+                if let Some(off_hand) = off_hand {
+                    attacks.push((off_hand, OFF_HAND_HIT_PENALTY));
+                }
 
                 let mut armor_item_bonus_f = 0.0;
                 for (wielded,armor) in (&equipped_items, &wearables).join() {
@@ -106,6 +105,9 @@ impl<'a> System<'a> for MeleeCombatSystem {
                         armor_item_bonus_f += armor.armor_class;
                     }
                 }
+                let target_shield = (&equipped_items, &shields).join()
+                    .find(|(wielded, _)| wielded.owner == wants_melee.target && wielded.slot == EquipmentSlot::Shield)
+                    .map(|(_, shield)| shield);
 
                 let base_armor_class = match natural.get(wants_melee.target) {
                     None => 10,
@@ -114,37 +116,120 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 let armor_quickness_bonus = target_attributes.quickness.bonus;
                 let armor_skill_bonus = skill_bonus(Skill::Defense, &*target_skills);
                 let armor_item_bonus = armor_item_bonus_f as i32;
-                let armor_class = base_armor_class + armor_quickness_bonus + armor_skill_bonus + armor_item_bonus;
-
-                if natural_roll != 1 && (natural_roll == 20 || modified_hit_roll > armor_class) {
-                    // Target hit! Until we support weapons, we're going with 1d4
-                    let base_damage = rng.roll_dice(weapon_info.damage_n_dice, weapon_info.damage_die_type);
-                    let attr_damage_bonus = attacker_attributes.might.bonus;
-                    let skill_damage_bonus = skill_bonus(Skill::Melee, &*attacker_skills);
-                    let weapon_damage_bonus = weapon_info.damage_bonus;
-
-                    let damage = i32::max(0, base_damage + attr_damage_bonus + skill_hit_bonus + skill_damage_bonus + weapon_damage_bonus);
-                    SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage, entity == *player_entity);
-                    log.entries.push(format!("{} hits {}, for {} hp.", &name.name, &target_name.name, damage));
-                    if let Some(pos) = positions.get(wants_melee.target) {
-                        particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::ORANGE), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('‼'), 200.0);
-                    }
-                } else if natural_roll == 1 {
-                    // Natural 1 miss
-                    log.entries.push(format!("{} considers attacking {}, but misjudges the timing.", name.name, target_name.name));
-                    if let Some(pos) = positions.get(wants_melee.target) {
-                        particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::BLUE), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('‼'), 200.0);
+                let armor_shield_bonus = target_shield.map_or(0, |shield| shield.defense_bonus);
+                let armor_class = base_armor_class + armor_quickness_bonus + armor_skill_bonus + armor_item_bonus + armor_shield_bonus;
+
+                for (weapon_info, hand_hit_bonus) in attacks {
+                    let natural_roll = rng.roll_dice(1, 20);
+                    let attribute_hit_bonus =
+                        if weapon_info.attribute == WeaponAttribute::Might { attacker_attributes.might.bonus }
+                        else { attacker_attributes.quickness.bonus};
+                    let skill_hit_bonus = skill_bonus(Skill::Melee, &*attacker_skills);
+                    let weapon_hit_bonus = weapon_info.hit_bonus;
+                    let mut status_hit_bonus = 0;
+                    if let Some(hc) = hunger_clocks.get(entity) {
+                        if hc.state == HungerState::WellFed {
+                            status_hit_bonus += 1;
+                        }
                     }
-                } else {
-                    // Miss
-                    log.entries.push(format!("{} attacks {}, but can't connect.", name.name, target_name.name));
-                    if let Some(pos) = positions.get(wants_melee.target) {
-                        particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::CYAN), rltk::RGB::named(rltk::BLACK), rltk::to_cp437('‼'), 200.0);
+                    let modified_hit_roll = natural_roll + attribute_hit_bonus + skill_hit_bonus + weapon_hit_bonus + status_hit_bonus + hand_hit_bonus;
+
+                    let hits = natural_roll != 1 && (natural_roll == 20 || modified_hit_roll > armor_class);
+                    let blocked = hits && target_shield.is_some_and(|shield| {
+                        rng.roll_dice(1, 100) <= block_chance(shield.block_chance, skill_bonus(Skill::Shield, &*target_skills))
+                    });
+
+                    let first_entry = log.entries.len();
+                    let outcome = if blocked {
+                        log.entries.push(format!("{} blocks {}'s attack with a shield.", &target_name.name, &name.name));
+                        if wants_melee.target == *player_entity {
+                            practiced.push((wants_melee.target, Skill::Shield));
+                        }
+                        add_effect(None, EffectType::Particle {
+                            glyph: rltk::to_cp437('‼'),
+                            fg: rltk::RGB::named(rltk::WHITE),
+                            bg: rltk::RGB::named(rltk::BLACK),
+                            lifespan: 200.0
+                        }, Targets::Single { target: wants_melee.target });
+                        AttackOutcome::Blocked
+                    } else if hits {
+                        // Target hit! Until we support weapons, we're going with 1d4
+                        let base_damage = weapon_info.damage.roll(&mut rng);
+                        let attr_damage_bonus = attacker_attributes.might.bonus;
+                        let skill_damage_bonus = skill_bonus(Skill::Melee, &*attacker_skills);
+
+                        let mut damage = i32::max(0, base_damage + attr_damage_bonus + skill_hit_bonus + skill_damage_bonus);
+                        let damage_type = weapon_info.damage_type;
+                        if natural_roll == 20 {
+                            damage *= weapon_info.critical_multiplier;
+                        }
+                        // Report what gets through the target's resistances and armor
+                        let dealt = mitigated_damage_in(&resistances, &equipped_items, &wearables, wants_melee.target, damage, damage_type);
+                        if natural_roll == 20 {
+                            log.entries.push(format!("{} critically hits {}, for {} {} damage!", &name.name, &target_name.name, dealt, damage_type.name()));
+                        } else {
+                            log.entries.push(format!("{} hits {}, for {} {} damage.", &name.name, &target_name.name, dealt, damage_type.name()));
+                        }
+                        add_effect(Some(entity), EffectType::Damage { amount: damage, damage_type: Some(damage_type) }, Targets::Single { target: wants_melee.target });
+                        if entity == *player_entity {
+                            practiced.push((entity, Skill::Melee));
+                        }
+                        AttackOutcome::Hit { damage: dealt, damage_type, critical: natural_roll == 20 }
+                    } else if natural_roll == 1 {
+                        // Natural 1 miss
+                        log.entries.push(format!("{} considers attacking {}, but misjudges the timing.", name.name, target_name.name));
+                        add_effect(None, EffectType::Particle {
+                            glyph: rltk::to_cp437('‼'),
+                            fg: rltk::RGB::named(rltk::BLUE),
+                            bg: rltk::RGB::named(rltk::BLACK),
+                            lifespan: 200.0
+                        }, Targets::Single { target: wants_melee.target });
+                        AttackOutcome::Fumble
+                    } else {
+                        // Miss
+                        log.entries.push(format!("{} attacks {}, but can't connect.", name.name, target_name.name));
+                        if wants_melee.target == *player_entity {
+                            practiced.push((wants_melee.target, Skill::Defense));
+                        }
+                        add_effect(None, EffectType::Particle {
+                            glyph: rltk::to_cp437('‼'),
+                            fg: rltk::RGB::named(rltk::CYAN),
+                            bg: rltk::RGB::named(rltk::BLACK),
+                            lifespan: 200.0
+                        }, Targets::Single { target: wants_melee.target });
+                        AttackOutcome::Miss
+                    };
+
+                    let breakdown = combat_log.record(AttackRecord {
+                        attacker: name.name.clone(),
+                        target: target_name.name.clone(),
+                        natural_roll,
+                        attribute_bonus: attribute_hit_bonus,
+                        skill_bonus: skill_hit_bonus,
+                        weapon_bonus: weapon_hit_bonus,
+                        status_bonus: status_hit_bonus,
+                        hand_bonus: hand_hit_bonus,
+                        base_armor_class,
+                        armor_quickness_bonus,
+                        armor_skill_bonus,
+                        armor_item_bonus,
+                        armor_shield_bonus,
+                        outcome
+                    });
+                    // The log is read newest first, so the breakdown goes just before its headline
+                    if let Some(breakdown) = breakdown {
+                        log.entries.insert(first_entry, breakdown);
                     }
                 }
             }
         }
 
+        for (learner, skill) in practiced {
+            if let Some(level) = skills.get_mut(learner).and_then(|s| practice_skill(s, skill.clone())) {
+                log.entries.push(format!("Your {:?} skill improves to {}!", skill, level));
+            }
+        }
+
         wants_melee.clear();
     }
 }
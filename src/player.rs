@@ -1,6 +1,9 @@
 use rltk::{VirtualKeyCode, Point, Rltk};
 use specs::prelude::*;
-use crate::{TileType, Door, BlocksTile, BlocksVisibility, Renderable, Faction, Attributes, Vendor, VendorMode, HungerClock, HungerState};
+use crate::{TileType, Door, BlocksTile, BlocksVisibility, Renderable, Faction, FactionStanding, Attributes, Vendor, VendorMode, Temple, HungerClock, HungerState,
+            Equipped, EquipmentSlot, RangedWeapon, Ammunition, WantsToShoot, KnownSpells, SpellTemplate, WantsToCastSpell, Ranged,
+            PendingLevelUp, Skills, Skill, TookAction, ActionType};
+use crate::gamesystem::{attr_bonus, player_hp_at_level, mana_at_level};
 
 use super::{Pools, Position, Player, RunState, State, Map, Viewshed, WantsToMelee, Item, GameLog, WantsToPickupItem, EntityMoved};
 use std::cmp::{min, max};
@@ -21,7 +24,10 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
     let mut blocks_movement = ecs.write_storage::<BlocksTile>();
     let mut renderables = ecs.write_storage::<Renderable>();
     let factions = ecs.read_storage::<Faction>();
+    let standings = ecs.read_storage::<FactionStanding>();
     let vendors = ecs.read_storage::<Vendor>();
+    let temples = ecs.read_storage::<Temple>();
+    let mut actions = ecs.write_storage::<TookAction>();
 
     let mut result = RunState::AwaitingInput;
 
@@ -39,13 +45,17 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
             if let Some(_vendor) = vendors.get(potential_target) {
                 return Some(RunState::ShowVendor { vendor: potential_target, mode: VendorMode::Sell });
             }
+            if temples.get(potential_target).is_some() {
+                return Some(RunState::ShowTemple { temple: potential_target });
+            }
             
             let mut hostile = true;
             if combat_stats.get(potential_target).is_some() {
                 if let Some(faction) = factions.get(potential_target) {
-                    let reaction = crate::raws::faction_reaction(
+                    let reaction = crate::raws::faction_reaction_to(
                         &faction.name,
                         "Player",
+                        standings.get(entity),
                         &crate::raws::RAWS.lock().unwrap()
                     );
                     if reaction != Reaction::Attack { hostile = false };
@@ -80,6 +90,7 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState
                 let glyph = renderables.get_mut(potential_target).unwrap();
                 glyph.glyph = rltk::to_cp437('/');
                 viewshed.dirty = true;
+                actions.insert(entity, TookAction{ action: ActionType::OpenDoor }).expect("Unable to insert action");
                 return Some(RunState::Ticking);
             }
 
@@ -169,34 +180,172 @@ pub fn try_previous_level(ecs: &mut World) -> bool {
     }
 }
 
-pub fn skip_turn(ecs: &mut World) -> RunState {
+pub fn fire_ranged_weapon(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let ranged_weapons = ecs.read_storage::<RangedWeapon>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    for (wielded, weapon) in (&equipped, &ranged_weapons).join() {
+        if wielded.owner == *player_entity && wielded.slot == EquipmentSlot::Ranged {
+            if let Some(ammo_type) = &weapon.ammunition {
+                let has_ammo = (&equipped, &ammunition).join().any(|(quivered, ammo)| {
+                    quivered.owner == *player_entity && quivered.slot == EquipmentSlot::Quiver
+                        && ammo.ammo_type == *ammo_type && ammo.count > 0
+                });
+                if !has_ammo {
+                    gamelog.entries.push("You have no ammunition for that weapon.".to_string());
+                    return RunState::AwaitingInput;
+                }
+            }
+            return RunState::ShowRangedTargeting { range: weapon.range };
+        }
+    }
+
+    gamelog.entries.push("You don't have a ranged weapon equipped.".to_string());
+    RunState::AwaitingInput
+}
+
+pub fn try_fire_at(ecs: &mut World, target: Point) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let pools = ecs.read_storage::<Pools>();
+    let mut wants_shoot = ecs.write_storage::<WantsToShoot>();
+
+    let index = map.xy_index(target.x, target.y);
+    let mut victim: Option<Entity> = None;
+    crate::spatial::for_each_tile_content(index, |entity| {
+        if entity != *player_entity && pools.get(entity).is_some() {
+            victim = Some(entity);
+        }
+    });
+
+    match victim {
+        None => {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push("There is nothing there to shoot at.".to_string());
+            RunState::AwaitingInput
+        }
+        Some(victim) => {
+            wants_shoot.insert(*player_entity, WantsToShoot { target: victim }).expect("Unable to insert intent.");
+            RunState::Ticking
+        }
+    }
+}
+
+pub fn cast_spell(ecs: &mut World, spell: Entity) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let pools = ecs.read_storage::<Pools>();
+    let spell_templates = ecs.read_storage::<SpellTemplate>();
+
+    let mana_cost = spell_templates.get(spell).map_or(0, |template| template.mana_cost);
+    if pools.get(*player_entity).unwrap().mana.current < mana_cost {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog.entries.push("You don't have enough mana to cast that!".to_string());
+        return RunState::AwaitingInput;
+    }
+
+    if let Some(ranged) = ecs.read_storage::<Ranged>().get(spell) {
+        return RunState::ShowTargeting { range: ranged.range, item: spell };
+    }
+
+    let mut intent = ecs.write_storage::<WantsToCastSpell>();
+    intent.insert(*player_entity, WantsToCastSpell { spell, target: None }).expect("Unable to insert intent.");
+    RunState::Ticking
+}
+
+/// Spends one of the player's level-up points on the chosen row of the level-up screen:
+/// the four attributes, followed by the skills. Returns true once every point is spent.
+pub fn spend_level_up_point(ecs: &mut World, choice: i32) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut pending = ecs.write_storage::<PendingLevelUp>();
+    let mut attributes = ecs.write_storage::<Attributes>();
+    let mut skills = ecs.write_storage::<Skills>();
+    let mut pools = ecs.write_storage::<Pools>();
+    let mut log = ecs.fetch_mut::<GameLog>();
+
+    let points = match pending.get_mut(player_entity) {
+        None => return true,
+        Some(points) => points
+    };
+
+    if choice < 4 {
+        let attr = attributes.get_mut(player_entity).unwrap();
+        let pool = pools.get_mut(player_entity).unwrap();
+        if points.attribute_points > 0 {
+            let (name, attribute) = match choice {
+                0 => ("Might", &mut attr.might),
+                1 => ("Fitness", &mut attr.fitness),
+                2 => ("Quickness", &mut attr.quickness),
+                _ => ("Intelligence", &mut attr.intelligence)
+            };
+            attribute.base += 1;
+            attribute.bonus = attr_bonus(attribute.base + attribute.modifiers);
+            points.attribute_points -= 1;
+            log.entries.push(format!("Your {} rises to {}.", name, attribute.base + attribute.modifiers));
+
+            let hp_max = player_hp_at_level(attr.fitness.base + attr.fitness.modifiers, pool.level);
+            let mana_max = mana_at_level(attr.intelligence.base + attr.intelligence.modifiers, pool.level);
+            pool.hit_points.current += hp_max - pool.hit_points.max;
+            pool.hit_points.max = hp_max;
+            pool.mana.current += mana_max - pool.mana.max;
+            pool.mana.max = mana_max;
+        }
+    } else if points.skill_points > 0 {
+        let skill = match choice {
+            4 => Skill::Melee,
+            5 => Skill::Defense,
+            6 => Skill::Magic,
+            _ => Skill::Shield
+        };
+        let skills = skills.get_mut(player_entity).unwrap();
+        let level = skills.skills.entry(skill.clone()).or_insert(0);
+        *level += 1;
+        points.skill_points -= 1;
+        log.entries.push(format!("Your {:?} skill rises to {}.", skill, level));
+    }
+
+    let done = points.attribute_points < 1 && points.skill_points < 1;
+    if done {
+        pending.remove(player_entity);
+    }
+    done
+}
+
+/// How many creatures hostile to the player are in view.
+pub fn visible_hostiles(ecs: &World) -> i32 {
     let player_entity = ecs.fetch::<Entity>();
     let viewshed_components = ecs.read_storage::<Viewshed>();
     let factions = ecs.read_storage::<Faction>();
+    let standings = ecs.read_storage::<FactionStanding>();
 
     let worldmap_resource = ecs.fetch::<Map>();
-    
-    let mut can_heal = true;
+
+    let mut hostiles = 0;
     let viewshed = viewshed_components.get(*player_entity).unwrap();
     for tile in viewshed.visible_tiles.iter() {
         let index = worldmap_resource.xy_index(tile.x, tile.y);
         crate::spatial::for_each_tile_content(index, |entity_id| {
-            let faction = factions.get(entity_id);
-            match faction {
-                None => {}
-                Some(faction) => {
-                    let reaction = crate::raws::faction_reaction(
-                        &faction.name,
-                        "Player",
-                        &crate::raws::RAWS.lock().unwrap()
-                    );
-                    if reaction == Reaction::Attack {
-                        can_heal = false;
-                    }
+            if let Some(faction) = factions.get(entity_id) {
+                let reaction = crate::raws::faction_reaction_to(
+                    &faction.name,
+                    "Player",
+                    standings.get(*player_entity),
+                    &crate::raws::RAWS.lock().unwrap()
+                );
+                if reaction == Reaction::Attack {
+                    hostiles += 1;
                 }
             }
         });
     }
+    hostiles
+}
+
+pub fn skip_turn(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut can_heal = visible_hostiles(ecs) == 0;
 
     let hunger_clocks = ecs.read_storage::<HungerClock>();
     let hc = hunger_clocks.get(*player_entity);
@@ -212,8 +361,12 @@ pub fn skip_turn(ecs: &mut World) -> RunState {
         let mut health_components = ecs.write_storage::<Pools>();
         let pools = health_components.get_mut(*player_entity).unwrap();
         pools.hit_points.current = i32::min(pools.hit_points.current  + 1, pools.hit_points.max);
+        pools.mana.current = i32::min(pools.mana.current + 1, pools.mana.max);
     }
 
+    let mut actions = ecs.write_storage::<TookAction>();
+    actions.insert(*player_entity, TookAction{ action: ActionType::Wait }).expect("Unable to insert action");
+
     RunState::Ticking
 }
 
@@ -236,6 +389,40 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
         if let Some(key) = key {
             return use_consumable_hotkey(gs, key-1);
         }
+
+        // Travel to known stairs
+        match ctx.key {
+            Some(VirtualKeyCode::Period) => return crate::action_queue::travel_to_stairs(&mut gs.ecs, TileType::DownStairs),
+            Some(VirtualKeyCode::Comma) => return crate::action_queue::travel_to_stairs(&mut gs.ecs, TileType::UpStairs),
+            _ => {}
+        }
+    }
+    // Travel to a clicked tile on the map
+    if ctx.left_click {
+        let (screen_x, screen_y) = ctx.mouse_pos();
+        if screen_x > 0 && screen_x < 49 && screen_y > 0 && screen_y < 45 {
+            if let Some((x, y)) = crate::gui::mouse_map_pos(&gs.ecs, ctx) {
+                let destination = gs.ecs.fetch::<Map>().xy_index(x, y);
+                return crate::action_queue::travel_to(&mut gs.ecs, destination);
+            }
+        }
+    }
+    if let Some(key) = ctx.key.filter(|_| ctx.control) {
+        let key : Option<i32> = match key {
+            VirtualKeyCode::Key1 => Some(1),
+            VirtualKeyCode::Key2 => Some(2),
+            VirtualKeyCode::Key3 => Some(3),
+            VirtualKeyCode::Key4 => Some(4),
+            VirtualKeyCode::Key5 => Some(5),
+            VirtualKeyCode::Key6 => Some(6),
+            VirtualKeyCode::Key7 => Some(7),
+            VirtualKeyCode::Key8 => Some(8),
+            VirtualKeyCode::Key9 => Some(9),
+            _ => None
+        };
+        if let Some(key) = key {
+            return use_spell_hotkey(gs, key-1);
+        }
     }
     match ctx.key {
         None => { return RunState::AwaitingInput }
@@ -285,10 +472,23 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
                 }
             }
             VirtualKeyCode::R => return RunState::ShowRemoveItem,
+
+            // Fire a ranged weapon
+            VirtualKeyCode::F => return fire_ranged_weapon(&mut gs.ecs),
+
+            // Cast a spell
+            VirtualKeyCode::C => return RunState::ShowCastSpell,
             
             // Skip Turn
             VirtualKeyCode::Numpad5 => return skip_turn(&mut gs.ecs),
             VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
+
+            // Rest for a while
+            VirtualKeyCode::Z => return RunState::ShowRestMenu,
+
+            // Auto-explore, and what it picks up
+            VirtualKeyCode::X => return crate::action_queue::explore(&mut gs.ecs),
+            VirtualKeyCode::O => return RunState::ShowAutoPickupMenu,
             
             // Save and Quit
             VirtualKeyCode::Escape => return RunState::SaveGame,
@@ -303,16 +503,18 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
 }
 
 fn use_consumable_hotkey(gs: &mut State, key: i32) -> RunState {
-    use super::{Consumable, InBackpack, WantsToUseItem};
+    use super::{Consumable, InBackpack};
 
-    let consumables = gs.ecs.read_storage::<Consumable>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let entities = gs.ecs.entities();
     let mut carried_consumables = Vec::new();
-    for (entity, carried_by, _consumable) in (&entities, &backpack, &consumables).join() {
-        if carried_by.owner == *player_entity {
-            carried_consumables.push(entity);
+    {
+        let consumables = gs.ecs.read_storage::<Consumable>();
+        let backpack = gs.ecs.read_storage::<InBackpack>();
+        let player_entity = gs.ecs.fetch::<Entity>();
+        let entities = gs.ecs.entities();
+        for (entity, carried_by, _consumable) in (&entities, &backpack, &consumables).join() {
+            if carried_by.owner == *player_entity {
+                carried_consumables.push(entity);
+            }
         }
     }
 
@@ -322,12 +524,23 @@ fn use_consumable_hotkey(gs: &mut State, key: i32) -> RunState {
             return RunState::ShowTargeting {range: ranged.range, item: carried_consumables[key as usize]};
         }
 
-        let mut intent = gs.ecs.write_storage::<WantsToUseItem>();
-        intent.insert(
-            *player_entity,
-            WantsToUseItem { item: carried_consumables[key as usize], target: None }
-        ).expect("Unable to insert intent)");
-        return RunState::Ticking;
+        return crate::action_queue::use_item(&mut gs.ecs, carried_consumables[key as usize], None);
     }
     RunState::Ticking
 }
+
+fn use_spell_hotkey(gs: &mut State, key: i32) -> RunState {
+    let spell_name = {
+        let player_entity = gs.ecs.fetch::<Entity>();
+        let known_spells = gs.ecs.read_storage::<KnownSpells>();
+        match known_spells.get(*player_entity).and_then(|known| known.spells.get(key as usize)) {
+            None => return RunState::AwaitingInput,
+            Some(spell) => spell.display_name.clone()
+        }
+    };
+
+    match crate::spell_system::find_spell_entity(&gs.ecs, &spell_name) {
+        None => RunState::AwaitingInput,
+        Some(spell) => cast_spell(&mut gs.ecs, spell)
+    }
+}
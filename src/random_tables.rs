@@ -33,17 +33,42 @@ impl RandomTable {
     pub fn roll(&self, rng: &mut RandomNumberGenerator) -> String {
         if self.total_weight == 0 { return "None".to_string() }
         let mut roll = rng.roll_dice(1, self.total_weight) - 1;
-        let mut index : usize = 0;
 
-        while roll > 0 {
-            if roll < self.entries[index].weight {
-                return self.entries[index].name.clone();
+        // The roll is 0-based, so the first entry's share starts at zero
+        for entry in self.entries.iter() {
+            if roll < entry.weight {
+                return entry.name.clone();
             }
-
-            roll -= self.entries[index].weight;
-            index += 1;
+            roll -= entry.weight;
         }
 
         "None".to_string()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_entry_can_be_rolled() {
+        let table = RandomTable::new().add("First", 1).add("Second", 1).add("Third", 2);
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let rolled: std::collections::HashSet<String> = (0..200).map(|_| table.roll(&mut rng)).collect();
+        assert_eq!(rolled.len(), 3);
+        assert!(!rolled.contains("None"));
+    }
+
+    #[test]
+    fn a_single_entry_is_always_rolled() {
+        let table = RandomTable::new().add("Only", 1);
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert!((0..20).all(|_| table.roll(&mut rng) == "Only"));
+    }
+
+    #[test]
+    fn an_empty_table_rolls_none() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(RandomTable::new().roll(&mut rng), "None");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,287 @@
+use specs::prelude::*;
+use rltk::{BaseMap, Point};
+use super::{Pools, WantsToShoot, Name, GameLog, Map};
+use crate::gamelog::{CombatLog, AttackRecord, AttackOutcome};
+use crate::effects::{add_effect, mitigated_damage_in, EffectType, Targets};
+use crate::{Attributes, Skills, Position, Skill, NaturalAttackDefense, RangedWeapon, Ammunition, Equipped, EquipmentSlot, Wearable, HungerClock, HungerState, Shield,
+    Viewshed, Resistances};
+use crate::gamesystem::{skill_bonus, practice_skill, block_chance};
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Attributes>,
+        WriteStorage<'a, Skills>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Pools>,
+        WriteExpect<'a, rltk::RandomNumberGenerator>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, RangedWeapon>,
+        WriteStorage<'a, Ammunition>,
+        ReadStorage<'a, Wearable>,
+        ReadStorage<'a, NaturalAttackDefense>,
+        ReadStorage<'a, HungerClock>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, Shield>,
+        WriteExpect<'a, CombatLog>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, Resistances>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut log,
+            mut wants_shoot,
+            names,
+            attributes,
+            mut skills,
+            positions,
+            pools,
+            mut rng,
+            equipped_items,
+            ranged_weapons,
+            mut ammunition,
+            wearables,
+            natural,
+            hunger_clocks,
+            player_entity,
+            map,
+            shields,
+            mut combat_log,
+            viewsheds,
+            resistances,
+        ) = data;
+
+        let mut spent_ammo: Vec<Entity> = Vec::new();
+        let mut practiced: Vec<(Entity, Skill)> = Vec::new();
+        for (entity, wants_shoot, name, attacker_attributes, attacker_pools, attacker_pos)
+            in (&entities, &wants_shoot, &names, &attributes, &pools, &positions).join()
+        {
+            let target_pools = pools.get(wants_shoot.target);
+            let target_pos = positions.get(wants_shoot.target);
+            let target_attributes = attributes.get(wants_shoot.target);
+            let target_skills = skills.get(wants_shoot.target);
+            if target_pools.is_none() || target_pos.is_none() || target_attributes.is_none() || target_skills.is_none() { continue; }
+            let target_pools = target_pools.unwrap();
+            let target_pos = target_pos.unwrap();
+            let target_attributes = target_attributes.unwrap();
+            let target_skills = target_skills.unwrap();
+
+            if attacker_pools.hit_points.current < 1 || target_pools.hit_points.current < 1 { continue; }
+            let target_name = names.get(wants_shoot.target).unwrap();
+
+            // Find the weapon being fired, and the ammunition it needs
+            let mut weapon_info: Option<RangedWeapon> = None;
+            for (wielded, ranged) in (&equipped_items, &ranged_weapons).join() {
+                if wielded.owner == entity && wielded.slot == EquipmentSlot::Ranged {
+                    weapon_info = Some(ranged.clone());
+                }
+            }
+            let weapon_info = match weapon_info {
+                None => continue,
+                Some(weapon) => weapon
+            };
+
+            // The target may have moved since it was picked: it must still be seen and in range
+            let start = Point::new(attacker_pos.x, attacker_pos.y);
+            let end = Point::new(target_pos.x, target_pos.y);
+            if viewsheds.get(entity).is_some_and(|viewshed| !viewshed.visible_tiles.contains(&end)) {
+                log.entries.push(format!("{} can't see {}.", &name.name, &target_name.name));
+                continue;
+            }
+            if rltk::DistanceAlg::Pythagoras.distance2d(start, end) > weapon_info.range as f32 {
+                log.entries.push(format!("{} is out of {}'s range.", &target_name.name, &name.name));
+                continue;
+            }
+
+            // Check that nothing opaque stands between the shooter and the target
+            let path = rltk::line2d(rltk::LineAlg::Bresenham, start, end);
+            let mut line_of_fire = true;
+            for step in path.iter().filter(|p| **p != start && **p != end) {
+                let index = map.xy_index(step.x, step.y);
+                if map.is_opaque(index) {
+                    line_of_fire = false;
+                }
+            }
+            if !line_of_fire {
+                log.entries.push(format!("{} has no clear shot at {}.", &name.name, &target_name.name));
+                continue;
+            }
+
+            let mut ammo_damage_bonus = 0;
+            if let Some(ammo_type) = &weapon_info.ammunition {
+                let mut ammo_entity: Option<Entity> = None;
+                for (ammo_item, wielded, ammo) in (&entities, &equipped_items, &ammunition).join() {
+                    if wielded.owner == entity && wielded.slot == EquipmentSlot::Quiver && ammo.ammo_type == *ammo_type && ammo.count > 0 {
+                        ammo_entity = Some(ammo_item);
+                    }
+                }
+                match ammo_entity {
+                    None => {
+                        if entity == *player_entity {
+                            log.entries.push("You have no ammunition for that weapon.".to_string());
+                        }
+                        continue;
+                    }
+                    Some(ammo_item) => {
+                        let ammo = ammunition.get_mut(ammo_item).unwrap();
+                        ammo_damage_bonus = ammo.damage_bonus;
+                        ammo.count -= 1;
+                        if ammo.count < 1 {
+                            spent_ammo.push(ammo_item);
+                            if entity == *player_entity {
+                                log.entries.push("You have used up your ammunition.".to_string());
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Draw the projectile's flight, fading from the shooter towards the target
+            for (i, step) in path.iter().enumerate().filter(|(_, p)| **p != start) {
+                add_effect(None, EffectType::Particle {
+                    glyph: rltk::to_cp437('*'),
+                    fg: rltk::RGB::named(rltk::WHITE),
+                    bg: rltk::RGB::named(rltk::BLACK),
+                    lifespan: 50.0 + (i as f32 * 25.0)
+                }, Targets::Tile { tile_index: map.xy_index(step.x, step.y) as i32 });
+            }
+
+            let natural_roll = rng.roll_dice(1, 20);
+            // Aiming is a matter of Quickness, whatever the weapon
+            let attribute_hit_bonus = attacker_attributes.quickness.bonus;
+            // Weapon skill covers shooting as well as melee
+            let skill_hit_bonus = skills.get(entity).map_or(0, |attacker_skills| skill_bonus(Skill::Melee, attacker_skills));
+            let weapon_hit_bonus = weapon_info.hit_bonus;
+            let mut status_hit_bonus = 0;
+            if let Some(hc) = hunger_clocks.get(entity) {
+                if hc.state == HungerState::WellFed {
+                    status_hit_bonus += 1;
+                }
+            }
+            let modified_hit_roll = natural_roll + attribute_hit_bonus + skill_hit_bonus + weapon_hit_bonus + status_hit_bonus;
+
+            let mut armor_item_bonus_f = 0.0;
+            for (wielded,armor) in (&equipped_items, &wearables).join() {
+                if wielded.owner == wants_shoot.target {
+                    armor_item_bonus_f += armor.armor_class;
+                }
+            }
+            let target_shield = (&equipped_items, &shields).join()
+                .find(|(wielded, _)| wielded.owner == wants_shoot.target && wielded.slot == EquipmentSlot::Shield)
+                .map(|(_, shield)| shield);
+            let armor_shield_bonus = target_shield.map_or(0, |shield| shield.defense_bonus);
+
+            let base_armor_class = match natural.get(wants_shoot.target) {
+                None => 10,
+                Some(nat) => nat.armor_class.unwrap_or(10)
+            };
+            let armor_quickness_bonus = target_attributes.quickness.bonus;
+            let armor_skill_bonus = skill_bonus(Skill::Defense, target_skills);
+            let armor_item_bonus = armor_item_bonus_f as i32;
+            let armor_class = base_armor_class + armor_quickness_bonus + armor_skill_bonus + armor_item_bonus + armor_shield_bonus;
+
+            let hits = natural_roll != 1 && (natural_roll == 20 || modified_hit_roll > armor_class);
+            let blocked = hits && target_shield.is_some_and(|shield| {
+                rng.roll_dice(1, 100) <= block_chance(shield.block_chance, skill_bonus(Skill::Shield, target_skills))
+            });
+
+            let first_entry = log.entries.len();
+            let outcome = if blocked {
+                log.entries.push(format!("{} blocks {}'s shot with a shield.", &target_name.name, &name.name));
+                if wants_shoot.target == *player_entity {
+                    practiced.push((wants_shoot.target, Skill::Shield));
+                }
+                add_effect(None, EffectType::Particle {
+                    glyph: rltk::to_cp437('‼'),
+                    fg: rltk::RGB::named(rltk::WHITE),
+                    bg: rltk::RGB::named(rltk::BLACK),
+                    lifespan: 200.0
+                }, Targets::Single { target: wants_shoot.target });
+                AttackOutcome::Blocked
+            } else if hits {
+                let base_damage = weapon_info.damage.roll(&mut rng);
+                let mut damage = i32::max(0, base_damage + ammo_damage_bonus);
+                let damage_type = weapon_info.damage_type;
+                if natural_roll == 20 {
+                    damage *= weapon_info.critical_multiplier;
+                }
+                // Report what gets through the target's resistances and armor
+                let dealt = mitigated_damage_in(&resistances, &equipped_items, &wearables, wants_shoot.target, damage, damage_type);
+                if natural_roll == 20 {
+                    log.entries.push(format!("{} lands a critical shot on {}, for {} {} damage!", &name.name, &target_name.name, dealt, damage_type.name()));
+                } else {
+                    log.entries.push(format!("{} shoots {}, for {} {} damage.", &name.name, &target_name.name, dealt, damage_type.name()));
+                }
+                add_effect(Some(entity), EffectType::Damage { amount: damage, damage_type: Some(damage_type) }, Targets::Single { target: wants_shoot.target });
+                if entity == *player_entity {
+                    practiced.push((entity, Skill::Melee));
+                }
+                AttackOutcome::Hit { damage: dealt, damage_type, critical: natural_roll == 20 }
+            } else if natural_roll == 1 {
+                // Natural 1 miss
+                log.entries.push(format!("{} fumbles a shot at {}.", name.name, target_name.name));
+                add_effect(None, EffectType::Particle {
+                    glyph: rltk::to_cp437('‼'),
+                    fg: rltk::RGB::named(rltk::BLUE),
+                    bg: rltk::RGB::named(rltk::BLACK),
+                    lifespan: 200.0
+                }, Targets::Single { target: wants_shoot.target });
+                AttackOutcome::Fumble
+            } else {
+                // Miss
+                log.entries.push(format!("{} shoots at {}, but misses.", name.name, target_name.name));
+                if wants_shoot.target == *player_entity {
+                    practiced.push((wants_shoot.target, Skill::Defense));
+                }
+                add_effect(None, EffectType::Particle {
+                    glyph: rltk::to_cp437('‼'),
+                    fg: rltk::RGB::named(rltk::CYAN),
+                    bg: rltk::RGB::named(rltk::BLACK),
+                    lifespan: 200.0
+                }, Targets::Single { target: wants_shoot.target });
+                AttackOutcome::Miss
+            };
+
+            let breakdown = combat_log.record(AttackRecord {
+                attacker: name.name.clone(),
+                target: target_name.name.clone(),
+                natural_roll,
+                attribute_bonus: attribute_hit_bonus,
+                skill_bonus: skill_hit_bonus,
+                weapon_bonus: weapon_hit_bonus,
+                status_bonus: status_hit_bonus,
+                hand_bonus: 0,
+                base_armor_class,
+                armor_quickness_bonus,
+                armor_skill_bonus,
+                armor_item_bonus,
+                armor_shield_bonus,
+                outcome
+            });
+            if let Some(breakdown) = breakdown {
+                log.entries.insert(first_entry, breakdown);
+            }
+        }
+
+        for (learner, skill) in practiced {
+            if let Some(level) = skills.get_mut(learner).and_then(|s| practice_skill(s, skill.clone())) {
+                log.entries.push(format!("Your {:?} skill improves to {}!", skill, level));
+            }
+        }
+
+        for ammo in spent_ammo.iter() {
+            entities.delete(*ammo).expect("Unable to delete ammunition");
+        }
+
+        wants_shoot.clear();
+    }
+}
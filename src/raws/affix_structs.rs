@@ -0,0 +1,16 @@
+use serde::{Deserialize};
+
+#[derive(Deserialize, Debug)]
+pub struct Affix {
+    pub name : String,
+    pub position : String,
+    pub applies_to : Vec<String>,
+    pub weight : i32,
+    pub min_depth : i32,
+    pub hit_bonus : Option<i32>,
+    pub damage_bonus : Option<i32>,
+    pub damage_die_type : Option<i32>,
+    pub armor_class : Option<f32>,
+    pub weight_multiplier : Option<f32>,
+    pub value_multiplier : Option<f32>
+}
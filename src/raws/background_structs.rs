@@ -0,0 +1,24 @@
+use serde::{Deserialize};
+use std::collections::HashMap;
+use crate::dice::DiceExpr;
+
+#[derive(Deserialize, Debug)]
+pub struct Background {
+    pub name : String,
+    pub description : String,
+    pub attributes : Option<BackgroundAttributes>,
+    pub skills : Option<HashMap<String, i32>>,
+    pub equipped : Option<Vec<String>>,
+    pub carried : Option<Vec<String>>,
+    pub spells : Option<Vec<String>>,
+    pub gold : Option<DiceExpr>,
+    pub faction_standing : Option<HashMap<String, String>>
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BackgroundAttributes {
+    pub might : Option<i32>,
+    pub fitness : Option<i32>,
+    pub quickness : Option<i32>,
+    pub intelligence : Option<i32>
+}
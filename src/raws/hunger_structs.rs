@@ -0,0 +1,9 @@
+use serde::{Deserialize};
+
+#[derive(Deserialize, Debug)]
+pub struct HungerClockInfo {
+    pub well_fed: i32,
+    pub normal: i32,
+    pub hungry: i32,
+    pub starving: i32,
+}
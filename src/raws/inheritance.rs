@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use super::validation::{RawsError, entry_path};
+
+/// Sections whose entries may name a parent template with `"extends"`.
+const INHERITING_SECTIONS : [&str; 3] = ["items", "mobs", "props"];
+
+/// Resolves `"extends": "Parent Name"` on items, mobs and props by deep-merging each entry
+/// over its (already resolved) parent. Objects merge key by key, anything else in the child
+/// replaces the parent's value, and a `null` removes the inherited key. Entries marked
+/// `"abstract": true` exist only to be inherited from, and are dropped once resolved.
+pub fn resolve_inheritance(raws: &mut Value) -> Result<(), Vec<RawsError>> {
+    let mut errors = Vec::new();
+    for section in INHERITING_SECTIONS.iter() {
+        if let Some(Value::Array(entries)) = raws.get_mut(*section) {
+            *entries = resolve_section(section, entries, &mut errors);
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn resolve_section(section: &str, entries: &[Value], errors: &mut Vec<RawsError>) -> Vec<Value> {
+    let mut by_name : HashMap<&str, &Value> = HashMap::new();
+    for entry in entries.iter() {
+        if let Some(name) = entry.get("name").and_then(Value::as_str) {
+            by_name.insert(name, entry);
+        }
+    }
+
+    let mut resolved = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.get("abstract").and_then(Value::as_bool) == Some(true) { continue; }
+        match resolve_entry(entry, &by_name, &mut Vec::new()) {
+            Ok(value) => resolved.push(value),
+            Err(message) => {
+                let path = match entry.get("name").and_then(Value::as_str) {
+                    Some(name) => entry_path(section, name),
+                    None => format!("{}[{}]", section, i)
+                };
+                errors.push(RawsError::new(&format!("{}.extends", path), message));
+            }
+        }
+    }
+    resolved
+}
+
+fn resolve_entry(entry: &Value, by_name: &HashMap<&str, &Value>, chain: &mut Vec<String>) -> Result<Value, String> {
+    let name = entry.get("name").and_then(Value::as_str).unwrap_or("<unnamed>").to_string();
+    if chain.contains(&name) {
+        chain.push(name);
+        return Err(format!("inheritance cycle {}", chain.join(" -> ")));
+    }
+
+    let mut resolved = match entry.get("extends") {
+        None => entry.clone(),
+        Some(Value::String(parent_name)) => {
+            let parent = by_name.get(parent_name.as_str())
+                .ok_or_else(|| format!("\"{}\" extends unknown template \"{}\"", name, parent_name))?;
+            chain.push(name);
+            let mut merged = resolve_entry(parent, by_name, chain)?;
+            chain.pop();
+            merge(&mut merged, entry);
+            merged
+        }
+        Some(_) => return Err(format!("\"{}\" has an \"extends\" that is not a template name", name))
+    };
+
+    if let Value::Object(fields) = &mut resolved {
+        fields.remove("extends");
+        fields.remove("abstract");
+    }
+    Ok(resolved)
+}
+
+fn merge(base: &mut Value, overlay: &Value) {
+    if let (Value::Object(base_fields), Value::Object(overlay_fields)) = (base, overlay) {
+        for (key, value) in overlay_fields.iter() {
+            match (base_fields.get_mut(key), value) {
+                (_, Value::Null) => { base_fields.remove(key); }
+                (Some(existing @ Value::Object(_)), Value::Object(_)) => merge(existing, value),
+                _ => { base_fields.insert(key.clone(), value.clone()); }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn errors(raws: &mut Value) -> Vec<String> {
+        resolve_inheritance(raws).unwrap_err().iter().map(|error| error.to_string()).collect()
+    }
+
+    #[test]
+    fn children_deep_merge_over_their_parents() {
+        let mut raws = json!({ "mobs": [
+            { "name": "Humanoid", "abstract": true, "movement": "static", "attributes": { "might": 11, "fitness": 11 },
+              "quips": ["Hello"], "loot_table": "Scraps" },
+            { "name": "Bandit", "extends": "Humanoid", "attributes": { "might": 13 }, "quips": ["Stand and deliver!"],
+              "loot_table": null },
+            { "name": "Bandit Chief", "extends": "Bandit", "attributes": { "fitness": 14 } }
+        ]});
+        resolve_inheritance(&mut raws).unwrap();
+        assert_eq!(raws, json!({ "mobs": [
+            { "name": "Bandit", "movement": "static", "attributes": { "might": 13, "fitness": 11 },
+              "quips": ["Stand and deliver!"] },
+            { "name": "Bandit Chief", "movement": "static", "attributes": { "might": 13, "fitness": 14 },
+              "quips": ["Stand and deliver!"] }
+        ]}));
+    }
+
+    #[test]
+    fn parents_may_come_later_and_other_sections_are_untouched() {
+        let mut raws = json!({
+            "items": [
+                { "name": "Longsword", "extends": "Sword", "weight_lbs": 3.0 },
+                { "name": "Sword", "weapon": { "range": "melee", "base_damage": "1d6" }, "weight_lbs": 2.0 }
+            ],
+            "spells": [ { "name": "Zap", "extends": "Nothing" } ]
+        });
+        resolve_inheritance(&mut raws).unwrap();
+        assert_eq!(raws["items"][0], json!({ "name": "Longsword", "weapon": { "range": "melee", "base_damage": "1d6" }, "weight_lbs": 3.0 }));
+        assert_eq!(raws["items"][1]["name"], "Sword");
+        assert_eq!(raws["spells"][0]["extends"], "Nothing");
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let mut raws = json!({ "props": [
+            { "name": "A", "extends": "B" },
+            { "name": "B", "extends": "A" },
+            { "name": "C", "extends": "C" }
+        ]});
+        assert_eq!(errors(&mut raws), vec![
+            "props[\"A\"].extends: inheritance cycle A -> B -> A",
+            "props[\"B\"].extends: inheritance cycle B -> A -> B",
+            "props[\"C\"].extends: inheritance cycle C -> C"
+        ]);
+    }
+
+    #[test]
+    fn bad_parents_are_reported() {
+        let mut raws = json!({ "items": [
+            { "name": "Dagger", "extends": "Knife" },
+            { "name": "Club", "extends": 3 },
+            { "extends": "Mace" }
+        ]});
+        assert_eq!(errors(&mut raws), vec![
+            "items[\"Dagger\"].extends: \"Dagger\" extends unknown template \"Knife\"",
+            "items[\"Club\"].extends: \"Club\" has an \"extends\" that is not a template name",
+            "items[2].extends: \"<unnamed>\" extends unknown template \"Mace\""
+        ]);
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize};
 use std::collections::HashMap;
 use super::Renderable;
+use crate::dice::DiceExpr;
 
 #[derive(Deserialize, Debug)]
 pub struct Item {
@@ -9,10 +10,17 @@ pub struct Item {
     pub consumable : Option<Consumable>,
     pub weapon : Option<Weapon>,
     pub wearable : Option<Wearable>,
+    pub ammunition : Option<Ammunition>,
     pub initiative_penalty: Option<f32>,
     pub weight_lbs: Option<f32>,
     pub base_value: Option<f32>,
     pub vendor_category: Option<String>,
+    pub attributes: Option<ItemAttributeBonus>,
+    pub skills: Option<HashMap<String, i32>>,
+    pub magic: Option<MagicItem>,
+    pub cursed: Option<bool>,
+    pub shield: Option<Shield>,
+    pub use_turns: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -24,17 +32,45 @@ pub struct Consumable {
 pub struct Weapon {
     pub range: String,
     pub attribute: String,
-    pub base_damage: String,
-    pub hit_bonus: i32
+    pub base_damage: DiceExpr,
+    pub hit_bonus: i32,
+    pub ammunition: Option<String>,
+    pub two_handed: Option<bool>,
+    pub damage_type: Option<String>,
+    pub critical_multiplier: Option<i32>
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Ammunition {
+    pub ammo_type: String,
+    pub count: i32,
+    pub damage_bonus: Option<i32>
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Wearable {
     pub armor_class: f32,
-    pub slot : String
+    pub slot : String,
+    pub damage_reduction: Option<i32>,
+    pub resistances: Option<Vec<String>>,
+    pub vulnerabilities: Option<Vec<String>>
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Shield {
-    pub defense_bonus: i32
+    pub defense_bonus: i32,
+    pub block_chance: Option<i32>
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ItemAttributeBonus {
+    pub might: Option<i32>,
+    pub fitness: Option<i32>,
+    pub quickness: Option<i32>,
+    pub intelligence: Option<i32>
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MagicItem {
+    pub naming: String
 }
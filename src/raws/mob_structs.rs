@@ -1,5 +1,6 @@
 use serde::{Deserialize};
 use super::{Renderable};
+use crate::dice::DiceExpr;
 use std::collections::HashMap;
 
 #[derive(Deserialize, Debug)]
@@ -20,8 +21,12 @@ pub struct Mob {
     pub loot_table: Option<String>,
     pub light: Option<MobLight>,
     pub faction: Option<String>,
-    pub gold: Option<String>,
+    pub gold: Option<DiceExpr>,
     pub vendor: Option<Vec<String>>,
+    pub temple: Option<MobTemple>,
+    pub spells: Option<Vec<String>>,
+    pub resistances: Option<Vec<String>>,
+    pub vulnerabilities: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -42,7 +47,8 @@ pub struct MobNatural {
 pub struct NaturalAttack {
     pub name : String,
     pub hit_bonus : i32,
-    pub damage : String
+    pub damage : DiceExpr,
+    pub damage_type : Option<String>
 }
 
 #[derive(Deserialize, Debug)]
@@ -50,3 +56,8 @@ pub struct MobLight {
     pub range: i32,
     pub color: String,
 }
+
+#[derive(Deserialize, Debug)]
+pub struct MobTemple {
+    pub remove_curse_cost: f32,
+}
@@ -9,6 +9,10 @@ use spawn_table_structs::SpawnTableEntry;
 use crate::raws::faction_structs::FactionInfo;
 use crate::raws::prop_structs::Prop;
 use crate::raws::loot_structs::LootTable;
+use crate::raws::hunger_structs::HungerClockInfo;
+use crate::raws::spell_structs::Spell;
+use crate::raws::background_structs::Background;
+use crate::raws::affix_structs::Affix;
 
 mod item_structs;
 mod mob_structs;
@@ -16,6 +20,14 @@ mod spawn_table_structs;
 mod rawmaster;
 mod prop_structs;
 mod loot_structs;
+mod hunger_structs;
+mod spell_structs;
+mod background_structs;
+mod affix_structs;
+mod inheritance;
+mod validation;
+mod overlay;
+pub use validation::RawsError;
 pub mod faction_structs;
 
 #[derive(Deserialize, Debug)]
@@ -26,6 +38,10 @@ pub struct Raws {
     pub spawn_table : Vec<SpawnTableEntry>,
     pub loot_table: Vec<LootTable>,
     pub faction_table: Vec<FactionInfo>,
+    pub spells: Vec<Spell>,
+    pub backgrounds: Vec<Background>,
+    pub affixes: Vec<Affix>,
+    pub hunger_clock: Option<HungerClockInfo>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -43,6 +59,25 @@ lazy_static! {
 rltk::embedded_resource!(RAW_FILE, "../../raws/spawns.json");
 
 pub fn load_raws() {
+    match read_raws() {
+        Ok(raws) => RAWS.lock().unwrap().load(raws),
+        Err(errors) => {
+            for error in errors.iter() {
+                rltk::console::log(format!("Raws error - {}", error));
+            }
+            panic!("Unable to load raws: {} problem(s) found", errors.len());
+        }
+    }
+}
+
+/// Problems with the embedded raws, for the `--check-raws` mode.
+pub fn check_raws() -> Vec<RawsError> {
+    read_raws().err().unwrap_or_default()
+}
+
+/// Reads the embedded raws and any overlays in `overlay::RAWS_DIRECTORY`, then resolves
+/// and validates them without installing them.
+pub fn read_raws() -> Result<Raws, Vec<RawsError>> {
     rltk::link_resource!(RAW_FILE, "../../raws/spawns.json");
 
     // Retrieve the raw data as an array of u8 (8-bit unsigned chars)
@@ -50,9 +85,73 @@ pub fn load_raws() {
         .lock()
         .get_resource("../../raws/spawns.json".to_string())
         .unwrap();
-    let raw_string = std::str::from_utf8(&raw_data).expect("Unable to convert to a valid UTF-8 string.");
-    let decoder : Raws = serde_json::from_str(&raw_string).expect("Unable to parse JSON");
+    let raw_string = std::str::from_utf8(raw_data)
+        .map_err(|_| vec![RawsError::new("$", "raws are not valid UTF-8")])?;
+    let root = raws_root();
+    if root.is_none() {
+        rltk::console::log("No raws directory found in the working directory or beside the executable; loading without overlays.");
+    }
+    parse_raws(raw_string, root.map(|root| root.join(overlay::RAWS_DIRECTORY)).as_deref())
+}
+
+/// Path of the raws on disk, re-read by `reload_raws` while the game is running.
+const RAW_FILE_PATH: &str = "raws/spawns.json";
 
-    RAWS.lock().unwrap().load(decoder);
+/// Where the raws directory lives: the working directory if it has one, otherwise beside the
+/// executable. `None` if neither has a `raws` directory.
+fn raws_root() -> Option<std::path::PathBuf> {
+    let working_directory = std::env::current_dir().ok();
+    let exe_directory = std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(|directory| directory.to_path_buf()));
+    working_directory.into_iter().chain(exe_directory)
+        .find(|directory| directory.join("raws").is_dir())
 }
 
+/// Re-reads the raws and overlays from disk into `RAWS` and respawns the spells, optionally
+/// refreshing existing entities from their templates. Problems are reported in the game log
+/// and leave the current raws in place.
+pub fn reload_raws(ecs: &mut specs::World, refresh_entities: bool) {
+    let result = raws_root()
+        .ok_or_else(|| vec![RawsError::new("$", "no raws directory found in the working directory or beside the executable")])
+        .and_then(|root| {
+            let raw_file = root.join(RAW_FILE_PATH);
+            std::fs::read_to_string(&raw_file)
+                .map_err(|error| vec![RawsError::new("$", format!("unable to read {}: {}", raw_file.display(), error))])
+                .and_then(|raw_string| parse_raws(&raw_string, Some(&root.join(overlay::RAWS_DIRECTORY))))
+        });
+
+    let mut messages = Vec::new();
+    match result {
+        Ok(raws) => {
+            RAWS.lock().unwrap().load(raws);
+            respawn_all_spells(ecs);
+            messages.push("Raws reloaded.".to_string());
+            if refresh_entities {
+                let count = refresh_entities_from_raws(&RAWS.lock().unwrap(), ecs);
+                messages.push(format!("Refreshed {} entities from their templates.", count));
+            }
+        }
+        Err(errors) => {
+            messages.push(format!("Raws reload failed with {} problem(s); keeping the old raws.", errors.len()));
+            for error in errors.iter() {
+                rltk::console::log(format!("Raws error - {}", error));
+                messages.push(error.to_string());
+            }
+        }
+    }
+    ecs.fetch_mut::<crate::gamelog::GameLog>().entries.extend(messages);
+}
+
+fn parse_raws(raw_string: &str, overlay_directory: Option<&std::path::Path>) -> Result<Raws, Vec<RawsError>> {
+    let mut raw_json : serde_json::Value = serde_json::from_str(raw_string)
+        .map_err(|error| vec![RawsError::new("$", format!("invalid JSON: {}", error))])?;
+    if let Some(overlay_directory) = overlay_directory {
+        overlay::apply_overlays(&mut raw_json, overlay_directory)?;
+    }
+    inheritance::resolve_inheritance(&mut raw_json)?;
+    let raws : Raws = serde_path_to_error::deserialize(&raw_json)
+        .map_err(|error| vec![RawsError::new(&validation::describe_path(&raw_json, error.path()), error.inner())])?;
+
+    let errors = validation::validate_raws(&raws);
+    if errors.is_empty() { Ok(raws) } else { Err(errors) }
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use super::validation::{RawsError, entry_path};
+
+/// Optional directory of extra raws files, read after the embedded `spawns.json`.
+pub const RAWS_DIRECTORY : &str = "raws/mods";
+
+/// Top-level lists whose entries are matched up by `"name"`.
+const NAMED_SECTIONS : [&str; 9] = ["items", "mobs", "props", "spawn_table", "loot_table", "faction_table", "spells", "backgrounds",
+    "affixes"];
+
+/// Sections that are a single object, replaced wholesale by a later file.
+const SINGLE_SECTIONS : [&str; 1] = ["hunger_clock"];
+
+/// Merges every `.json` file under `directory` over `base`, in order of their paths, so a
+/// pack can control where it lands by naming (e.g. `10-goblins/mobs.json`). Each file may hold
+/// any subset of the top-level sections. An entry replaces any earlier entry of the same name,
+/// and an entry of just `{ "name": ..., "remove": true }` deletes it. A missing directory is fine.
+pub fn apply_overlays(base: &mut Value, directory: &Path) -> Result<(), Vec<RawsError>> {
+    let mut files = Vec::new();
+    collect_files(directory, &mut files);
+    files.sort();
+
+    let mut errors = Vec::new();
+    for file in files.iter() {
+        let file_name = file.display().to_string();
+        let overlay = fs::read_to_string(file)
+            .map_err(|error| error.to_string())
+            .and_then(|text| serde_json::from_str::<Value>(&text).map_err(|error| format!("invalid JSON: {}", error)));
+        match overlay {
+            Ok(overlay) => apply_overlay(base, overlay, &file_name, &mut errors),
+            Err(message) => errors.push(RawsError::new(&file_name, message))
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(directory) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_files(&path, files);
+            } else if path.extension().is_some_and(|extension| extension == "json") {
+                files.push(path);
+            }
+        }
+    }
+}
+
+fn apply_overlay(base: &mut Value, overlay: Value, file_name: &str, errors: &mut Vec<RawsError>) {
+    let sections = match overlay {
+        Value::Object(sections) => sections,
+        _ => {
+            errors.push(RawsError::new(file_name, "expected an object of raws sections"));
+            return;
+        }
+    };
+
+    for (section, value) in sections {
+        let section_path = format!("{}: {}", file_name, section);
+        if SINGLE_SECTIONS.contains(&section.as_str()) {
+            base[section.as_str()] = value;
+        } else if NAMED_SECTIONS.contains(&section.as_str()) {
+            let entries = match value {
+                Value::Array(entries) => entries,
+                _ => {
+                    errors.push(RawsError::new(&section_path, "expected a list"));
+                    continue;
+                }
+            };
+            if !base[section.as_str()].is_array() {
+                base[section.as_str()] = Value::Array(Vec::new());
+            }
+            let existing = base[section.as_str()].as_array_mut().unwrap();
+            for (i, entry) in entries.into_iter().enumerate() {
+                let name = match entry.get("name").and_then(Value::as_str) {
+                    Some(name) => name.to_string(),
+                    None => {
+                        errors.push(RawsError::new(&format!("{}[{}]", section_path, i), "entry has no name"));
+                        continue;
+                    }
+                };
+                let remove = entry.get("remove").and_then(Value::as_bool) == Some(true);
+                let before = existing.len();
+                existing.retain(|e| e.get("name").and_then(Value::as_str) != Some(name.as_str()));
+                if remove {
+                    if existing.len() == before {
+                        let path = format!("{}: {}", file_name, entry_path(&section, &name));
+                        errors.push(RawsError::new(&path, "cannot remove an entry that does not exist"));
+                    }
+                } else {
+                    existing.push(entry);
+                }
+            }
+        } else {
+            errors.push(RawsError::new(&section_path, "unknown raws section"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn overlay(base: &mut Value, overlay: Value) -> Vec<String> {
+        let mut errors = Vec::new();
+        apply_overlay(base, overlay, "mods/test.json", &mut errors);
+        errors.iter().map(|error| error.to_string()).collect()
+    }
+
+    fn names(base: &Value, section: &str) -> Vec<String> {
+        base[section].as_array().unwrap().iter().map(|entry| entry["name"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn entries_are_added_replaced_and_removed_by_name() {
+        let mut base = json!({
+            "mobs": [ { "name": "Rat", "level": 1 }, { "name": "Bandit", "level": 2 }, { "name": "Orc", "level": 3 } ],
+            "items": [ { "name": "Dagger" } ]
+        });
+        let errors = overlay(&mut base, json!({ "mobs": [
+            { "name": "Bandit", "level": 5 },
+            { "name": "Rat", "remove": true },
+            { "name": "Goblin", "level": 1 }
+        ]}));
+        assert!(errors.is_empty());
+        assert_eq!(names(&base, "mobs"), vec!["Orc", "Bandit", "Goblin"]);
+        assert_eq!(base["mobs"][1], json!({ "name": "Bandit", "level": 5 }));
+        assert_eq!(names(&base, "items"), vec!["Dagger"]);
+    }
+
+    #[test]
+    fn missing_sections_are_created_and_single_sections_replaced() {
+        let mut base = json!({ "hunger_clock": { "well_fed": 100, "normal": 100, "hungry": 100, "starving": 100 } });
+        let errors = overlay(&mut base, json!({
+            "affixes": [ { "name": "of Speed" } ],
+            "hunger_clock": { "well_fed": 50 }
+        }));
+        assert!(errors.is_empty());
+        assert_eq!(names(&base, "affixes"), vec!["of Speed"]);
+        assert_eq!(base["hunger_clock"], json!({ "well_fed": 50 }));
+    }
+
+    #[test]
+    fn problems_are_reported_with_the_file_name() {
+        let mut base = json!({ "mobs": [ { "name": "Rat" } ] });
+        let errors = overlay(&mut base, json!({
+            "mobs": [ { "name": "Ghost", "remove": true }, { "level": 2 } ],
+            "items": { "name": "Dagger" },
+            "monsters": []
+        }));
+        assert_eq!(errors, vec![
+            "mods/test.json: items: expected a list",
+            "mods/test.json: mobs[\"Ghost\"]: cannot remove an entry that does not exist",
+            "mods/test.json: mobs[1]: entry has no name",
+            "mods/test.json: monsters: unknown raws section"
+        ]);
+        assert_eq!(names(&base, "mobs"), vec!["Rat"]);
+
+        assert_eq!(overlay(&mut base, json!([])), vec!["mods/test.json: expected an object of raws sections"]);
+    }
+
+    #[test]
+    fn files_apply_in_path_order() {
+        let directory = std::env::temp_dir().join(format!("raws-overlay-test-{}", std::process::id()));
+        fs::create_dir_all(directory.join("20-late")).unwrap();
+        fs::write(directory.join("10-early.json"), r#"{ "mobs": [ { "name": "Rat", "level": 2 } ] }"#).unwrap();
+        fs::write(directory.join("20-late").join("mobs.json"), r#"{ "mobs": [ { "name": "Rat", "level": 3 } ] }"#).unwrap();
+        fs::write(directory.join("notes.txt"), "not raws").unwrap();
+
+        let mut base = json!({ "mobs": [ { "name": "Rat", "level": 1 } ] });
+        let result = apply_overlays(&mut base, &directory);
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(result.is_ok());
+        assert_eq!(base, json!({ "mobs": [ { "name": "Rat", "level": 3 } ] }));
+    }
+
+    #[test]
+    fn a_missing_directory_changes_nothing() {
+        let mut base = json!({ "mobs": [] });
+        assert!(apply_overlays(&mut base, Path::new("no/such/directory")).is_ok());
+        assert_eq!(base, json!({ "mobs": [] }));
+    }
+}
@@ -1,12 +1,24 @@
 use std::collections::{HashMap, HashSet};
 use specs::prelude::*;
-use regex::Regex;
 use crate::{random_tables::RandomTable, components::*};
 use super::{Raws, spawn_table_structs::SpawnTableEntry};
-use crate::gamesystem::{attr_bonus, npc_hp, mana_at_level};
+use crate::gamesystem::{attr_bonus, npc_hp, mana_at_level, roll_item_quality, affix_chance, ItemQuality, CRITICAL_HIT_MULTIPLIER};
 use specs::saveload::{SimpleMarker, MarkedBuilder};
 use rltk::RandomNumberGenerator;
 use crate::raws::faction_structs::Reaction;
+use crate::map::MasterDungeonMap;
+
+const DEFAULT_FOOD_NUTRITION: i32 = 20;
+const DEFAULT_HUNGER_DURATION: i32 = 200;
+const DEFAULT_STATUS_DURATION: i32 = 10;
+const DEFAULT_BLOCK_CHANCE: i32 = 10;
+pub(super) const STATUS_EFFECT_KEYS: [&str; 12] = ["confusion", "poison", "slow", "haste", "regeneration", "might", "fitness",
+    "quickness", "intelligence", "duration", "status_name", "stacking"];
+pub(super) const ITEM_EFFECT_KEYS: [&str; 9] = ["provides_healing", "ranged", "damage", "damage_type", "area_of_effect", "food",
+    "magic_mapping", "teach_spell", "remove_curse"];
+pub(super) const TRIGGER_EFFECT_KEYS: [&str; 4] = ["damage", "damage_type", "teleport", "single_activation"];
+pub(super) const NUMERIC_EFFECT_KEYS: [&str; 14] = ["provides_healing", "ranged", "damage", "area_of_effect", "confusion",
+    "poison", "slow", "haste", "regeneration", "might", "fitness", "quickness", "intelligence", "duration"];
 
 pub enum SpawnType {
     AtPosition { x: i32, y: i32 },
@@ -21,6 +33,8 @@ pub struct RawMaster {
     prop_index: HashMap<String, usize>,
     loot_index: HashMap<String, usize>,
     faction_index: HashMap<String, HashMap<String, Reaction>>,
+    spell_index: HashMap<String, usize>,
+    background_index: HashMap<String, usize>,
 }
 
 impl RawMaster {
@@ -34,18 +48,25 @@ impl RawMaster {
                 spawn_table: Vec::new(),
                 loot_table: Vec::new(),
                 faction_table: Vec::new(),
+                spells: Vec::new(),
+                backgrounds: Vec::new(),
+                affixes: Vec::new(),
+                hunger_clock: None,
             },
             item_index : HashMap::new(),
             mob_index: HashMap::new(),
             prop_index: HashMap::new(),
             loot_index: HashMap::new(),
             faction_index: HashMap::new(),
+            spell_index: HashMap::new(),
+            background_index: HashMap::new(),
         }
     }
 
     pub fn load(&mut self, raws : Raws) {
+        // Start from empty indices, so that reloading never keeps entries that were removed
+        *self = RawMaster::empty();
         self.raws = raws;
-        self.item_index = HashMap::new();
         let mut used_names : HashSet<String> = HashSet::new();
         for (i,item) in self.raws.items.iter().enumerate() {
             if used_names.contains(&item.name) {
@@ -73,20 +94,19 @@ impl RawMaster {
                 rltk::console::log(format!("WARNING - Spawn tables references unspecified entity {}", spawn.name));
             }
         }
+        for (i, spell) in self.raws.spells.iter().enumerate() {
+            self.spell_index.insert(spell.name.clone(), i);
+        }
+        for (i, background) in self.raws.backgrounds.iter().enumerate() {
+            self.background_index.insert(background.name.clone(), i);
+        }
         for (i, loot) in self.raws.loot_table.iter().enumerate() {
             self.loot_index.insert(loot.name.clone(), i);
         }
         for faction in self.raws.faction_table.iter() {
             let mut reactions: HashMap<String, Reaction> = HashMap::new();
             for other in faction.responses.iter() {
-                reactions.insert(
-                    other.0.clone(),
-                    match other.1.as_str() {
-                        "ignore" => Reaction::Ignore,
-                        "flee" => Reaction::Flee,
-                        _ => Reaction::Attack
-                    }
-                );
+                reactions.insert(other.0.clone(), string_to_reaction(other.1));
             }
             self.faction_index.insert(faction.name.clone(), reactions);
         }
@@ -121,51 +141,73 @@ pub fn spawn_named_item(raws: &RawMaster, ecs: &mut World, key: &str, pos: Spawn
         eb = eb.with(Name { name: item_template.name.clone()});
         eb = eb.with(Item {
             initiative_penalty: item_template.initiative_penalty.unwrap_or(0.0),
-            weight_lbs: item_template.initiative_penalty.unwrap_or(0.0),
-            base_value: item_template.initiative_penalty.unwrap_or(0.0),
+            weight_lbs: item_template.weight_lbs.unwrap_or(0.0),
+            base_value: item_template.base_value.unwrap_or(0.0),
         });
 
         if let Some(consumable) = &item_template.consumable {
             eb = eb.with(crate::components::Consumable {});
-            for effect in consumable.effects.iter() {
-                let effect_name = effect.0.as_str();
-                match effect_name {
-                    "provides_healing" => {
-                        eb = eb.with(ProvidesHealing { heal_amount: effect.1.parse::<i32>().unwrap() })
-                    }
-                    "ranged" => eb = eb.with(Ranged { range: effect.1.parse::<i32>().unwrap() }),
-                    "damage" => eb = eb.with(InflictsDamage { damage: effect.1.parse::<i32>().unwrap() }),
-                    "area_of_effect" => eb = eb.with(AreaOfEffect { radius: effect.1.parse::<i32>().unwrap()}),
-                    "confusion" => eb = eb.with(Confusion { turns: effect.1.parse::<i32>().unwrap()}),
-                    // "magic_mapping" => eb = eb.with( MagicMapper {}),
-                    _ => {
-                        rltk::console::log(format!("Warning: consumable effect {} not implemented.", effect_name));
+            eb = apply_effects(&consumable.effects, eb);
+        }
+
+        if let Some(weapon) = &item_template.weapon {
+            match get_weapon_component(weapon) {
+                WeaponComponent::Ranged(ranged) => {
+                    eb = eb.with(Equippable { slot: EquipmentSlot::Ranged });
+                    eb = eb.with(ranged);
+                }
+                WeaponComponent::Melee(melee) => {
+                    eb = eb.with(Equippable { slot: EquipmentSlot::Melee });
+                    eb = eb.with(melee);
+                    if weapon.two_handed.unwrap_or(false) {
+                        eb = eb.with(TwoHanded {});
                     }
                 }
             }
         }
 
-        if let Some(weapon) = &item_template.weapon {
-            eb = eb.with(Equippable { slot: EquipmentSlot::Melee });
-            let (n_dice, die_type, bonus) = parse_dice_string(&weapon.base_damage);
-            let mut wpn = MeleeWeapon {
-                attribute : WeaponAttribute::Might,
-                damage_n_dice : n_dice,
-                damage_die_type : die_type,
-                damage_bonus : bonus,
-                hit_bonus : weapon.hit_bonus
-            };
-            match weapon.attribute.as_str() {
-                "Quickness" => wpn.attribute = WeaponAttribute::Quickness,
-                _ => wpn.attribute = WeaponAttribute::Might
-            }
-            eb = eb.with(wpn);
+        if let Some(shield) = &item_template.shield {
+            eb = eb.with(Equippable { slot: EquipmentSlot::Shield });
+            eb = eb.with(get_shield_component(shield));
+        }
+
+        if let Some(ammo) = &item_template.ammunition {
+            eb = eb.with(Equippable { slot: EquipmentSlot::Quiver });
+            eb = eb.with(Ammunition {
+                ammo_type: ammo.ammo_type.clone(),
+                count: ammo.count,
+                damage_bonus: ammo.damage_bonus.unwrap_or(0)
+            });
         }
 
         if let Some(wearable) = &item_template.wearable {
             let slot = string_to_slot(&wearable.slot);
             eb = eb.with(Equippable{ slot });
-            eb = eb.with(Wearable{ slot, armor_class: wearable.armor_class });
+            eb = eb.with(get_wearable_component(wearable));
+            if let Some(resistances) = get_resistances(&wearable.resistances, &wearable.vulnerabilities) {
+                eb = eb.with(resistances);
+            }
+        }
+
+        if let Some(attributes) = &item_template.attributes {
+            eb = eb.with(AttributeBonus {
+                might: attributes.might,
+                fitness: attributes.fitness,
+                quickness: attributes.quickness,
+                intelligence: attributes.intelligence
+            });
+        }
+
+        if item_template.cursed.unwrap_or(false) {
+            eb = eb.with(CursedItem {});
+        }
+
+        if let Some(turns) = item_template.use_turns {
+            eb = eb.with(UseTurns { turns });
+        }
+
+        if let Some(item_skills) = &item_template.skills {
+            eb = eb.with(get_skill_bonus(item_skills));
         }
 
         return Some(eb.build());
@@ -174,9 +216,259 @@ pub fn spawn_named_item(raws: &RawMaster, ecs: &mut World, key: &str, pos: Spawn
     None
 }
 
+/// Stat changes from a quality tier or affix, applied on top of an item's template.
+struct ItemModifier {
+    name: String,
+    hit_bonus: i32,
+    damage_bonus: i32,
+    damage_die_type: i32,
+    armor_class: f32,
+    weight_multiplier: f32,
+    value_multiplier: f32
+}
+
+impl ItemModifier {
+    fn from_affix(affix: &super::affix_structs::Affix) -> ItemModifier {
+        ItemModifier {
+            name: affix.name.clone(),
+            hit_bonus: affix.hit_bonus.unwrap_or(0),
+            damage_bonus: affix.damage_bonus.unwrap_or(0),
+            damage_die_type: affix.damage_die_type.unwrap_or(0),
+            armor_class: affix.armor_class.unwrap_or(0.0),
+            weight_multiplier: affix.weight_multiplier.unwrap_or(1.0),
+            value_multiplier: affix.value_multiplier.unwrap_or(1.0)
+        }
+    }
+
+    fn from_quality(quality: ItemQuality) -> Option<ItemModifier> {
+        let (name, hit_bonus, damage_bonus, armor_class, weight_multiplier, value_multiplier) = match quality {
+            ItemQuality::Crude => ("Crude", -1, -1, -0.5, 1.2, 0.5),
+            ItemQuality::Normal => return None,
+            ItemQuality::Fine => ("Fine", 1, 0, 0.5, 1.0, 2.0),
+            ItemQuality::Masterwork => ("Masterwork", 1, 1, 1.0, 0.8, 4.0)
+        };
+        Some(ItemModifier { name: name.to_string(), hit_bonus, damage_bonus, damage_die_type: 0, armor_class,
+            weight_multiplier, value_multiplier })
+    }
+
+    fn from_quality_name(name: &str) -> Option<ItemModifier> {
+        [ItemQuality::Crude, ItemQuality::Fine, ItemQuality::Masterwork].iter()
+            .filter_map(|quality| ItemModifier::from_quality(*quality))
+            .find(|modifier| modifier.name == name)
+    }
+}
+
+/// Spawns a weapon or piece of armor with a rolled quality tier and possibly a prefix and
+/// suffix from the raws' affixes, with better odds deeper in the dungeon. Magic and cursed
+/// items, and everything else, spawn as written. Returns `None` if `key` isn't an item.
+pub fn spawn_generated_item(raws: &RawMaster, ecs: &mut World, key: &str, pos: SpawnType, depth: i32) -> Option<Entity> {
+    let entity = spawn_named_item(raws, ecs, key, pos)?;
+    let item_template = &raws.raws.items[raws.item_index[key]];
+    let kind = if item_template.weapon.is_some() { "weapon" }
+        else if item_template.wearable.is_some() || item_template.shield.is_some() { "wearable" }
+        else { return Some(entity) };
+    if item_template.magic.is_some() || item_template.cursed.unwrap_or(false) {
+        return Some(entity);
+    }
+
+    let generated = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let quality = ItemModifier::from_quality(roll_item_quality(&mut rng, depth));
+        let prefix = roll_affix(raws, &mut rng, "prefix", kind, depth);
+        let suffix = roll_affix(raws, &mut rng, "suffix", kind, depth);
+        GeneratedItem {
+            template: key.to_string(),
+            quality: quality.map(|modifier| modifier.name),
+            prefix: prefix.map(|modifier| modifier.name),
+            suffix: suffix.map(|modifier| modifier.name)
+        }
+    };
+    if generated.quality.is_some() || generated.prefix.is_some() || generated.suffix.is_some() {
+        apply_generated_item(raws, ecs, entity, generated);
+    }
+
+    Some(entity)
+}
+
+/// Applies a generated item's quality and affixes on top of its template's stats, and names it
+/// after them. Affixes no longer in the raws are dropped.
+fn apply_generated_item(raws: &RawMaster, ecs: &mut World, entity: Entity, generated: GeneratedItem) {
+    let quality = generated.quality.as_deref().and_then(ItemModifier::from_quality_name);
+    let prefix = generated.prefix.as_deref().and_then(|name| find_affix(raws, "prefix", name));
+    let suffix = generated.suffix.as_deref().and_then(|name| find_affix(raws, "suffix", name));
+
+    let mut name = raws.raws.items[raws.item_index[&generated.template]].name.clone();
+    if let Some(prefix) = &prefix { name = format!("{} {}", prefix.name, name); }
+    if let Some(quality) = &quality { name = format!("{} {}", quality.name, name); }
+    if let Some(suffix) = &suffix { name = format!("{} {}", name, suffix.name); }
+
+    for modifier in [&quality, &prefix, &suffix].iter().filter_map(|modifier| modifier.as_ref()) {
+        apply_item_modifier(ecs, entity, modifier);
+    }
+    ecs.write_storage::<Name>().insert(entity, Name { name }).expect("Unable to insert");
+    ecs.write_storage::<GeneratedItem>().insert(entity, generated).expect("Unable to insert");
+}
+
+fn roll_affix(raws: &RawMaster, rng: &mut RandomNumberGenerator, position: &str, kind: &str, depth: i32) -> Option<ItemModifier> {
+    if rng.roll_dice(1, 100) > affix_chance(depth) {
+        return None;
+    }
+    let mut table = RandomTable::new();
+    for affix in raws.raws.affixes.iter()
+        .filter(|affix| affix.position == position && affix.min_depth <= depth && affix.applies_to.iter().any(|a| a == kind)) {
+        table = table.add(&affix.name, affix.weight);
+    }
+    let picked = table.roll(rng);
+    find_affix(raws, position, &picked)
+}
+
+fn find_affix(raws: &RawMaster, position: &str, name: &str) -> Option<ItemModifier> {
+    raws.raws.affixes.iter().find(|affix| affix.name == name && affix.position == position).map(ItemModifier::from_affix)
+}
+
+fn apply_item_modifier(ecs: &mut World, entity: Entity, modifier: &ItemModifier) {
+    if let Some(melee) = ecs.write_storage::<MeleeWeapon>().get_mut(entity) {
+        melee.hit_bonus += modifier.hit_bonus;
+        melee.damage.add_bonus(modifier.damage_bonus);
+        melee.damage.add_sides(modifier.damage_die_type);
+    }
+    if let Some(ranged) = ecs.write_storage::<RangedWeapon>().get_mut(entity) {
+        ranged.hit_bonus += modifier.hit_bonus;
+        ranged.damage.add_bonus(modifier.damage_bonus);
+        ranged.damage.add_sides(modifier.damage_die_type);
+    }
+    if let Some(wearable) = ecs.write_storage::<Wearable>().get_mut(entity) {
+        wearable.armor_class = f32::max(0.0, wearable.armor_class + modifier.armor_class);
+    }
+    if let Some(shield) = ecs.write_storage::<Shield>().get_mut(entity) {
+        shield.defense_bonus = i32::max(0, shield.defense_bonus + modifier.armor_class as i32);
+    }
+    if let Some(item) = ecs.write_storage::<Item>().get_mut(entity) {
+        item.weight_lbs *= modifier.weight_multiplier;
+        item.base_value *= modifier.value_multiplier;
+    }
+}
+
+fn get_skill_bonus(item_skills: &HashMap<String, i32>) -> SkillBonus {
+    let mut skills = HashMap::new();
+    for sk in item_skills.iter() {
+        match string_to_skill(sk.0) {
+            Some(skill) => { skills.insert(skill, *sk.1); }
+            None => { rltk::console::log(format!("Unknown skill references [{}]", sk.0)); }
+        }
+    }
+    SkillBonus { skills }
+}
+
+fn apply_effects<'a>(effects: &HashMap<String, String>, mut eb: EntityBuilder<'a>) -> EntityBuilder<'a> {
+    for effect in effects.iter() {
+        let effect_name = effect.0.as_str();
+        match effect_name {
+            "provides_healing" => {
+                eb = eb.with(ProvidesHealing { heal_amount: effect.1.parse::<i32>().unwrap() })
+            }
+            "ranged" => eb = eb.with(Ranged { range: effect.1.parse::<i32>().unwrap() }),
+            "damage" => eb = eb.with(get_inflicts_damage(effects)),
+            "area_of_effect" => eb = eb.with(AreaOfEffect { radius: effect.1.parse::<i32>().unwrap()}),
+            "food" => eb = eb.with(ProvidesFood { nutrition: effect.1.parse::<i32>().unwrap_or(DEFAULT_FOOD_NUTRITION) }),
+            "magic_mapping" => eb = eb.with(MagicMapper {}),
+            "teach_spell" => eb = eb.with(TeachesSpell { spell: effect.1.to_string() }),
+            "remove_curse" => eb = eb.with(ProvidesRemoveCurse {}),
+            "damage_type" => {}
+            _ if STATUS_EFFECT_KEYS.contains(&effect_name) => {}
+            _ => {
+                rltk::console::log(format!("Warning: consumable effect {} not implemented.", effect_name));
+            }
+        }
+    }
+    if let Some(status) = parse_status_effect(effects) {
+        eb = eb.with(InflictsStatus { status });
+    }
+    eb
+}
+
+/// "damage" with an optional "damage_type"; damage without a type ignores resistances.
+fn get_inflicts_damage(effects: &HashMap<String, String>) -> InflictsDamage {
+    InflictsDamage {
+        damage: effects["damage"].parse::<i32>().unwrap(),
+        damage_type: effects.get("damage_type").and_then(|damage_type| string_to_damage_type(damage_type))
+    }
+}
+
+/// Gathers the status-related effect keys into a single template. "confusion" keeps its
+/// old meaning of a number of turns; the other keys take their magnitude and share "duration".
+fn parse_status_effect(effects: &HashMap<String, String>) -> Option<StatusEffectTemplate> {
+    let number = |key: &str| effects.get(key).map(|value| value.parse::<i32>().unwrap());
+    let attribute_keys = ["might", "fitness", "quickness", "intelligence"];
+
+    let name = if effects.contains_key("confusion") { "Confused" }
+        else if effects.contains_key("poison") { "Poisoned" }
+        else if effects.contains_key("slow") { "Slowed" }
+        else if effects.contains_key("haste") { "Hasted" }
+        else if effects.contains_key("regeneration") { "Regenerating" }
+        else if attribute_keys.iter().any(|key| effects.contains_key(*key)) {
+            if attribute_keys.iter().filter_map(|key| number(key)).sum::<i32>() < 0 { "Weakened" } else { "Empowered" }
+        }
+        else { return None };
+
+    let attributes = if attribute_keys.iter().any(|key| effects.contains_key(*key)) {
+        Some(AttributeBonus {
+            might: number("might"),
+            fitness: number("fitness"),
+            quickness: number("quickness"),
+            intelligence: number("intelligence")
+        })
+    } else {
+        None
+    };
+
+    Some(StatusEffectTemplate {
+        name: effects.get("status_name").cloned().unwrap_or_else(|| name.to_string()),
+        turns: number("confusion").or_else(|| number("duration")).unwrap_or(DEFAULT_STATUS_DURATION),
+        confusion: effects.contains_key("confusion"),
+        damage_over_time: number("poison").unwrap_or(0),
+        heal_over_time: number("regeneration").unwrap_or(0),
+        initiative_penalty: number("slow").unwrap_or(0) - number("haste").unwrap_or(0),
+        attributes,
+        stacking: effects.contains_key("stacking")
+    })
+}
+
+pub fn spawn_named_spell(raws: &RawMaster, ecs: &mut World, key: &str) -> Option<Entity> {
+    if raws.spell_index.contains_key(key) {
+        let spell_template = &raws.raws.spells[raws.spell_index[key]];
+
+        let mut eb = ecs.create_entity().marked::<SimpleMarker<SerializeMe>>();
+        eb = eb.with(SpellTemplate { mana_cost: spell_template.mana_cost });
+        eb = eb.with(Name { name: spell_template.name.clone() });
+        eb = apply_effects(&spell_template.effects, eb);
+
+        return Some(eb.build());
+    }
+    None
+}
+
+pub fn spawn_all_spells(ecs: &mut World) {
+    let raws = &super::RAWS.lock().unwrap();
+    for spell in raws.raws.spells.iter() {
+        spawn_named_spell(raws, ecs, &spell.name);
+    }
+}
+
+/// Replaces every spell entity with a fresh one from the current raws.
+pub fn respawn_all_spells(ecs: &mut World) {
+    let spells : Vec<Entity> = (&ecs.entities(), &ecs.read_storage::<SpellTemplate>()).join()
+        .map(|(entity, _spell)| entity)
+        .collect();
+    ecs.delete_entities(&spells).expect("Unable to delete");
+    spawn_all_spells(ecs);
+}
+
 pub fn spawn_named_mob(raws: &RawMaster, ecs : &mut World, key: &str, pos: SpawnType) -> Option<Entity> {
     if raws.mob_index.contains_key(key) {
         let mob_template = &raws.raws.mobs[raws.mob_index[key]];
+        let gold = mob_template.gold.as_ref()
+            .map_or(0.0, |gold| gold.roll(&mut ecs.write_resource::<RandomNumberGenerator>()) as f32);
 
         let mut eb = ecs.create_entity().marked::<SimpleMarker<SerializeMe>>();
 
@@ -204,28 +496,9 @@ pub fn spawn_named_mob(raws: &RawMaster, ecs : &mut World, key: &str, pos: Spawn
             });
         }
 
-        let mut mob_fitness = 11;
-        let mut mob_int = 11;
-        let mut attr = Attributes {
-            might: Attribute { base: 11, modifiers: 0, bonus: attr_bonus(11) },
-            fitness: Attribute { base: 11, modifiers: 0, bonus: attr_bonus(11) },
-            quickness: Attribute { base: 11, modifiers: 0, bonus: attr_bonus(11) },
-            intelligence: Attribute { base: 11, modifiers: 0, bonus: attr_bonus(11) }
-        };
-        if let Some(might) = mob_template.attributes.might {
-            attr.might = Attribute { base: might, modifiers: 0, bonus: attr_bonus(might) };
-        }
-        if let Some(fitness) = mob_template.attributes.fitness {
-            attr.fitness = Attribute { base: fitness, modifiers: 0, bonus: attr_bonus(fitness) };
-            mob_fitness = fitness;
-        }
-        if let Some(quickness) = mob_template.attributes.quickness {
-            attr.quickness = Attribute { base: quickness, modifiers: 0, bonus: attr_bonus(quickness) };
-        }
-        if let Some(intelligence) = mob_template.attributes.intelligence {
-            attr.intelligence = Attribute { base: intelligence, modifiers: 0, bonus: attr_bonus(intelligence) };
-            mob_int = intelligence;
-        }
+        let attr = get_mob_attributes(&mob_template.attributes);
+        let mob_fitness = attr.fitness.base;
+        let mob_int = attr.intelligence.base;
         eb = eb.with(attr);
 
         let mob_level = if mob_template.level.is_some() { mob_template.level.unwrap() } else { 1 };
@@ -239,51 +512,14 @@ pub fn spawn_named_mob(raws: &RawMaster, ecs : &mut World, key: &str, pos: Spawn
             mana: Pool { current: mob_mana, max: mob_mana },
             total_weight: 0.0,
             total_initiative_penalty: 0.0,
-            gold: if let Some(gold) = &mob_template.gold {
-                    let mut rng = rltk::RandomNumberGenerator::new();
-                    let (n, d, b) = parse_dice_string(&gold);
-                    (rng.roll_dice(n, d) + b) as f32
-                } else {
-                    0.0
-                }
+            gold
         };
         eb = eb.with(pools);
 
-        let mut skills = Skills { skills: HashMap::new() };
-        skills.skills.insert(Skill::Melee, 1);
-        skills.skills.insert(Skill::Defense, 1);
-        skills.skills.insert(Skill::Magic, 1);
-        if let Some(mob_skills) = &mob_template.skills {
-            for sk in mob_skills.iter() {
-                match sk.0.as_str() {
-                    "Melee" => { skills.skills.insert(Skill::Melee, *sk.1); }
-                    "Defense" => { skills.skills.insert(Skill::Defense, *sk.1); }
-                    "Magic" => { skills.skills.insert(Skill::Magic, *sk.1); }
-                    _ => { rltk::console::log(format!("Unknown skill references [{}]", sk.0)); }
-                }
-            }
-        }
-        eb = eb.with(skills);
+        eb = eb.with(get_mob_skills(mob_template));
 
         if let Some(na) = &mob_template.natural {
-            let mut nature = NaturalAttackDefense{
-                armor_class : na.armor_class,
-                attacks: Vec::new()
-            };
-            if let Some(attacks) = &na.attacks {
-                for nattack in attacks.iter() {
-                    let (n, d, b) = parse_dice_string(&nattack.damage);
-                    let attack = NaturalAttack{
-                        name : nattack.name.clone(),
-                        hit_bonus : nattack.hit_bonus,
-                        damage_n_dice : n,
-                        damage_die_type : d,
-                        damage_bonus: b
-                    };
-                    nature.attacks.push(attack);
-                }
-            }
-            eb = eb.with(nature);
+            eb = eb.with(get_natural_attack_defense(na));
         }
 
         if let Some(loot) = &mob_template.loot_table {
@@ -296,7 +532,7 @@ pub fn spawn_named_mob(raws: &RawMaster, ecs : &mut World, key: &str, pos: Spawn
             eb = eb.with(LightSource{ range: light.range, color : rltk::RGB::from_hex(&light.color).expect("Bad color") });
         }
 
-        eb = eb.with(Initiative { current: 2 });
+        eb = eb.with(Initiative { energy: 0 });
 
         if let Some(faction) = &mob_template.faction {
             eb = eb.with(Faction { name: faction.clone() });
@@ -306,10 +542,31 @@ pub fn spawn_named_mob(raws: &RawMaster, ecs : &mut World, key: &str, pos: Spawn
 
         eb = eb.with(EquipmentChanged{});
 
+        if let Some(spells) = &mob_template.spells {
+            let mut known_spells = KnownSpells { spells: Vec::new() };
+            for spell_name in spells.iter() {
+                if raws.spell_index.contains_key(spell_name) {
+                    let spell = &raws.raws.spells[raws.spell_index[spell_name]];
+                    known_spells.spells.push(KnownSpell { display_name: spell.name.clone(), mana_cost: spell.mana_cost });
+                } else {
+                    rltk::console::log(format!("Warning: mob {} knows unknown spell {}.", mob_template.name, spell_name));
+                }
+            }
+            eb = eb.with(known_spells);
+        }
+
         if let Some(vendor) = &mob_template.vendor {
             eb = eb.with(Vendor { categories: vendor.clone() });
         }
 
+        if let Some(temple) = &mob_template.temple {
+            eb = eb.with(Temple { remove_curse_cost: temple.remove_curse_cost });
+        }
+
+        if let Some(resistances) = get_resistances(&mob_template.resistances, &mob_template.vulnerabilities) {
+            eb = eb.with(resistances);
+        }
+
         let new_mob = eb.build();
 
         // Are they wielding anyting?
@@ -350,6 +607,24 @@ fn spawn_named_props(raws: &RawMaster, ecs : &mut World, key : &str, pos : Spawn
         if let Some(door_open) = prop_template.door_open {
             eb = eb.with(Door{ open: door_open });
         }
+        if let Some(entry_trigger) = &prop_template.entry_trigger {
+            eb = eb.with(EntryTrigger{});
+            for effect in entry_trigger.effects.iter() {
+                match effect.0.as_str() {
+                    "damage" => { eb = eb.with(get_inflicts_damage(&entry_trigger.effects)) }
+                    "damage_type" => {}
+                    "teleport" => { eb = eb.with(RandomTeleport{}) }
+                    "single_activation" => { eb = eb.with(SingleActivation{}) }
+                    _ if STATUS_EFFECT_KEYS.contains(&effect.0.as_str()) => {}
+                    _ => {
+                        rltk::console::log(format!("Warning: entry trigger effect {} not implemented.", effect.0));
+                    }
+                }
+            }
+            if let Some(status) = parse_status_effect(&entry_trigger.effects) {
+                eb = eb.with(InflictsStatus { status });
+            }
+        }
 
         return Some(eb.build());
     }
@@ -369,6 +644,383 @@ fn spawn_position<'a>(pos: SpawnType, new_entity: EntityBuilder<'a>, tag: &str,
     }
 }
 
+/// Re-applies the current templates to entities already in the world, matched by `Name` (or,
+/// for generated items, by the template they were made from). Renderables, mob statistics,
+/// natural attacks, loot tables, factions, shops, item statistics, consumable effects, skill
+/// bonuses and trap effects are replaced, along with a generated item's quality and affixes,
+/// and components the template no longer has are removed; position, health fraction, gold
+/// and inventory are kept. An equipped item that no longer fits its slot goes back in its
+/// owner's backpack. Returns how many entities were refreshed.
+pub fn refresh_entities_from_raws(raws: &RawMaster, ecs: &mut World) -> usize {
+    let player = *ecs.fetch::<Entity>();
+    let targets : Vec<(Entity, String)> = {
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let generated = ecs.read_storage::<GeneratedItem>();
+        (&entities, &names).join()
+            .map(|(entity, name)| (entity, generated.get(entity).map_or(&name.name, |generated| &generated.template)))
+            .filter(|(entity, key)| *entity != player && (raws.item_index.contains_key(*key)
+                || raws.mob_index.contains_key(*key) || raws.prop_index.contains_key(*key)))
+            .map(|(entity, key)| (entity, key.clone()))
+            .collect()
+    };
+
+    for (entity, name) in targets.iter() {
+        if let Some(index) = raws.item_index.get(name) {
+            refresh_item(&raws.raws.items[*index], ecs, *entity);
+            let generated = ecs.read_storage::<GeneratedItem>().get(*entity).cloned();
+            if let Some(generated) = generated {
+                apply_generated_item(raws, ecs, *entity, generated);
+            }
+        } else if let Some(index) = raws.mob_index.get(name) {
+            refresh_mob(&raws.raws.mobs[*index], ecs, *entity);
+        } else if let Some(index) = raws.prop_index.get(name) {
+            refresh_prop(&raws.raws.props[*index], ecs, *entity);
+        }
+    }
+
+    targets.len()
+}
+
+/// Inserts `component` if the template provides one, and removes any old one if it doesn't.
+fn set_component<T: Component>(ecs: &World, entity: Entity, component: Option<T>) {
+    let mut storage = ecs.write_storage::<T>();
+    match component {
+        Some(component) => { storage.insert(entity, component).expect("Unable to insert"); }
+        None => { storage.remove(entity); }
+    }
+}
+
+/// Re-derives the components `apply_effects` and a prop's entry trigger build from `effects`.
+fn refresh_effects(ecs: &World, entity: Entity, effects: Option<&HashMap<String, String>>) {
+    let effect = |key: &str| effects.and_then(|effects| effects.get(key));
+    let number = |key: &str| effect(key).map(|value| value.parse::<i32>().unwrap());
+    set_component(ecs, entity, number("provides_healing").map(|heal_amount| ProvidesHealing { heal_amount }));
+    set_component(ecs, entity, number("ranged").map(|range| Ranged { range }));
+    set_component(ecs, entity, effects.filter(|effects| effects.contains_key("damage")).map(get_inflicts_damage));
+    set_component(ecs, entity, number("area_of_effect").map(|radius| AreaOfEffect { radius }));
+    set_component(ecs, entity, effect("food")
+        .map(|food| ProvidesFood { nutrition: food.parse::<i32>().unwrap_or(DEFAULT_FOOD_NUTRITION) }));
+    set_component(ecs, entity, effect("magic_mapping").map(|_| MagicMapper {}));
+    set_component(ecs, entity, effect("teach_spell").map(|spell| TeachesSpell { spell: spell.to_string() }));
+    set_component(ecs, entity, effect("remove_curse").map(|_| ProvidesRemoveCurse {}));
+    set_component(ecs, entity, effect("teleport").map(|_| RandomTeleport {}));
+    set_component(ecs, entity, effect("single_activation").map(|_| SingleActivation {}));
+    set_component(ecs, entity, effects.and_then(parse_status_effect).map(|status| InflictsStatus { status }));
+}
+
+fn refresh_prop(prop_template: &super::prop_structs::Prop, ecs: &mut World, entity: Entity) {
+    if let Some(renderable) = &prop_template.renderable {
+        ecs.write_storage::<crate::components::Renderable>().insert(entity, get_renderable_component(renderable)).expect("Unable to insert");
+    }
+    set_component(ecs, entity, prop_template.entry_trigger.as_ref().map(|_| EntryTrigger {}));
+    refresh_effects(ecs, entity, prop_template.entry_trigger.as_ref().map(|trigger| &trigger.effects));
+}
+
+fn refresh_item(item_template: &super::item_structs::Item, ecs: &mut World, entity: Entity) {
+    if let Some(renderable) = &item_template.renderable {
+        ecs.write_storage::<crate::components::Renderable>().insert(entity, get_renderable_component(renderable)).expect("Unable to insert");
+    }
+    ecs.write_storage::<Item>().insert(entity, Item {
+        initiative_penalty: item_template.initiative_penalty.unwrap_or(0.0),
+        weight_lbs: item_template.weight_lbs.unwrap_or(0.0),
+        base_value: item_template.base_value.unwrap_or(0.0),
+    }).expect("Unable to insert");
+
+    set_component(ecs, entity, item_template.consumable.as_ref().map(|_| crate::components::Consumable {}));
+    refresh_effects(ecs, entity, item_template.consumable.as_ref().map(|consumable| &consumable.effects));
+    set_component(ecs, entity, item_template.skills.as_ref().map(get_skill_bonus));
+
+    let mut melee_weapons = ecs.write_storage::<MeleeWeapon>();
+    let mut ranged_weapons = ecs.write_storage::<RangedWeapon>();
+    let mut two_handed = ecs.write_storage::<TwoHanded>();
+    melee_weapons.remove(entity);
+    ranged_weapons.remove(entity);
+    two_handed.remove(entity);
+    if let Some(weapon) = &item_template.weapon {
+        match get_weapon_component(weapon) {
+            WeaponComponent::Ranged(ranged) => { ranged_weapons.insert(entity, ranged).expect("Unable to insert"); }
+            WeaponComponent::Melee(melee) => {
+                melee_weapons.insert(entity, melee).expect("Unable to insert");
+                if weapon.two_handed.unwrap_or(false) {
+                    two_handed.insert(entity, TwoHanded {}).expect("Unable to insert");
+                }
+            }
+        }
+    }
+    drop((melee_weapons, ranged_weapons, two_handed));
+
+    if let Some(ammo) = &item_template.ammunition {
+        if let Some(ammunition) = ecs.write_storage::<Ammunition>().get_mut(entity) {
+            ammunition.damage_bonus = ammo.damage_bonus.unwrap_or(0);
+        }
+    }
+
+    let mut wearables = ecs.write_storage::<Wearable>();
+    let mut resistances = ecs.write_storage::<Resistances>();
+    match &item_template.wearable {
+        Some(wearable) => {
+            wearables.insert(entity, get_wearable_component(wearable)).expect("Unable to insert");
+            match get_resistances(&wearable.resistances, &wearable.vulnerabilities) {
+                Some(r) => { resistances.insert(entity, r).expect("Unable to insert"); }
+                None => { resistances.remove(entity); }
+            }
+        }
+        None => {
+            wearables.remove(entity);
+            resistances.remove(entity);
+        }
+    }
+    drop((wearables, resistances));
+
+    let mut shields = ecs.write_storage::<Shield>();
+    match &item_template.shield {
+        Some(shield) => { shields.insert(entity, get_shield_component(shield)).expect("Unable to insert"); }
+        None => { shields.remove(entity); }
+    }
+    drop(shields);
+
+    let mut bonuses = ecs.write_storage::<AttributeBonus>();
+    match &item_template.attributes {
+        Some(attributes) => {
+            bonuses.insert(entity, AttributeBonus {
+                might: attributes.might,
+                fitness: attributes.fitness,
+                quickness: attributes.quickness,
+                intelligence: attributes.intelligence
+            }).expect("Unable to insert");
+        }
+        None => { bonuses.remove(entity); }
+    }
+    drop(bonuses);
+
+    let mut use_turns = ecs.write_storage::<UseTurns>();
+    match item_template.use_turns {
+        Some(turns) => { use_turns.insert(entity, UseTurns { turns }).expect("Unable to insert"); }
+        None => { use_turns.remove(entity); }
+    }
+    drop(use_turns);
+
+    // An item that now goes in a different slot, or can't be equipped at all, is taken off
+    let slot = equippable_slot(item_template);
+    let mut equippables = ecs.write_storage::<Equippable>();
+    match slot {
+        Some(slot) => { equippables.insert(entity, Equippable { slot }).expect("Unable to insert"); }
+        None => { equippables.remove(entity); }
+    }
+    drop(equippables);
+    let off_hand = slot == Some(EquipmentSlot::Melee) && !item_template.weapon.as_ref().is_some_and(|weapon| weapon.two_handed.unwrap_or(false));
+    let mut equipped = ecs.write_storage::<Equipped>();
+    if let Some(worn) = equipped.get(entity) {
+        if Some(worn.slot) != slot && !(off_hand && worn.slot == EquipmentSlot::Shield) {
+            let owner = worn.owner;
+            equipped.remove(entity);
+            ecs.write_storage::<InBackpack>().insert(entity, InBackpack { owner }).expect("Unable to insert");
+        }
+    }
+    drop(equipped);
+
+    // Whoever carries the item needs their encumbrance and bonuses recalculated
+    let owner = ecs.read_storage::<Equipped>().get(entity).map(|equipped| equipped.owner)
+        .or_else(|| ecs.read_storage::<InBackpack>().get(entity).map(|pack| pack.owner));
+    if let Some(owner) = owner {
+        ecs.write_storage::<EquipmentChanged>().insert(owner, EquipmentChanged{}).expect("Unable to insert");
+    }
+}
+
+fn refresh_mob(mob_template: &super::mob_structs::Mob, ecs: &mut World, entity: Entity) {
+    if let Some(renderable) = &mob_template.renderable {
+        ecs.write_storage::<crate::components::Renderable>().insert(entity, get_renderable_component(renderable)).expect("Unable to insert");
+    }
+
+    let mut attr = get_mob_attributes(&mob_template.attributes);
+    if let Some(old) = ecs.read_storage::<Attributes>().get(entity) {
+        attr.might.modifiers = old.might.modifiers;
+        attr.fitness.modifiers = old.fitness.modifiers;
+        attr.quickness.modifiers = old.quickness.modifiers;
+        attr.intelligence.modifiers = old.intelligence.modifiers;
+    }
+    let mob_level = mob_template.level.unwrap_or(1);
+    if let Some(pools) = ecs.write_storage::<Pools>().get_mut(entity) {
+        let hp_fraction = pools.hit_points.current as f32 / i32::max(1, pools.hit_points.max) as f32;
+        let mana_fraction = pools.mana.current as f32 / i32::max(1, pools.mana.max) as f32;
+        pools.level = mob_level;
+        pools.hit_points.max = npc_hp(attr.fitness.base + attr.fitness.modifiers, mob_level);
+        pools.mana.max = mana_at_level(attr.intelligence.base + attr.intelligence.modifiers, mob_level);
+        pools.hit_points.current = i32::max(1, (pools.hit_points.max as f32 * hp_fraction) as i32);
+        pools.mana.current = (pools.mana.max as f32 * mana_fraction) as i32;
+    }
+    ecs.write_storage::<Attributes>().insert(entity, attr).expect("Unable to insert");
+
+    let mut skills = get_mob_skills(mob_template);
+    if let Some(old) = ecs.read_storage::<Skills>().get(entity) {
+        skills.modifiers = old.modifiers.clone();
+    }
+    ecs.write_storage::<Skills>().insert(entity, skills).expect("Unable to insert");
+
+    let mut naturals = ecs.write_storage::<NaturalAttackDefense>();
+    match &mob_template.natural {
+        Some(na) => { naturals.insert(entity, get_natural_attack_defense(na)).expect("Unable to insert"); }
+        None => { naturals.remove(entity); }
+    }
+    drop(naturals);
+
+    let mut resistances = ecs.write_storage::<Resistances>();
+    match get_resistances(&mob_template.resistances, &mob_template.vulnerabilities) {
+        Some(r) => { resistances.insert(entity, r).expect("Unable to insert"); }
+        None => { resistances.remove(entity); }
+    }
+    drop(resistances);
+
+    let mut loot = ecs.write_storage::<LootTable>();
+    match &mob_template.loot_table {
+        Some(table) => { loot.insert(entity, LootTable { table: table.clone() }).expect("Unable to insert"); }
+        None => { loot.remove(entity); }
+    }
+    drop(loot);
+
+    let mut lights = ecs.write_storage::<LightSource>();
+    match &mob_template.light {
+        Some(light) => {
+            lights.insert(entity, LightSource{ range: light.range, color : rltk::RGB::from_hex(&light.color).expect("Bad color") }).expect("Unable to insert");
+        }
+        None => { lights.remove(entity); }
+    }
+    drop(lights);
+
+    if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(entity) {
+        viewshed.range = mob_template.vision_range;
+        viewshed.dirty = true;
+    }
+    let faction = mob_template.faction.clone().unwrap_or_else(|| "Mindless".to_string());
+    ecs.write_storage::<Faction>().insert(entity, Faction { name: faction }).expect("Unable to insert");
+    let mut vendors = ecs.write_storage::<Vendor>();
+    match &mob_template.vendor {
+        Some(vendor) => { vendors.insert(entity, Vendor { categories: vendor.clone() }).expect("Unable to insert"); }
+        None => { vendors.remove(entity); }
+    }
+    drop(vendors);
+
+    let mut temples = ecs.write_storage::<Temple>();
+    match &mob_template.temple {
+        Some(temple) => { temples.insert(entity, Temple { remove_curse_cost: temple.remove_curse_cost }).expect("Unable to insert"); }
+        None => { temples.remove(entity); }
+    }
+    drop(temples);
+
+    ecs.write_storage::<EquipmentChanged>().insert(entity, EquipmentChanged{}).expect("Unable to insert");
+}
+
+enum WeaponComponent {
+    Melee(MeleeWeapon),
+    Ranged(RangedWeapon)
+}
+
+/// A weapon with a numeric range is a ranged weapon, aimed with Quickness; anything else ("melee")
+/// is wielded.
+fn get_weapon_component(weapon: &super::item_structs::Weapon) -> WeaponComponent {
+    let attribute = match weapon.attribute.as_str() {
+        "Quickness" => WeaponAttribute::Quickness,
+        _ => WeaponAttribute::Might
+    };
+    let damage_type = weapon.damage_type.as_ref().and_then(|damage_type| string_to_damage_type(damage_type));
+    let critical_multiplier = weapon.critical_multiplier.unwrap_or(CRITICAL_HIT_MULTIPLIER);
+    match weapon.range.parse::<i32>() {
+        Ok(range) => WeaponComponent::Ranged(RangedWeapon {
+            range,
+            damage : weapon.base_damage.clone(),
+            hit_bonus : weapon.hit_bonus,
+            ammunition : weapon.ammunition.clone(),
+            damage_type : damage_type.unwrap_or(DamageType::Piercing),
+            critical_multiplier
+        }),
+        Err(_) => WeaponComponent::Melee(MeleeWeapon {
+            attribute,
+            damage : weapon.base_damage.clone(),
+            hit_bonus : weapon.hit_bonus,
+            damage_type : damage_type.unwrap_or(DamageType::Bludgeoning),
+            critical_multiplier
+        })
+    }
+}
+
+fn get_wearable_component(wearable: &super::item_structs::Wearable) -> Wearable {
+    Wearable {
+        slot: string_to_slot(&wearable.slot),
+        armor_class: wearable.armor_class,
+        damage_reduction: wearable.damage_reduction.unwrap_or(0)
+    }
+}
+
+fn get_resistances(resistances: &Option<Vec<String>>, vulnerabilities: &Option<Vec<String>>) -> Option<Resistances> {
+    if resistances.is_none() && vulnerabilities.is_none() {
+        return None;
+    }
+    let damage_types = |names: &Option<Vec<String>>| -> Vec<DamageType> {
+        names.iter().flatten().filter_map(|name| string_to_damage_type(name)).collect()
+    };
+    Some(Resistances {
+        resistances: damage_types(resistances),
+        vulnerabilities: damage_types(vulnerabilities)
+    })
+}
+
+fn get_shield_component(shield: &super::item_structs::Shield) -> Shield {
+    Shield {
+        defense_bonus: shield.defense_bonus,
+        block_chance: shield.block_chance.unwrap_or(DEFAULT_BLOCK_CHANCE)
+    }
+}
+
+fn get_mob_attributes(template: &super::mob_structs::MobAttributes) -> Attributes {
+    let attribute = |value: Option<i32>| {
+        let base = value.unwrap_or(11);
+        Attribute { base, modifiers: 0, bonus: attr_bonus(base) }
+    };
+    Attributes {
+        might: attribute(template.might),
+        fitness: attribute(template.fitness),
+        quickness: attribute(template.quickness),
+        intelligence: attribute(template.intelligence)
+    }
+}
+
+fn get_mob_skills(mob_template: &super::mob_structs::Mob) -> Skills {
+    let mut skills = Skills { skills: HashMap::new(), modifiers: HashMap::new(), practice: HashMap::new() };
+    skills.skills.insert(Skill::Melee, 1);
+    skills.skills.insert(Skill::Defense, 1);
+    skills.skills.insert(Skill::Magic, 1);
+    skills.skills.insert(Skill::Shield, 1);
+    if let Some(mob_skills) = &mob_template.skills {
+        for sk in mob_skills.iter() {
+            match string_to_skill(sk.0) {
+                Some(skill) => { skills.skills.insert(skill, *sk.1); }
+                None => { rltk::console::log(format!("Unknown skill references [{}]", sk.0)); }
+            }
+        }
+    }
+    skills
+}
+
+fn get_natural_attack_defense(na: &super::mob_structs::MobNatural) -> NaturalAttackDefense {
+    let mut nature = NaturalAttackDefense{
+        armor_class : na.armor_class,
+        attacks: Vec::new()
+    };
+    if let Some(attacks) = &na.attacks {
+        for nattack in attacks.iter() {
+            let attack = NaturalAttack{
+                name : nattack.name.clone(),
+                hit_bonus : nattack.hit_bonus,
+                damage : nattack.damage.clone(),
+                damage_type: nattack.damage_type.as_ref().and_then(|damage_type| string_to_damage_type(damage_type)).unwrap_or(DamageType::Bludgeoning)
+            };
+            nature.attacks.push(attack);
+        }
+    }
+    nature
+}
+
 fn get_renderable_component(renderable: &super::Renderable) -> crate::Renderable {
     crate::components::Renderable {
         glyph: rltk::to_cp437(renderable.glyph.chars().next().unwrap()),
@@ -396,28 +1048,6 @@ pub fn get_spawn_table_for_depth(raws: &RawMaster, depth: i32) -> RandomTable {
     rt
 }
 
-pub fn parse_dice_string(dice : &str) -> (i32, i32, i32) {
-    lazy_static! {
-        static ref DICE_RE : Regex = Regex::new(r"(\d+)d(\d+)([\+\-]\d+)?").unwrap();
-    }
-    let mut n_dice = 1;
-    let mut die_type = 4;
-    let mut die_bonus = 0;
-    for cap in DICE_RE.captures_iter(dice) {
-        if let Some(group) = cap.get(1) {
-            n_dice = group.as_str().parse::<i32>().expect("Not a digit");
-        }
-        if let Some(group) = cap.get(2) {
-            die_type = group.as_str().parse::<i32>().expect("Not a digit");
-        }
-        if let Some(group) = cap.get(3) {
-            die_bonus = group.as_str().parse::<i32>().expect("Not a digit");
-        }
-
-    }
-    (n_dice, die_type, die_bonus)
-}
-
 pub fn string_to_slot(slot : &str) -> EquipmentSlot {
     match slot {
         "Shield" => EquipmentSlot::Shield,
@@ -427,22 +1057,65 @@ pub fn string_to_slot(slot : &str) -> EquipmentSlot {
         "Feet" => EquipmentSlot::Feet,
         "Hands" => EquipmentSlot::Hands,
         "Melee" => EquipmentSlot::Melee,
+        "Ranged" => EquipmentSlot::Ranged,
+        "Quiver" => EquipmentSlot::Quiver,
         _ => { rltk::console::log(format!("Warning: unknown equipment slot type [{}])", slot)); EquipmentSlot::Melee }
     }
 }
 
+fn string_to_reaction(reaction : &str) -> Reaction {
+    match reaction {
+        "ignore" => Reaction::Ignore,
+        "flee" => Reaction::Flee,
+        _ => Reaction::Attack
+    }
+}
+
+pub(super) fn string_to_skill(skill : &str) -> Option<Skill> {
+    match skill {
+        "Melee" => Some(Skill::Melee),
+        "Defense" => Some(Skill::Defense),
+        "Magic" => Some(Skill::Magic),
+        "Shield" => Some(Skill::Shield),
+        _ => None
+    }
+}
+
+pub(super) fn string_to_damage_type(damage_type : &str) -> Option<DamageType> {
+    match damage_type {
+        "Slashing" => Some(DamageType::Slashing),
+        "Piercing" => Some(DamageType::Piercing),
+        "Bludgeoning" => Some(DamageType::Bludgeoning),
+        "Fire" => Some(DamageType::Fire),
+        "Cold" => Some(DamageType::Cold),
+        "Poison" => Some(DamageType::Poison),
+        _ => None
+    }
+}
+
 fn find_slot_for_equippable_item(tag : &str, raws: &RawMaster) -> EquipmentSlot {
     if !raws.item_index.contains_key(tag) {
         panic!("Trying to equip an unknown item: {}", tag);
     }
     let item_index = raws.item_index[tag];
-    let item = &raws.raws.items[item_index];
-    if let Some(_wpn) = &item.weapon {
-        return EquipmentSlot::Melee;
+    equippable_slot(&raws.raws.items[item_index])
+        .unwrap_or_else(|| panic!("Trying to equip {}, but it has no slot tag.", tag))
+}
+
+fn equippable_slot(item: &super::item_structs::Item) -> Option<EquipmentSlot> {
+    if let Some(wpn) = &item.weapon {
+        if wpn.range.parse::<i32>().is_ok() {
+            return Some(EquipmentSlot::Ranged);
+        }
+        return Some(EquipmentSlot::Melee);
+    } else if item.ammunition.is_some() {
+        return Some(EquipmentSlot::Quiver);
     } else if let Some(wearable) = &item.wearable {
-        return string_to_slot(&wearable.slot);
+        return Some(string_to_slot(&wearable.slot));
+    } else if item.shield.is_some() {
+        return Some(EquipmentSlot::Shield);
     }
-    panic!("Trying to equip {}, but it has no slot tag.", tag);
+    None
 }
 
 pub fn get_item_drop(raws: &RawMaster, rng: &mut RandomNumberGenerator, table: &str) -> Option<String> {
@@ -457,6 +1130,14 @@ pub fn get_item_drop(raws: &RawMaster, rng: &mut RandomNumberGenerator, table: &
     None
 }
 
+/// Like `faction_reaction`, but the target's own standing with `my_faction` takes precedence.
+pub fn faction_reaction_to(my_faction: &str, their_faction: &str, their_standing: Option<&FactionStanding>, raws: &RawMaster) -> Reaction {
+    if let Some(reaction) = their_standing.and_then(|standing| standing.reactions.get(my_faction)) {
+        return *reaction;
+    }
+    faction_reaction(my_faction, their_faction, raws)
+}
+
 pub fn faction_reaction(my_faction: &str, their_faction: &str, raws: &RawMaster) -> Reaction {
     if raws.faction_index.contains_key(my_faction) {
         let mf = &raws.faction_index[my_faction];
@@ -487,3 +1168,122 @@ pub fn get_vendor_items(categories: &[String], raws: &RawMaster) -> Vec<(String,
     
     results
 }
+
+pub fn hunger_duration(raws: &RawMaster, state: HungerState) -> i32 {
+    match &raws.raws.hunger_clock {
+        Some(clock) => match state {
+            HungerState::WellFed => clock.well_fed,
+            HungerState::Normal => clock.normal,
+            HungerState::Hungry => clock.hungry,
+            HungerState::Starving => clock.starving,
+        },
+        None => match state {
+            HungerState::WellFed => DEFAULT_FOOD_NUTRITION,
+            _ => DEFAULT_HUNGER_DURATION
+        }
+    }
+}
+
+pub fn get_background_list(raws: &RawMaster) -> Vec<(String, String)> {
+    raws.raws.backgrounds.iter().map(|b| (b.name.clone(), b.description.clone())).collect()
+}
+
+/// Applies a background's attribute adjustments, skills, gold, spells and faction standing
+/// to the player, and hands them its starting kit.
+pub fn apply_background(raws: &RawMaster, ecs: &mut World, player: Entity, key: &str) {
+    if !raws.background_index.contains_key(key) {
+        rltk::console::log(format!("Unknown background [{}]", key));
+        return;
+    }
+    let background = &raws.raws.backgrounds[raws.background_index[key]];
+
+    if let Some(adjust) = &background.attributes {
+        let mut attributes = ecs.write_storage::<Attributes>();
+        if let Some(attr) = attributes.get_mut(player) {
+            attr.might.base += adjust.might.unwrap_or(0);
+            attr.fitness.base += adjust.fitness.unwrap_or(0);
+            attr.quickness.base += adjust.quickness.unwrap_or(0);
+            attr.intelligence.base += adjust.intelligence.unwrap_or(0);
+        }
+    }
+
+    if let Some(background_skills) = &background.skills {
+        let mut skills = ecs.write_storage::<Skills>();
+        if let Some(skills) = skills.get_mut(player) {
+            for sk in background_skills.iter() {
+                match string_to_skill(sk.0) {
+                    Some(skill) => { skills.skills.insert(skill, *sk.1); }
+                    None => { rltk::console::log(format!("Unknown skill references [{}]", sk.0)); }
+                }
+            }
+        }
+    }
+
+    if let Some(gold) = &background.gold {
+        let mut pools = ecs.write_storage::<Pools>();
+        if let Some(pools) = pools.get_mut(player) {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            pools.gold = gold.roll(&mut rng) as f32;
+        }
+    }
+
+    if let Some(spells) = &background.spells {
+        let mut known_spells = ecs.write_storage::<KnownSpells>();
+        if let Some(known) = known_spells.get_mut(player) {
+            for spell in spells.iter().filter(|s| raws.spell_index.contains_key(*s)) {
+                known.spells.push(KnownSpell {
+                    display_name: spell.clone(),
+                    mana_cost: raws.raws.spells[raws.spell_index[spell]].mana_cost
+                });
+            }
+        }
+    }
+
+    if let Some(standing) = &background.faction_standing {
+        let reactions = standing.iter().map(|(faction, reaction)| (faction.clone(), string_to_reaction(reaction))).collect();
+        ecs.write_storage::<FactionStanding>().insert(player, FactionStanding { reactions }).expect("Unable to insert faction standing");
+    }
+
+    // The starting kit is always known to its owner
+    if let Some(equipped) = &background.equipped {
+        for item in equipped.iter() {
+            spawn_named_entity(raws, ecs, item, SpawnType::Equipped { by: player });
+            ecs.write_resource::<MasterDungeonMap>().identify(item);
+        }
+    }
+    if let Some(carried) = &background.carried {
+        for item in carried.iter() {
+            spawn_named_entity(raws, ecs, item, SpawnType::Carried { by: player });
+            ecs.write_resource::<MasterDungeonMap>().identify(item);
+        }
+    }
+}
+
+/// Every magic item's name along with its naming scheme: "scroll", "potion", or a fixed
+/// unidentified name.
+pub fn get_magic_item_namings(raws: &RawMaster) -> Vec<(String, String)> {
+    raws.raws.items.iter()
+        .filter_map(|item| item.magic.as_ref().map(|magic| (item.name.clone(), magic.naming.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_affix_can_be_rolled() {
+        let mut raws = RawMaster::empty();
+        raws.load(super::super::read_raws().expect("raws should load"));
+        let mut rng = RandomNumberGenerator::seeded(1);
+
+        for affix in raws.raws.affixes.iter() {
+            for kind in affix.applies_to.iter() {
+                let rolled = (0..2000)
+                    .filter_map(|_| roll_affix(&raws, &mut rng, &affix.position, kind, affix.min_depth))
+                    .any(|modifier| modifier.name == affix.name);
+                assert!(rolled, "{} {} never rolled for {}", affix.position, affix.name, kind);
+            }
+        }
+    }
+}
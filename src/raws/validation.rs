@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use serde_json::Value;
+use super::{Raws, Renderable};
+use super::rawmaster::{string_to_skill, string_to_damage_type, STATUS_EFFECT_KEYS, ITEM_EFFECT_KEYS, TRIGGER_EFFECT_KEYS, NUMERIC_EFFECT_KEYS};
+
+const SLOTS : [&str; 9] = ["Shield", "Head", "Torso", "Legs", "Feet", "Hands", "Melee", "Ranged", "Quiver"];
+const MOVEMENTS : [&str; 3] = ["static", "random", "random_waypoint"];
+const REACTIONS : [&str; 3] = ["ignore", "flee", "attack"];
+const AFFIX_POSITIONS : [&str; 2] = ["prefix", "suffix"];
+const AFFIX_KINDS : [&str; 2] = ["weapon", "wearable"];
+
+/// A single problem found in the raws, with the JSON path of the offending value.
+#[derive(Debug, Clone)]
+pub struct RawsError {
+    pub path : String,
+    pub message : String
+}
+
+impl RawsError {
+    pub fn new<S: ToString>(path: &str, message: S) -> RawsError {
+        RawsError { path: path.to_string(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for RawsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Path of a named entry in one of the top-level lists, e.g. `mobs["Bandit"]`.
+pub fn entry_path(section: &str, name: &str) -> String {
+    format!("{}[\"{}\"]", section, name)
+}
+
+/// Renders a deserialization path against the JSON it came from, naming list entries
+/// where they have a name so the path survives template resolution reordering things.
+pub fn describe_path(json: &Value, path: &serde_path_to_error::Path) -> String {
+    let mut result = String::new();
+    let mut current = Some(json);
+    for segment in path.iter() {
+        match segment {
+            serde_path_to_error::Segment::Seq { index } => {
+                current = current.and_then(|value| value.get(*index));
+                match current.and_then(|value| value.get("name")).and_then(Value::as_str) {
+                    Some(name) => result.push_str(&format!("[\"{}\"]", name)),
+                    None => result.push_str(&format!("[{}]", index))
+                }
+            }
+            serde_path_to_error::Segment::Map { key } => {
+                current = current.and_then(|value| value.get(key));
+                if !result.is_empty() { result.push('.'); }
+                result.push_str(key);
+            }
+            _ => {}
+        }
+    }
+    if result.is_empty() { "$".to_string() } else { result }
+}
+
+struct Validator<'a> {
+    raws : &'a Raws,
+    errors : Vec<RawsError>,
+    items : HashSet<&'a str>,
+    equippable : HashSet<&'a str>,
+    spawnable : HashSet<&'a str>,
+    loot_tables : HashSet<&'a str>,
+    factions : HashSet<&'a str>,
+    spells : HashSet<&'a str>,
+    ammo_types : HashSet<&'a str>,
+    vendor_categories : HashSet<&'a str>
+}
+
+/// Checks everything the spawning code would otherwise panic on or silently ignore,
+/// returning every problem found rather than stopping at the first.
+pub fn validate_raws(raws: &Raws) -> Vec<RawsError> {
+    let mut validator = Validator {
+        raws,
+        errors : Vec::new(),
+        items : raws.items.iter().map(|i| i.name.as_str()).collect(),
+        equippable : raws.items.iter()
+            .filter(|i| i.weapon.is_some() || i.wearable.is_some() || i.ammunition.is_some() || i.shield.is_some())
+            .map(|i| i.name.as_str()).collect(),
+        spawnable : raws.items.iter().map(|i| i.name.as_str())
+            .chain(raws.mobs.iter().map(|m| m.name.as_str()))
+            .chain(raws.props.iter().map(|p| p.name.as_str())).collect(),
+        loot_tables : raws.loot_table.iter().map(|l| l.name.as_str()).collect(),
+        factions : raws.faction_table.iter().map(|f| f.name.as_str()).collect(),
+        spells : raws.spells.iter().map(|s| s.name.as_str()).collect(),
+        ammo_types : raws.items.iter().filter_map(|i| i.ammunition.as_ref()).map(|a| a.ammo_type.as_str()).collect(),
+        vendor_categories : raws.items.iter().filter_map(|i| i.vendor_category.as_deref()).collect()
+    };
+
+    validator.check_duplicates();
+    validator.check_items();
+    validator.check_mobs();
+    validator.check_props();
+    validator.check_spawn_table();
+    validator.check_loot_tables();
+    validator.check_factions();
+    validator.check_spells();
+    validator.check_backgrounds();
+    validator.check_affixes();
+    validator.check_hunger_clock();
+    validator.errors
+}
+
+impl<'a> Validator<'a> {
+    fn error<S: ToString>(&mut self, path: &str, message: S) {
+        self.errors.push(RawsError::new(path, message));
+    }
+
+    fn check_duplicates(&mut self) {
+        let mut seen : HashMap<&str, &str> = HashMap::new();
+        let sections = [
+            ("items", self.raws.items.iter().map(|i| i.name.as_str()).collect::<Vec<&str>>()),
+            ("mobs", self.raws.mobs.iter().map(|m| m.name.as_str()).collect()),
+            ("props", self.raws.props.iter().map(|p| p.name.as_str()).collect())
+        ];
+        for (section, names) in sections.iter() {
+            for name in names.iter() {
+                if let Some(first) = seen.get(name) {
+                    let message = format!("duplicate name, already used in {}", first);
+                    self.error(&entry_path(section, name), message);
+                } else {
+                    seen.insert(name, section);
+                }
+            }
+        }
+    }
+
+    fn check_renderable(&mut self, path: &str, renderable: &Option<Renderable>) {
+        if let Some(renderable) = renderable {
+            if renderable.glyph.is_empty() {
+                self.error(&format!("{}.renderable.glyph", path), "glyph is empty");
+            }
+            for (field, color) in [("fg", &renderable.fg), ("bg", &renderable.bg)] {
+                self.check_color(&format!("{}.renderable.{}", path, field), color);
+            }
+        }
+    }
+
+    fn check_color(&mut self, path: &str, color: &str) {
+        if rltk::RGB::from_hex(color).is_err() {
+            self.error(path, format!("\"{}\" is not a #RRGGBB color", color));
+        }
+    }
+
+    fn check_skills(&mut self, path: &str, skills: &Option<HashMap<String, i32>>) {
+        for skill in skills.iter().flat_map(|s| s.keys()) {
+            if string_to_skill(skill).is_none() {
+                self.error(&format!("{}.skills.{}", path, skill), format!("unknown skill \"{}\"", skill));
+            }
+        }
+    }
+
+    fn check_effects(&mut self, path: &str, effects: &HashMap<String, String>, allowed: &[&str]) {
+        for (key, value) in effects.iter() {
+            let effect_path = format!("{}.{}", path, key);
+            if !allowed.contains(&key.as_str()) && !STATUS_EFFECT_KEYS.contains(&key.as_str()) {
+                self.error(&effect_path, format!("unknown effect \"{}\"", key));
+            } else if NUMERIC_EFFECT_KEYS.contains(&key.as_str()) && value.parse::<i32>().is_err() {
+                self.error(&effect_path, format!("\"{}\" is not a number", value));
+            } else if key == "teach_spell" && !self.spells.contains(value.as_str()) {
+                self.error(&effect_path, format!("unknown spell \"{}\"", value));
+            } else if key == "damage_type" {
+                self.check_damage_type(&effect_path, value);
+            }
+        }
+    }
+
+    fn check_damage_type(&mut self, path: &str, damage_type: &str) {
+        if string_to_damage_type(damage_type).is_none() {
+            self.error(path, format!("unknown damage type \"{}\"", damage_type));
+        }
+    }
+
+    fn check_resistances(&mut self, path: &str, resistances: &Option<Vec<String>>, vulnerabilities: &Option<Vec<String>>) {
+        for (i, damage_type) in resistances.iter().flatten().enumerate() {
+            self.check_damage_type(&format!("{}.resistances[{}]", path, i), damage_type);
+        }
+        for (i, damage_type) in vulnerabilities.iter().flatten().enumerate() {
+            self.check_damage_type(&format!("{}.vulnerabilities[{}]", path, i), damage_type);
+        }
+    }
+
+    fn check_items_exist(&mut self, path: &str, names: &Option<Vec<String>>, must_equip: bool) {
+        for (i, name) in names.iter().flatten().enumerate() {
+            let item_path = format!("{}[{}]", path, i);
+            if !self.items.contains(name.as_str()) {
+                self.error(&item_path, format!("unknown item \"{}\"", name));
+            } else if must_equip && !self.equippable.contains(name.as_str()) {
+                self.error(&item_path, format!("\"{}\" cannot be equipped", name));
+            }
+        }
+    }
+
+    fn check_spells_exist(&mut self, path: &str, spells: &Option<Vec<String>>) {
+        for (i, spell) in spells.iter().flatten().enumerate() {
+            if !self.spells.contains(spell.as_str()) {
+                self.error(&format!("{}[{}]", path, i), format!("unknown spell \"{}\"", spell));
+            }
+        }
+    }
+
+    fn check_reaction(&mut self, path: &str, faction: &str, reaction: &str) {
+        if faction != "Default" && faction != "Player" && !self.factions.contains(faction) {
+            self.error(path, format!("unknown faction \"{}\"", faction));
+        }
+        if !REACTIONS.contains(&reaction) {
+            self.error(path, format!("\"{}\" is not one of ignore, flee or attack", reaction));
+        }
+    }
+
+    fn check_items(&mut self) {
+        let raws = self.raws;
+        for item in raws.items.iter() {
+            let path = entry_path("items", &item.name);
+            self.check_renderable(&path, &item.renderable);
+            if let Some(consumable) = &item.consumable {
+                self.check_effects(&format!("{}.consumable.effects", path), &consumable.effects, &ITEM_EFFECT_KEYS);
+            }
+            if let Some(weapon) = &item.weapon {
+                if weapon.range != "melee" && weapon.range.parse::<i32>().is_err() {
+                    self.error(&format!("{}.weapon.range", path), format!("\"{}\" is neither \"melee\" nor a number", weapon.range));
+                }
+                if weapon.attribute != "Might" && weapon.attribute != "Quickness" {
+                    self.error(&format!("{}.weapon.attribute", path), format!("\"{}\" is not Might or Quickness", weapon.attribute));
+                }
+                if let Some(damage_type) = &weapon.damage_type {
+                    self.check_damage_type(&format!("{}.weapon.damage_type", path), damage_type);
+                }
+                if weapon.critical_multiplier.is_some_and(|multiplier| multiplier < 1) {
+                    self.error(&format!("{}.weapon.critical_multiplier", path), "critical multiplier must be at least 1");
+                }
+                if weapon.two_handed.unwrap_or(false) && weapon.range != "melee" {
+                    self.error(&format!("{}.weapon.two_handed", path), "only melee weapons can be two-handed");
+                }
+                if let Some(ammo_type) = &weapon.ammunition {
+                    if !self.ammo_types.contains(ammo_type.as_str()) {
+                        self.error(&format!("{}.weapon.ammunition", path), format!("no item provides ammunition \"{}\"", ammo_type));
+                    }
+                }
+            }
+            if let Some(wearable) = &item.wearable {
+                if !SLOTS.contains(&wearable.slot.as_str()) {
+                    self.error(&format!("{}.wearable.slot", path), format!("unknown equipment slot \"{}\"", wearable.slot));
+                }
+                if wearable.damage_reduction.is_some_and(|reduction| reduction < 0) {
+                    self.error(&format!("{}.wearable.damage_reduction", path), "must not be negative");
+                }
+                self.check_resistances(&format!("{}.wearable", path), &wearable.resistances, &wearable.vulnerabilities);
+            }
+            if let Some(shield) = &item.shield {
+                if item.wearable.is_some() || item.weapon.is_some() {
+                    self.error(&format!("{}.shield", path), "a shield can't also be a weapon or wearable");
+                }
+                if shield.block_chance.is_some_and(|chance| !(0..=100).contains(&chance)) {
+                    self.error(&format!("{}.shield.block_chance", path), "block chance must be between 0 and 100");
+                }
+            }
+            if item.use_turns.is_some_and(|turns| turns < 1) {
+                self.error(&format!("{}.use_turns", path), "must be at least 1");
+            }
+            self.check_skills(&path, &item.skills);
+            if item.magic.as_ref().is_some_and(|magic| magic.naming.trim().is_empty()) {
+                self.error(&format!("{}.magic.naming", path), "expected \"scroll\", \"potion\" or an unidentified name");
+            }
+        }
+    }
+
+    fn check_mobs(&mut self) {
+        let raws = self.raws;
+        for mob in raws.mobs.iter() {
+            let path = entry_path("mobs", &mob.name);
+            self.check_renderable(&path, &mob.renderable);
+            if !MOVEMENTS.contains(&mob.movement.as_str()) {
+                self.error(&format!("{}.movement", path), format!("unknown movement \"{}\"", mob.movement));
+            }
+            self.check_skills(&path, &mob.skills);
+            if mob.level.is_some_and(|level| level < 1) {
+                self.error(&format!("{}.level", path), "level must be at least 1");
+            }
+            self.check_items_exist(&format!("{}.equipped", path), &mob.equipped, true);
+            if let Some(natural) = &mob.natural {
+                for (i, attack) in natural.attacks.iter().flatten().enumerate() {
+                    if let Some(damage_type) = &attack.damage_type {
+                        self.check_damage_type(&format!("{}.natural.attacks[{}].damage_type", path, i), damage_type);
+                    }
+                }
+            }
+            if let Some(loot) = &mob.loot_table {
+                if !self.loot_tables.contains(loot.as_str()) {
+                    self.error(&format!("{}.loot_table", path), format!("unknown loot table \"{}\"", loot));
+                }
+            }
+            if let Some(light) = &mob.light {
+                self.check_color(&format!("{}.light.color", path), &light.color);
+            }
+            if let Some(faction) = &mob.faction {
+                if !self.factions.contains(faction.as_str()) {
+                    self.error(&format!("{}.faction", path), format!("unknown faction \"{}\"", faction));
+                }
+            }
+            for (i, category) in mob.vendor.iter().flatten().enumerate() {
+                if !self.vendor_categories.contains(category.as_str()) {
+                    self.error(&format!("{}.vendor[{}]", path, i), format!("no items are sold in category \"{}\"", category));
+                }
+            }
+            if mob.temple.as_ref().is_some_and(|temple| temple.remove_curse_cost < 0.0) {
+                self.error(&format!("{}.temple.remove_curse_cost", path), "must not be negative");
+            }
+            self.check_spells_exist(&format!("{}.spells", path), &mob.spells);
+            self.check_resistances(&path, &mob.resistances, &mob.vulnerabilities);
+        }
+    }
+
+    fn check_props(&mut self) {
+        let raws = self.raws;
+        for prop in raws.props.iter() {
+            let path = entry_path("props", &prop.name);
+            self.check_renderable(&path, &prop.renderable);
+            if let Some(trigger) = &prop.entry_trigger {
+                self.check_effects(&format!("{}.entry_trigger.effects", path), &trigger.effects, &TRIGGER_EFFECT_KEYS);
+            }
+        }
+    }
+
+    fn check_spawn_table(&mut self) {
+        let raws = self.raws;
+        for (i, spawn) in raws.spawn_table.iter().enumerate() {
+            let path = format!("spawn_table[{}]", i);
+            if !self.spawnable.contains(spawn.name.as_str()) {
+                self.error(&format!("{}.name", path), format!("unknown item, mob or prop \"{}\"", spawn.name));
+            }
+            if spawn.weight < 1 {
+                self.error(&format!("{}.weight", path), "weight must be at least 1");
+            }
+            if spawn.min_depth < 0 {
+                self.error(&format!("{}.min_depth", path), "depth cannot be negative");
+            }
+            if spawn.max_depth < spawn.min_depth {
+                self.error(&format!("{}.max_depth", path), format!("max_depth {} is below min_depth {}", spawn.max_depth, spawn.min_depth));
+            }
+        }
+    }
+
+    fn check_loot_tables(&mut self) {
+        let raws = self.raws;
+        for table in raws.loot_table.iter() {
+            let path = entry_path("loot_table", &table.name);
+            for (i, drop) in table.drops.iter().enumerate() {
+                if !self.items.contains(drop.name.as_str()) {
+                    self.error(&format!("{}.drops[{}].name", path, i), format!("unknown item \"{}\"", drop.name));
+                }
+                if drop.weight < 1 {
+                    self.error(&format!("{}.drops[{}].weight", path, i), "weight must be at least 1");
+                }
+            }
+        }
+    }
+
+    fn check_factions(&mut self) {
+        let raws = self.raws;
+        for faction in raws.faction_table.iter() {
+            let path = entry_path("faction_table", &faction.name);
+            for (other, reaction) in faction.responses.iter() {
+                self.check_reaction(&format!("{}.responses.{}", path, other), other, reaction);
+            }
+        }
+    }
+
+    fn check_spells(&mut self) {
+        let raws = self.raws;
+        for spell in raws.spells.iter() {
+            let path = entry_path("spells", &spell.name);
+            if spell.mana_cost < 0 {
+                self.error(&format!("{}.mana_cost", path), "mana cost cannot be negative");
+            }
+            self.check_effects(&format!("{}.effects", path), &spell.effects, &ITEM_EFFECT_KEYS);
+        }
+    }
+
+    fn check_backgrounds(&mut self) {
+        let raws = self.raws;
+        for background in raws.backgrounds.iter() {
+            let path = entry_path("backgrounds", &background.name);
+            self.check_skills(&path, &background.skills);
+            self.check_items_exist(&format!("{}.equipped", path), &background.equipped, true);
+            self.check_items_exist(&format!("{}.carried", path), &background.carried, false);
+            self.check_spells_exist(&format!("{}.spells", path), &background.spells);
+            for (faction, reaction) in background.faction_standing.iter().flatten() {
+                self.check_reaction(&format!("{}.faction_standing.{}", path, faction), faction, reaction);
+            }
+        }
+    }
+
+    fn check_affixes(&mut self) {
+        let raws = self.raws;
+        let mut seen : HashSet<(&str, &str)> = HashSet::new();
+        for affix in raws.affixes.iter() {
+            let path = entry_path("affixes", &affix.name);
+            if !seen.insert((affix.name.as_str(), affix.position.as_str())) {
+                self.error(&path, format!("duplicate {} name", affix.position));
+            }
+            if !AFFIX_POSITIONS.contains(&affix.position.as_str()) {
+                self.error(&format!("{}.position", path), format!("\"{}\" is not prefix or suffix", affix.position));
+            }
+            if affix.applies_to.is_empty() {
+                self.error(&format!("{}.applies_to", path), "must list at least one of weapon or wearable");
+            }
+            for (i, kind) in affix.applies_to.iter().enumerate() {
+                if !AFFIX_KINDS.contains(&kind.as_str()) {
+                    self.error(&format!("{}.applies_to[{}]", path, i), format!("\"{}\" is not weapon or wearable", kind));
+                }
+            }
+            if affix.weight < 1 {
+                self.error(&format!("{}.weight", path), "weight must be at least 1");
+            }
+            let multipliers = [("weight_multiplier", affix.weight_multiplier), ("value_multiplier", affix.value_multiplier)];
+            for (field, multiplier) in multipliers {
+                if multiplier.is_some_and(|m| m <= 0.0) {
+                    self.error(&format!("{}.{}", path, field), "multiplier must be positive");
+                }
+            }
+        }
+    }
+
+    fn check_hunger_clock(&mut self) {
+        if let Some(hunger) = &self.raws.hunger_clock {
+            let durations = [("well_fed", hunger.well_fed), ("normal", hunger.normal), ("hungry", hunger.hungry), ("starving", hunger.starving)];
+            for (field, duration) in durations {
+                if duration < 1 {
+                    self.error(&format!("hunger_clock.{}", field), "duration must be at least 1 turn");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Validates `sections` on top of otherwise empty raws.
+    fn errors(sections: Value) -> Vec<String> {
+        let mut json = json!({ "items": [], "mobs": [], "props": [], "spawn_table": [], "loot_table": [],
+            "faction_table": [], "spells": [], "backgrounds": [], "affixes": [] });
+        for (section, value) in sections.as_object().unwrap() {
+            json[section.as_str()] = value.clone();
+        }
+        let raws : Raws = serde_json::from_value(json).unwrap();
+        validate_raws(&raws).iter().map(|error| error.to_string()).collect()
+    }
+
+    fn mob(name: &str, extra: Value) -> Value {
+        let mut mob = json!({ "name": name, "blocks_tile": true, "vision_range": 8, "movement": "static", "attributes": {} });
+        for (key, value) in extra.as_object().unwrap() {
+            mob[key.as_str()] = value.clone();
+        }
+        mob
+    }
+
+    #[test]
+    fn valid_raws_have_no_errors() {
+        assert!(errors(json!({
+            "items": [
+                { "name": "Dagger", "weapon": { "range": "melee", "attribute": "Quickness", "base_damage": "1d4", "hit_bonus": 0 } },
+                { "name": "Rations", "consumable": { "effects": { "food": "" } }, "use_turns": 3 }
+            ],
+            "mobs": [ mob("Bandit", json!({ "skills": { "Melee": 2 }, "equipped": ["Dagger"], "loot_table": "Scraps", "faction": "Bandits" })) ],
+            "loot_table": [ { "name": "Scraps", "drops": [ { "name": "Rations", "weight": 1 } ] } ],
+            "faction_table": [ { "name": "Bandits", "responses": { "Default": "attack", "Bandits": "ignore" } } ],
+            "spawn_table": [ { "name": "Bandit", "weight": 5, "min_depth": 1, "max_depth": 4 } ]
+        })).is_empty());
+    }
+
+    #[test]
+    fn mob_problems_name_the_mob_and_field() {
+        assert_eq!(errors(json!({
+            "items": [ { "name": "Rations" } ],
+            "mobs": [ mob("Bandit", json!({ "skills": { "X": 1 }, "movement": "teleport", "level": 0,
+                "equipped": ["Rations", "Axe"], "loot_table": "Hoard", "faction": "Pirates" })) ]
+        })), vec![
+            "mobs[\"Bandit\"].movement: unknown movement \"teleport\"",
+            "mobs[\"Bandit\"].skills.X: unknown skill \"X\"",
+            "mobs[\"Bandit\"].level: level must be at least 1",
+            "mobs[\"Bandit\"].equipped[0]: \"Rations\" cannot be equipped",
+            "mobs[\"Bandit\"].equipped[1]: unknown item \"Axe\"",
+            "mobs[\"Bandit\"].loot_table: unknown loot table \"Hoard\"",
+            "mobs[\"Bandit\"].faction: unknown faction \"Pirates\""
+        ]);
+    }
+
+    #[test]
+    fn item_problems_name_the_item_and_field() {
+        // Effects are a map, so their problems can come in any order
+        let mut found = errors(json!({ "items": [
+            { "name": "Bow", "weapon": { "range": "far", "attribute": "Luck", "base_damage": "1d6", "hit_bonus": 0,
+                "two_handed": true, "ammunition": "Arrow" } },
+            { "name": "Potion", "consumable": { "effects": { "provides_healing": "lots", "sparkle": "1", "teach_spell": "Zap" } } },
+            { "name": "Plate", "wearable": { "armor_class": 3.0, "slot": "Back", "resistances": ["Acid"] }, "use_turns": 0 }
+        ]}));
+        found.sort();
+        assert_eq!(found, vec![
+            "items[\"Bow\"].weapon.ammunition: no item provides ammunition \"Arrow\"",
+            "items[\"Bow\"].weapon.attribute: \"Luck\" is not Might or Quickness",
+            "items[\"Bow\"].weapon.range: \"far\" is neither \"melee\" nor a number",
+            "items[\"Bow\"].weapon.two_handed: only melee weapons can be two-handed",
+            "items[\"Plate\"].use_turns: must be at least 1",
+            "items[\"Plate\"].wearable.resistances[0]: unknown damage type \"Acid\"",
+            "items[\"Plate\"].wearable.slot: unknown equipment slot \"Back\"",
+            "items[\"Potion\"].consumable.effects.provides_healing: \"lots\" is not a number",
+            "items[\"Potion\"].consumable.effects.sparkle: unknown effect \"sparkle\"",
+            "items[\"Potion\"].consumable.effects.teach_spell: unknown spell \"Zap\""
+        ]);
+    }
+
+    #[test]
+    fn duplicates_and_table_references_are_checked() {
+        assert_eq!(errors(json!({
+            "items": [ { "name": "Rat" } ],
+            "mobs": [ mob("Rat", json!({})) ],
+            "spawn_table": [ { "name": "Ghost", "weight": 0, "min_depth": 3, "max_depth": 2 } ],
+            "loot_table": [ { "name": "Scraps", "drops": [ { "name": "Bone", "weight": 1 } ] } ]
+        })), vec![
+            "mobs[\"Rat\"]: duplicate name, already used in items",
+            "spawn_table[0].name: unknown item, mob or prop \"Ghost\"",
+            "spawn_table[0].weight: weight must be at least 1",
+            "spawn_table[0].max_depth: max_depth 2 is below min_depth 3",
+            "loot_table[\"Scraps\"].drops[0].name: unknown item \"Bone\""
+        ]);
+    }
+
+    #[test]
+    fn deserialization_paths_name_list_entries() {
+        #[derive(serde::Deserialize)]
+        struct Entry { #[allow(dead_code)] level: i32 }
+        #[derive(serde::Deserialize)]
+        struct Sections { #[allow(dead_code)] mobs: Vec<Entry> }
+
+        let json = json!({ "mobs": [ { "name": "Rat", "level": 1 }, { "name": "Bandit", "level": "high" } ] });
+        let error = serde_path_to_error::deserialize::<_, Sections>(&json).err().unwrap();
+        assert_eq!(describe_path(&json, error.path()), "mobs[\"Bandit\"].level");
+
+        let json = json!({ "mobs": [ { "level": 1 }, { "level": "high" } ] });
+        let error = serde_path_to_error::deserialize::<_, Sections>(&json).err().unwrap();
+        assert_eq!(describe_path(&json, error.path()), "mobs[1].level");
+
+        let json = json!([]);
+        let error = serde_path_to_error::deserialize::<_, Sections>(&json).err().unwrap();
+        assert_eq!(describe_path(&json, error.path()), "$");
+    }
+}
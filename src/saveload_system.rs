@@ -12,7 +12,11 @@ use crate::{Position, SerializeMe, Renderable, Player, Viewshed, Name, BlocksTil
             WantsToRemoveItem, BlocksVisibility, Door, Hidden, Quips, Attributes,
             Skills, Pools, NaturalAttackDefense, LootTable, OtherLevelPosition,
             DMSerializationHelper, LightSource, Initiative, MyTurn, Faction, WantsToApproach, WantsToFlee,
-            MoveMode, EquipmentChanged, Vendor, ParticleLifetime, HungerClock, ProvidesFood};
+            MoveMode, EquipmentChanged, Vendor, ParticleLifetime, HungerClock, ProvidesFood,
+            EntryTrigger, SingleActivation, RandomTeleport, MagicMapper, RangedWeapon, Ammunition,
+            WantsToShoot, SpellTemplate, KnownSpells, WantsToCastSpell, TeachesSpell, StatusEffect, Duration,
+            DamageOverTime, HealOverTime, Slow, AttributeBonus, InflictsStatus, SkillBonus, FactionStanding, PendingLevelUp,
+            CursedItem, ProvidesRemoveCurse, Temple, TwoHanded, Shield, Resistances, TookAction, UseTurns, ActionQueue, GeneratedItem};
 
 macro_rules! serialize_individually {
     ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
@@ -54,7 +58,11 @@ pub fn save_game(ecs: &mut World) {
             WantsToRemoveItem, BlocksVisibility, Door, Hidden, Quips, Attributes,
             Skills, Pools, NaturalAttackDefense, LootTable, OtherLevelPosition,
             DMSerializationHelper, LightSource, Initiative, MyTurn, Faction, WantsToApproach, WantsToFlee,
-            MoveMode, EquipmentChanged, Vendor, ParticleLifetime, HungerClock, ProvidesFood
+            MoveMode, EquipmentChanged, Vendor, ParticleLifetime, HungerClock, ProvidesFood,
+            EntryTrigger, SingleActivation, RandomTeleport, MagicMapper, RangedWeapon, Ammunition,
+            WantsToShoot, SpellTemplate, KnownSpells, WantsToCastSpell, TeachesSpell, StatusEffect, Duration,
+            DamageOverTime, HealOverTime, Slow, AttributeBonus, InflictsStatus, SkillBonus, FactionStanding, PendingLevelUp,
+            CursedItem, ProvidesRemoveCurse, Temple, TwoHanded, Shield, Resistances, TookAction, UseTurns, ActionQueue, GeneratedItem
         );
     }
     ecs.delete_entity(savehelper).expect("Crash on cleanup");
@@ -110,7 +118,11 @@ pub fn load_game(ecs: &mut World) {
             WantsToRemoveItem, BlocksVisibility, Door, Hidden, Quips, Attributes,
             Skills, Pools, NaturalAttackDefense, LootTable, OtherLevelPosition,
             DMSerializationHelper, LightSource, Initiative, MyTurn, Faction, WantsToApproach, WantsToFlee,
-            MoveMode, EquipmentChanged, Vendor, ParticleLifetime, HungerClock, ProvidesFood
+            MoveMode, EquipmentChanged, Vendor, ParticleLifetime, HungerClock, ProvidesFood,
+            EntryTrigger, SingleActivation, RandomTeleport, MagicMapper, RangedWeapon, Ammunition,
+            WantsToShoot, SpellTemplate, KnownSpells, WantsToCastSpell, TeachesSpell, StatusEffect, Duration,
+            DamageOverTime, HealOverTime, Slow, AttributeBonus, InflictsStatus, SkillBonus, FactionStanding, PendingLevelUp,
+            CursedItem, ProvidesRemoveCurse, Temple, TwoHanded, Shield, Resistances, TookAction, UseTurns, ActionQueue, GeneratedItem
         );
     }
 
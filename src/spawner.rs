@@ -3,21 +3,23 @@ use std::collections::HashMap;
 use rltk::{ RGB, RandomNumberGenerator };
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
-use crate::{EquipmentChanged, HungerState, HungerClock, Item, ProvidesFood, Consumable};
+use crate::{EquipmentChanged, HungerState, HungerClock, Item, ProvidesFood, Consumable, KnownSpells};
 use crate::{Map, TileType, raws::get_spawn_table_for_depth, Attributes, Attribute, Skills, Skill, Pool, Pools, LightSource, Initiative, Faction};
 
 use super::{
     Player, Renderable, Name, Position, Rect, Viewshed, SerializeMe, RandomTable, raws::*
 };
+use crate::gui::CharacterSheet;
 use crate::gamesystem::{attr_bonus, player_hp_at_level, mana_at_level};
 
 const MAX_MONSTERS: i32 = 4;
 
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-    let mut skills = Skills { skills: HashMap::new() };
+    let mut skills = Skills { skills: HashMap::new(), modifiers: HashMap::new(), practice: HashMap::new() };
     skills.skills.insert(Skill::Melee, 1);
     skills.skills.insert(Skill::Defense, 1);
     skills.skills.insert(Skill::Magic, 1);
+    skills.skills.insert(Skill::Shield, 1);
 
     let player = ecs
         .create_entity()
@@ -54,22 +56,48 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         })
         .with(skills)
         .with(LightSource { color: RGB::from_f32(1.0, 1.0, 0.5), range: 8})
-        .with(Initiative { current: 0 })
+        .with(Initiative { energy: 0 })
         .with(Faction { name: "Player".to_string() })
         .with(EquipmentChanged {})
-        .with(HungerClock { state: HungerState::WellFed, duration: 20 })
+        .with(HungerClock { state: HungerState::WellFed, duration: hunger_duration(&RAWS.lock().unwrap(), HungerState::WellFed) })
+        .with(KnownSpells { spells: Vec::new() })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
-    // Starting equipment
-    spawn_named_entity(&RAWS.lock().unwrap(), ecs, "Rusty Longsword", SpawnType::Equipped{by : player});
-    spawn_named_entity(&RAWS.lock().unwrap(), ecs, "Stained Tunic", SpawnType::Equipped{by : player});
-    spawn_named_entity(&RAWS.lock().unwrap(), ecs, "Torn Trousers", SpawnType::Equipped{by : player});
-    spawn_named_entity(&RAWS.lock().unwrap(), ecs, "Old Boots", SpawnType::Equipped{by : player});
-
     player
 }
 
+/// Applies the choices from character creation to the already-spawned player: name,
+/// attributes, and everything the chosen background brings with it.
+pub fn apply_character(ecs: &mut World, sheet: &CharacterSheet) {
+    let player = *ecs.fetch::<Entity>();
+    ecs.write_storage::<Name>().insert(player, Name { name: sheet.name.clone() }).expect("Unable to insert name");
+    {
+        let mut attributes = ecs.write_storage::<Attributes>();
+        let attr = attributes.get_mut(player).unwrap();
+        attr.might.base = sheet.attributes[0];
+        attr.fitness.base = sheet.attributes[1];
+        attr.quickness.base = sheet.attributes[2];
+        attr.intelligence.base = sheet.attributes[3];
+    }
+
+    apply_background(&RAWS.lock().unwrap(), ecs, player, &sheet.background);
+
+    let mut attributes = ecs.write_storage::<Attributes>();
+    let mut pools = ecs.write_storage::<Pools>();
+    let attr = attributes.get_mut(player).unwrap();
+    let pools = pools.get_mut(player).unwrap();
+    for attribute in [&mut attr.might, &mut attr.fitness, &mut attr.quickness, &mut attr.intelligence] {
+        attribute.bonus = attr_bonus(attribute.base + attribute.modifiers);
+    }
+    pools.hit_points.max = player_hp_at_level(attr.fitness.base, pools.level);
+    pools.hit_points.current = pools.hit_points.max;
+    pools.mana.max = mana_at_level(attr.intelligence.base, pools.level);
+    pools.mana.current = pools.mana.max;
+
+    ecs.write_storage::<EquipmentChanged>().insert(player, EquipmentChanged {}).expect("Unable to insert");
+}
+
 #[allow(clippy::map_entry)]
 pub fn spawn_room(map: &Map, rng: &mut RandomNumberGenerator, room: &Rect, map_depth: i32, spawn_list: &mut Vec<(usize, String)>) {
     let mut possible_targets : Vec<usize> = Vec::new();
@@ -116,12 +144,10 @@ pub fn spawn_entity(ecs: &mut World, spawn: &(&usize, &String)) {
     let y = (*spawn.0 / width) as i32;
     std::mem::drop(map);
 
-    let spawn_result = spawn_named_entity(
-        &RAWS.lock().unwrap(), 
-        ecs,
-        &spawn.1, 
-        SpawnType::AtPosition{ x, y }
-    );
+    let map_depth = ecs.fetch::<Map>().depth;
+    let raws = &RAWS.lock().unwrap();
+    let spawn_result = spawn_generated_item(raws, ecs, spawn.1, SpawnType::AtPosition{ x, y }, map_depth)
+        .or_else(|| spawn_named_entity(raws, ecs, spawn.1, SpawnType::AtPosition{ x, y }));
     if spawn_result.is_some() {
         return;
     }
@@ -0,0 +1,134 @@
+use specs::prelude::*;
+use crate::gamesystem::{spell_succeeds, skill_bonus, practice_skill};
+use crate::{KnownSpells, KnownSpell, SpellTemplate, WantsToCastSpell, Skill, Skills, Attributes, AreaOfEffect, Map};
+use crate::effects::{add_effect, item_targets, EffectType, Targets};
+use super::{Pools, Name, gamelog::GameLog};
+
+pub struct SpellCastSystem {}
+
+impl<'a> System<'a> for SpellCastSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToCastSpell>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, KnownSpells>,
+        ReadStorage<'a, SpellTemplate>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Pools>,
+        WriteStorage<'a, Skills>,
+        ReadStorage<'a, Attributes>,
+        WriteExpect<'a, rltk::RandomNumberGenerator>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            mut log,
+            mut wants_cast,
+            map,
+            aoe,
+            known_spells,
+            spell_templates,
+            names,
+            mut pools,
+            mut skills,
+            attributes,
+            mut rng,
+        ) = data;
+
+        for (entity, cast, known, caster_name) in (&entities, &wants_cast, &known_spells, &names).join() {
+            let spell_template = spell_templates.get(cast.spell);
+            let spell_name = names.get(cast.spell);
+            if spell_template.is_none() || spell_name.is_none() { continue; }
+            let spell_template = spell_template.unwrap();
+            let spell_name = spell_name.unwrap();
+
+            // You can only cast what you know
+            if !known.spells.iter().any(|s| s.display_name == spell_name.name) { continue; }
+
+            let caster_pools = pools.get_mut(entity);
+            if caster_pools.is_none() { continue; }
+            let caster_pools = caster_pools.unwrap();
+            if caster_pools.mana.current < spell_template.mana_cost {
+                if entity == *player_entity {
+                    log.entries.push("You don't have enough mana to cast that!".to_string());
+                }
+                continue;
+            }
+            caster_pools.mana.current -= spell_template.mana_cost;
+
+            let magic_bonus = skills.get(entity).map_or(0, |s| skill_bonus(Skill::Magic, s))
+                + attributes.get(entity).map_or(0, |a| a.intelligence.bonus);
+            let natural_roll = rng.roll_dice(1, 20);
+            if spell_succeeds(natural_roll, magic_bonus, spell_template.mana_cost) {
+                log.entries.push(format!("{} casts {}!", caster_name.name, spell_name.name));
+                if entity == *player_entity {
+                    if let Some(level) = skills.get_mut(entity).and_then(|s| practice_skill(s, Skill::Magic)) {
+                        log.entries.push(format!("Your {:?} skill improves to {}!", Skill::Magic, level));
+                    }
+                }
+                add_effect(
+                    Some(entity),
+                    EffectType::ItemUse { item: cast.spell },
+                    item_targets(&map, entity, cast.target, aoe.get(cast.spell))
+                );
+            } else {
+                log.entries.push(format!("{}'s {} fizzles.", caster_name.name, spell_name.name));
+                add_effect(None, EffectType::Particle {
+                    glyph: rltk::to_cp437('*'),
+                    fg: rltk::RGB::named(rltk::GRAY),
+                    bg: rltk::RGB::named(rltk::BLACK),
+                    lifespan: 200.0
+                }, Targets::Single { target: entity });
+            }
+        }
+
+        wants_cast.clear();
+    }
+}
+
+pub fn find_spell_entity(ecs: &World, name: &str) -> Option<Entity> {
+    let names = ecs.read_storage::<Name>();
+    let spell_templates = ecs.read_storage::<SpellTemplate>();
+    let entities = ecs.entities();
+
+    for (entity, spell_name, _template) in (&entities, &names, &spell_templates).join() {
+        if spell_name.name == name {
+            return Some(entity);
+        }
+    }
+    None
+}
+
+/// Teaches `learner` the named spell. Returns false, teaching nothing, if there is no such
+/// spell, the learner can't learn spells or already knows it.
+pub fn learn_spell(ecs: &mut World, learner: Entity, name: &str) -> bool {
+    let spell = match find_spell_entity(ecs, name) {
+        None => return false,
+        Some(spell) => spell
+    };
+    let mana_cost = ecs.read_storage::<SpellTemplate>().get(spell).unwrap().mana_cost;
+    let is_player = learner == *ecs.fetch::<Entity>();
+    let mut known_spells = ecs.write_storage::<KnownSpells>();
+    let mut log = ecs.fetch_mut::<GameLog>();
+
+    if let Some(known) = known_spells.get_mut(learner) {
+        if known.spells.iter().any(|s| s.display_name == name) {
+            if is_player {
+                log.entries.push(format!("You already know {}.", name));
+            }
+        } else {
+            known.spells.push(KnownSpell { display_name: name.to_string(), mana_cost });
+            if is_player {
+                log.entries.push(format!("You learn the spell {}!", name));
+            }
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,137 @@
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+use crate::{StatusEffect, Duration, DamageOverTime, HealOverTime, Slow, AttributeBonus, Confusion, StatusEffectTemplate,
+            EquipmentChanged, MyTurn, RunState, SerializeMe, DamageType};
+use crate::effects::{add_effect, EffectType, Targets};
+use super::{Name, gamelog::GameLog};
+
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, StatusEffect>,
+        WriteStorage<'a, Duration>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, MyTurn>,
+        ReadStorage<'a, DamageOverTime>,
+        ReadStorage<'a, HealOverTime>,
+        ReadStorage<'a, Slow>,
+        ReadStorage<'a, AttributeBonus>,
+        WriteStorage<'a, EquipmentChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            runstate,
+            mut log,
+            statuses,
+            mut durations,
+            names,
+            turns,
+            damage_over_time,
+            heal_over_time,
+            slows,
+            attribute_bonuses,
+            mut dirty,
+        ) = data;
+
+        match *runstate {
+            RunState::Ticking | RunState::AwaitingInput => {}
+            _ => return
+        }
+
+        let mut expired: Vec<Entity> = Vec::new();
+        for (effect_entity, status, duration) in (&entities, &statuses, &mut durations).join() {
+            // Remove effects whose target has died
+            if !entities.is_alive(status.target) {
+                expired.push(effect_entity);
+                continue;
+            }
+
+            // Effects only advance on their target's turn
+            if turns.get(status.target).is_none() { continue; }
+
+            if let Some(dot) = damage_over_time.get(effect_entity) {
+                add_effect(None, EffectType::Damage { amount: dot.damage, damage_type: Some(DamageType::Poison) }, Targets::Single { target: status.target });
+            }
+            if let Some(hot) = heal_over_time.get(effect_entity) {
+                add_effect(None, EffectType::Healing { amount: hot.heal }, Targets::Single { target: status.target });
+            }
+
+            duration.turns -= 1;
+            if duration.turns < 1 {
+                expired.push(effect_entity);
+                if status.target == *player_entity {
+                    if let Some(name) = names.get(effect_entity) {
+                        log.entries.push(format!("You are no longer {}.", name.name.to_lowercase()));
+                    }
+                }
+                if slows.get(effect_entity).is_some() || attribute_bonuses.get(effect_entity).is_some() {
+                    dirty.insert(status.target, EquipmentChanged {}).expect("Unable to insert");
+                }
+            }
+        }
+
+        for effect in expired.iter() {
+            entities.delete(*effect).expect("Unable to delete status effect");
+        }
+    }
+}
+
+/// Creates a status effect entity on `target`. Effects that don't stack refresh the
+/// duration of an existing effect with the same name instead of adding a second copy.
+pub fn add_status_effect(ecs: &mut World, target: Entity, status: &StatusEffectTemplate) {
+    if !ecs.entities().is_alive(target) { return; }
+
+    if !status.stacking {
+        let mut existing: Option<Entity> = None;
+        {
+            let entities = ecs.entities();
+            let statuses = ecs.read_storage::<StatusEffect>();
+            let names = ecs.read_storage::<Name>();
+            for (effect_entity, effect, name) in (&entities, &statuses, &names).join() {
+                if effect.target == target && name.name == status.name {
+                    existing = Some(effect_entity);
+                }
+            }
+        }
+        if let Some(existing) = existing {
+            if let Some(duration) = ecs.write_storage::<Duration>().get_mut(existing) {
+                duration.turns = i32::max(duration.turns, status.turns);
+            }
+            return;
+        }
+    }
+
+    let mut eb = ecs.create_entity()
+        .with(StatusEffect { target })
+        .with(Duration { turns: status.turns })
+        .with(Name { name: status.name.clone() });
+    if status.confusion {
+        eb = eb.with(Confusion {});
+    }
+    if status.damage_over_time != 0 {
+        eb = eb.with(DamageOverTime { damage: status.damage_over_time });
+    }
+    if status.heal_over_time != 0 {
+        eb = eb.with(HealOverTime { heal: status.heal_over_time });
+    }
+    if status.initiative_penalty != 0 {
+        eb = eb.with(Slow { initiative_penalty: status.initiative_penalty });
+    }
+    if let Some(attributes) = &status.attributes {
+        eb = eb.with(attributes.clone());
+    }
+    eb.marked::<SimpleMarker<SerializeMe>>().build();
+
+    if status.initiative_penalty != 0 || status.attributes.is_some() {
+        ecs.write_storage::<EquipmentChanged>().insert(target, EquipmentChanged {}).expect("Unable to insert");
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use rltk::{Algorithm2D, BaseMap, Point, SmallVec};
+use specs::prelude::*;
+use crate::{Map, TileType, Position, Hidden, EntryTrigger, Item, Consumable, Ammunition, Equippable, Name, WantsToPickupItem,
+    Viewshed, RunState, tile_walkable, tile_cost};
+use crate::player::{try_move_player, visible_hostiles};
+
+/// Which items auto-explore picks up on its way.
+pub struct AutoPickup {
+    pub consumables: bool,
+    pub ammunition: bool,
+    pub equipment: bool,
+}
+
+impl Default for AutoPickup {
+    fn default() -> AutoPickup {
+        AutoPickup { consumables: true, ammunition: true, equipment: false }
+    }
+}
+
+impl AutoPickup {
+    fn wants(&self, ecs: &World, item: Entity) -> bool {
+        if ecs.read_storage::<Ammunition>().get(item).is_some() {
+            self.ammunition
+        } else if ecs.read_storage::<Consumable>().get(item).is_some() {
+            self.consumables
+        } else if ecs.read_storage::<Equippable>().get(item).is_some() {
+            self.equipment
+        } else {
+            false
+        }
+    }
+}
+
+/// The map as the player knows it: revealed, walkable tiles that aren't known traps. Closed doors
+/// count as passable, since walking into one opens it. Stairs are avoided unless they are the
+/// destination, as stepping onto them changes level.
+pub struct KnownMap<'a> {
+    map: &'a Map,
+    traps: HashSet<usize>,
+    destination: Option<usize>,
+}
+
+impl<'a> KnownMap<'a> {
+    pub fn new(ecs: &World, map: &'a Map, destination: Option<usize>) -> KnownMap<'a> {
+        let positions = ecs.read_storage::<Position>();
+        let triggers = ecs.read_storage::<EntryTrigger>();
+        let hidden = ecs.read_storage::<Hidden>();
+        let traps = (&positions, &triggers, !&hidden).join()
+            .map(|(pos, _trigger, _hidden)| map.xy_index(pos.x, pos.y))
+            .collect();
+        KnownMap { map, traps, destination }
+    }
+
+    pub fn is_passable(&self, index: usize) -> bool {
+        let tile = self.map.tiles[index];
+        self.map.revealed_tiles[index]
+            && tile_walkable(tile)
+            && !self.traps.contains(&index)
+            && (!matches!(tile, TileType::DownStairs | TileType::UpStairs) || self.destination == Some(index))
+    }
+
+    fn in_interior(&self, x: i32, y: i32) -> bool {
+        x > 0 && x < self.map.width - 1 && y > 0 && y < self.map.height - 1
+    }
+
+    /// Unrevealed tiles next to somewhere the player can walk.
+    fn frontier(&self) -> Vec<usize> {
+        let mut frontier = Vec::new();
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let index = self.map.xy_index(x, y);
+                if self.map.revealed_tiles[index] { continue; }
+                let borders_known = (-1..=1).any(|dy| (-1..=1).any(|dx| {
+                    self.in_interior(x + dx, y + dy) && self.is_passable(self.map.xy_index(x + dx, y + dy))
+                }));
+                if borders_known {
+                    frontier.push(index);
+                }
+            }
+        }
+        frontier
+    }
+}
+
+impl<'a> Algorithm2D for KnownMap<'a> {
+    fn dimensions(&self) -> Point {
+        Point::new(self.map.width, self.map.height)
+    }
+}
+
+impl<'a> BaseMap for KnownMap<'a> {
+    fn is_opaque(&self, index: usize) -> bool {
+        self.map.is_opaque(index)
+    }
+
+    fn get_available_exits(&self, index: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = SmallVec::new();
+        let x = index as i32 % self.map.width;
+        let y = index as i32 / self.map.width;
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)] {
+            if !self.in_interior(x + dx, y + dy) { continue; }
+            let exit = self.map.xy_index(x + dx, y + dy);
+            if self.is_passable(exit) {
+                let cost = tile_cost(self.map.tiles[exit]);
+                exits.push((exit, if dx != 0 && dy != 0 { cost * 1.45 } else { cost }));
+            }
+        }
+        exits
+    }
+
+    fn get_pathing_distance(&self, index1: usize, index2: usize) -> f32 {
+        self.map.get_pathing_distance(index1, index2)
+    }
+}
+
+/// Floor items the player can see, and whether auto-explore wants each of them.
+fn visible_items(ecs: &World) -> Vec<(Entity, bool)> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let rules = ecs.fetch::<AutoPickup>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let visible: HashSet<Point> = viewsheds.get(player_entity).map_or(HashSet::new(), |viewshed| viewshed.visible_tiles.iter().copied().collect());
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let items = ecs.read_storage::<Item>();
+    let hidden = ecs.read_storage::<Hidden>();
+    (&entities, &positions, &items, !&hidden).join()
+        .filter(|(_entity, pos, _item, _hidden)| visible.contains(&Point::new(pos.x, pos.y)))
+        .map(|(entity, _pos, _item, _hidden)| (entity, rules.wants(ecs, entity)))
+        .collect()
+}
+
+/// How many floor items in view auto-explore would walk past.
+pub fn unwanted_items_in_view(ecs: &World) -> i32 {
+    visible_items(ecs).iter().filter(|(_item, wanted)| !wanted).count() as i32
+}
+
+/// Why auto-explore or travel stopped, and the state to carry on in if trying still took the
+/// player's turn (bumping into something unseen, for instance).
+pub struct Interruption {
+    pub reason: String,
+    pub state: Option<RunState>,
+}
+
+impl From<String> for Interruption {
+    fn from(reason: String) -> Interruption {
+        Interruption { reason, state: None }
+    }
+}
+
+/// Takes one auto-explore turn: picking up a wanted item underfoot, otherwise stepping towards
+/// the nearest wanted item or unrevealed tile. `items_in_view` is how many unwanted items were in
+/// sight last turn. Returns the new count, or why exploring stopped.
+pub fn explore(ecs: &mut World, items_in_view: i32) -> Result<i32, Interruption> {
+    if visible_hostiles(ecs) > 0 {
+        return Err("You see an enemy!".to_string().into());
+    }
+    let items = visible_items(ecs);
+    let unwanted: Vec<Entity> = items.iter().filter(|(_item, wanted)| !wanted).map(|(item, _wanted)| *item).collect();
+    if unwanted.len() as i32 > items_in_view {
+        let name = ecs.read_storage::<Name>().get(*unwanted.last().unwrap()).map(|name| name.name.clone());
+        let display_name = ecs.fetch::<crate::map::MasterDungeonMap>().display_name(&name.unwrap_or_default());
+        return Err(format!("You see {}.", display_name).into());
+    }
+
+    let player_entity = *ecs.fetch::<Entity>();
+    let player_pos = *ecs.fetch::<Point>();
+    let wanted: Vec<(Entity, Point)> = {
+        let positions = ecs.read_storage::<Position>();
+        items.iter().filter(|(_item, wanted)| *wanted)
+            .filter_map(|(item, _wanted)| positions.get(*item).map(|pos| (*item, Point::new(pos.x, pos.y))))
+            .collect()
+    };
+    if let Some((item, _pos)) = wanted.iter().find(|(_item, pos)| *pos == player_pos) {
+        let mut pickup = ecs.write_storage::<WantsToPickupItem>();
+        pickup.insert(player_entity, WantsToPickupItem { collected_by: player_entity, item: *item }).expect("Unable to insert want to pickup");
+        return Ok(unwanted.len() as i32);
+    }
+
+    let step = {
+        let map = ecs.fetch::<Map>();
+        let known = KnownMap::new(ecs, &map, None);
+        let mut targets = known.frontier();
+        targets.extend(wanted.iter().map(|(_item, pos)| map.xy_index(pos.x, pos.y)));
+        if targets.is_empty() {
+            return Err("There is nothing left to explore.".to_string().into());
+        }
+        let player_index = map.xy_index(player_pos.x, player_pos.y);
+        let dijkstra = rltk::DijkstraMap::new(map.width as usize, map.height as usize, &targets, &known, 1000.0);
+        let exit = rltk::DijkstraMap::find_lowest_exit(&dijkstra, player_index, &known)
+            .filter(|exit| dijkstra.map[*exit] < f32::MAX)
+            .ok_or_else(|| "There is nothing left to explore.".to_string())?;
+        if dijkstra.map[exit] >= dijkstra.map[player_index] {
+            return Err("You can't find a way forward.".to_string().into());
+        }
+        (exit as i32 % map.width - player_pos.x, exit as i32 / map.width - player_pos.y)
+    };
+    step_towards(ecs, step).map(|_| unwanted.len() as i32)
+}
+
+/// The steps of the shortest known route from the player to `destination`, not counting where
+/// the player stands; empty if there isn't one.
+pub fn route(ecs: &World, destination: usize) -> Vec<usize> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = *ecs.fetch::<Point>();
+    let known = KnownMap::new(ecs, &map, Some(destination));
+    if !known.is_passable(destination) {
+        return Vec::new();
+    }
+    let path = rltk::a_star_search(map.xy_index(player_pos.x, player_pos.y), destination, &known);
+    if path.success { path.steps.into_iter().skip(1).collect() } else { Vec::new() }
+}
+
+/// Takes one step of the known route to `destination`. Returns the state the step left the game
+/// in (stepping onto stairs changes level), or why travel stopped.
+pub fn travel(ecs: &mut World, destination: usize) -> Result<RunState, Interruption> {
+    let next = *route(ecs, destination).first().ok_or_else(|| "You don't know a way there.".to_string())?;
+    let player_pos = *ecs.fetch::<Point>();
+    let width = ecs.fetch::<Map>().width;
+    step_towards(ecs, (next as i32 % width - player_pos.x, next as i32 / width - player_pos.y))
+}
+
+/// Takes a step by `(delta_x, delta_y)`, or explains why the player couldn't. Anything that keeps
+/// the player in place interrupts, even if it used the turn (an attack on something unseen, or
+/// opening a door).
+fn step_towards(ecs: &mut World, (delta_x, delta_y): (i32, i32)) -> Result<RunState, Interruption> {
+    let before = *ecs.fetch::<Point>();
+    let result = try_move_player(delta_x, delta_y, ecs);
+    if *ecs.fetch::<Point>() != before {
+        Ok(if result == RunState::AwaitingInput { RunState::Ticking } else { result })
+    } else if result == RunState::Ticking {
+        Err(Interruption { reason: "Something blocks your way.".to_string(), state: Some(result) })
+    } else {
+        Err("Something is in the way.".to_string().into())
+    }
+}
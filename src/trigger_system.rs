@@ -0,0 +1,59 @@
+use specs::prelude::*;
+use crate::EntryTrigger;
+use crate::effects::{add_effect, EffectType, Targets};
+
+use super::{Map, Position, EntityMoved, Hidden, Name, gamelog::GameLog};
+
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, EntityMoved>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, EntryTrigger>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            mut entity_moved,
+            positions,
+            entry_triggers,
+            mut hidden,
+            names,
+            entities,
+            mut log,
+        ) = data;
+
+        // Find everyone who moved this turn, and what they stepped onto
+        let mut triggered: Vec<(Entity, Entity)> = Vec::new();
+        for (entity, _moved, pos) in (&entities, &entity_moved, &positions).join() {
+            let index = map.xy_index(pos.x, pos.y);
+            crate::spatial::for_each_tile_content(index, |entity_id| {
+                if entity != entity_id && entry_triggers.get(entity_id).is_some() {
+                    triggered.push((entity, entity_id));
+                }
+            });
+        }
+        entity_moved.clear();
+
+        for (victim, trap) in triggered.iter() {
+            // We triggered it
+            if let Some(name) = names.get(*trap) {
+                if let Some(victim_name) = names.get(*victim) {
+                    log.entries.push(format!("{} triggers {}!", &victim_name.name, &name.name));
+                }
+            }
+            hidden.remove(*trap); // The trap is no longer hidden
+
+            // Call the effects system
+            add_effect(None, EffectType::TriggerFire { trigger: *trap }, Targets::Single { target: *victim });
+        }
+    }
+}